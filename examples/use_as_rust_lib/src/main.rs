@@ -1,7 +1,7 @@
 use scnr::options::CommonArgs;
 
 fn main() -> anyhow::Result<()> {
-  let args = CommonArgs { input: "../../_samples".into(), ..Default::default() };
+  let args = CommonArgs { input: vec!["../../_samples".into()], ..Default::default() };
 
   let scanner = scnr::get_scanner_from_options(&args)?;
   for entry in scanner.scan()? {
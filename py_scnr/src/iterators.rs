@@ -72,6 +72,8 @@ impl JqIterator {
 #[derive(Debug, Clone)]
 pub struct ScanContent {
   pub rel_path: PathBuf,
+  /// Rendered path with the container boundaries (`a.zip!/b.txt`)
+  pub path: String,
   pub content: Content,
 }
 
@@ -85,7 +87,11 @@ pub enum Content {
 #[pymethods]
 impl ScanContent {
   fn __str__(&self) -> String {
-    format!("{} : {:?}", self.rel_path.display(), self.content)
+    format!("{} : {:?}", self.path, self.content)
+  }
+
+  fn path(&self) -> &str {
+    &self.path
   }
 
   fn json(&self) -> Option<&str> {
@@ -129,7 +135,7 @@ pub enum ContentType {
 
 impl From<ScnrScanContent> for ScanContent {
   fn from(content: ScnrScanContent) -> Self {
-    Self { rel_path: content.rel_path, content: content.content.into() }
+    Self { rel_path: content.rel_path, path: content.path.to_string(), content: content.content.into() }
   }
 }
 
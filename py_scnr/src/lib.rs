@@ -1,6 +1,7 @@
 use pyo3::prelude::*;
 use scnr::options::{CommonArgs, DEFAULT_INPUT, DEFAULT_JQ_QUERY};
-use scnr_core::ScanError;
+use scnr_core::{BinRepr, DateRepr, ScanError};
+use std::{collections::HashMap, path::PathBuf};
 
 // https://pyo3.rs/
 
@@ -34,6 +35,17 @@ fn to_scnr_cfg(cfg: Vec<(String, Plugin)>) -> Vec<(String, scnr::options::Plugin
   cfg.into_iter().map(|(pattern, plugin)| (pattern, plugin.into())).collect()
 }
 
+/// `None` keeps the representation of the profile (or the default one)
+fn to_scnr_reprs(bin_repr: Option<&str>, date_repr: Option<&str>) -> Result<(Option<BinRepr>, Option<DateRepr>), PyScnrError> {
+  let bin_repr = bin_repr.map(str::parse).transpose().map_err(ScanError::from)?;
+  let date_repr = date_repr.map(str::parse).transpose().map_err(ScanError::from)?;
+  Ok((bin_repr, date_repr))
+}
+
+fn to_scnr_opt(opts: HashMap<String, PluginOptionValue>) -> Vec<(String, String)> {
+  opts.into_iter().map(|(key, value)| (key, value.to_string())).collect()
+}
+
 fn activate_verbose(verbose: bool) {
   if verbose {
     pretty_env_logger::try_init().ok();
@@ -42,22 +54,43 @@ fn activate_verbose(verbose: bool) {
 
 #[pyfunction]
 #[allow(clippy::too_many_arguments)]
-#[pyo3(signature = (*, input = DEFAULT_INPUT.to_string(), filter=vec![], starter=vec![], cfg=vec![], profile=CfgProfile::default(), print_file_names=false, pretty_print=false, verbose=false))]
+#[pyo3(signature = (*, input = Input::One(DEFAULT_INPUT.to_string()), name=None, filter=vec![], starter=vec![], cfg=vec![], profile=Profile::default(), print_file_names=false, pretty_print=false, bin_repr=None, date_repr=None, opts=HashMap::new(), config=None, verbose=false))]
 fn scan(
-  input: String,
+  input: Input,
+  name: Option<String>,
   filter: Vec<String>,
   starter: Vec<Plugin>,
   cfg: Vec<(String, Plugin)>,
-  profile: CfgProfile,
+  profile: Profile,
   print_file_names: bool,
   pretty_print: bool,
+  bin_repr: Option<&str>,
+  date_repr: Option<&str>,
+  opts: HashMap<String, PluginOptionValue>,
+  config: Option<PathBuf>,
   verbose: bool,
 ) -> Result<ScanResultIterator, PyScnrError> {
   activate_verbose(verbose);
   let starter = to_scnr_starter(starter);
   let cfg = to_scnr_cfg(cfg);
   let profile = profile.into();
-  let common = CommonArgs { input, filter, starter, cfg, profile, print_file_names, pretty_print };
+  let (bin_repr, date_repr) = to_scnr_reprs(bin_repr, date_repr)?;
+  let opt = to_scnr_opt(opts);
+  let common = CommonArgs {
+    input: input.into_vec(),
+    name,
+    filter,
+    starter,
+    cfg,
+    profile,
+    print_file_names,
+    pretty_print,
+    bin_repr,
+    date_repr,
+    opt,
+    config,
+    ..Default::default()
+  };
   let scanner = scnr::get_scanner_from_options(&common)?;
   let result = scanner.scan()?;
   Ok(result.into())
@@ -65,23 +98,44 @@ fn scan(
 
 #[pyfunction]
 #[allow(clippy::too_many_arguments)]
-#[pyo3(signature = (*, input = DEFAULT_INPUT.to_string(), query = DEFAULT_JQ_QUERY, filter=vec![], starter=vec![], cfg=vec![], profile=CfgProfile::default(), print_file_names=false, pretty_print=false, verbose=false))]
+#[pyo3(signature = (*, input = Input::One(DEFAULT_INPUT.to_string()), name=None, query = DEFAULT_JQ_QUERY, filter=vec![], starter=vec![], cfg=vec![], profile=Profile::default(), print_file_names=false, pretty_print=false, bin_repr=None, date_repr=None, opts=HashMap::new(), config=None, verbose=false))]
 fn jq(
-  input: String,
+  input: Input,
+  name: Option<String>,
   query: &str,
   filter: Vec<String>,
   starter: Vec<Plugin>,
   cfg: Vec<(String, Plugin)>,
-  profile: CfgProfile,
+  profile: Profile,
   print_file_names: bool,
   pretty_print: bool,
+  bin_repr: Option<&str>,
+  date_repr: Option<&str>,
+  opts: HashMap<String, PluginOptionValue>,
+  config: Option<PathBuf>,
   verbose: bool,
 ) -> Result<JqIterator, PyScnrError> {
   activate_verbose(verbose);
   let starter = to_scnr_starter(starter);
   let cfg = to_scnr_cfg(cfg);
   let profile = profile.into();
-  let common = CommonArgs { input, filter, starter, cfg, profile, print_file_names, pretty_print };
+  let (bin_repr, date_repr) = to_scnr_reprs(bin_repr, date_repr)?;
+  let opt = to_scnr_opt(opts);
+  let common = CommonArgs {
+    input: input.into_vec(),
+    name,
+    filter,
+    starter,
+    cfg,
+    profile,
+    print_file_names,
+    pretty_print,
+    bin_repr,
+    date_repr,
+    opt,
+    config,
+    ..Default::default()
+  };
   let scanner = scnr::get_scanner_from_options(&common)?;
   let result = scanner.scan()?;
   let iterator = JqIterator::new(result, query)?;
@@ -22,10 +22,52 @@ impl Into<scnr::options::CfgProfile> for CfgProfile {
   }
 }
 
+/// One input, or a list of inputs (labeled by their file names, or by the `label=` prefix)
+#[derive(Debug, Clone, FromPyObject)]
+pub enum Input {
+  One(String),
+  Many(Vec<String>),
+}
+
+impl Input {
+  #[must_use]
+  pub fn into_vec(self) -> Vec<String> {
+    match self {
+      Input::One(input) => vec![input],
+      Input::Many(inputs) => inputs,
+    }
+  }
+}
+
+/// A builtin profile, or the name of a profile defined in the config file
+#[derive(Debug, Clone, FromPyObject)]
+pub enum Profile {
+  Builtin(CfgProfile),
+  Named(String),
+}
+
+impl Default for Profile {
+  fn default() -> Self {
+    Profile::Builtin(CfgProfile::default())
+  }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<scnr::options::Profile> for Profile {
+  fn into(self) -> scnr::options::Profile {
+    match self {
+      Profile::Builtin(profile) => scnr::options::Profile::Builtin(profile.into()),
+      Profile::Named(name) => name.parse().unwrap_or(scnr::options::Profile::Named(name)),
+    }
+  }
+}
+
 #[pyclass(eq, eq_int)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Plugin {
   FileSystem,
+  Stdin,
+  Index,
   Json,
   Zip,
   TarGz,
@@ -43,6 +85,8 @@ impl Into<scnr::options::Plugin> for Plugin {
     use scnr::options::Plugin::*;
     match self {
       Plugin::FileSystem => FileSystem,
+      Plugin::Stdin => Stdin,
+      Plugin::Index => Index,
       Plugin::Json => Json,
       Plugin::Zip => Zip,
       Plugin::TarGz => TarGz,
@@ -55,6 +99,29 @@ impl Into<scnr::options::Plugin> for Plugin {
   }
 }
 
+/// Value of a plugin option given as python kwarg, converted to the string the plugins parse
+#[derive(Debug, Clone, FromPyObject)]
+pub enum PluginOptionValue {
+  // bool first: python booleans are also integers
+  Bool(bool),
+  Int(i64),
+  Float(f64),
+  Str(String),
+  List(Vec<PluginOptionValue>),
+}
+
+impl std::fmt::Display for PluginOptionValue {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      PluginOptionValue::Bool(b) => write!(f, "{b}"),
+      PluginOptionValue::Int(i) => write!(f, "{i}"),
+      PluginOptionValue::Float(x) => write!(f, "{x}"),
+      PluginOptionValue::Str(s) => write!(f, "{s}"),
+      PluginOptionValue::List(values) => write!(f, "{}", scnr::core::plugin_options::join_list(values.iter().map(ToString::to_string))),
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use scnr::options::{CommonArgs, DEFAULT_INPUT};
@@ -70,7 +137,18 @@ mod tests {
   fn ensure_default_options_are_same() {
     let default_args = CommonArgs::default();
 
-    assert_eq!(default_args.input, DEFAULT_INPUT.to_string(), "If this changes, change the pyfunction signatures");
-    assert_eq!(default_args.profile, CfgProfile::default().into());
+    assert_eq!(default_args.input, [DEFAULT_INPUT.to_string()], "If this changes, change the pyfunction signatures");
+    assert_eq!(default_args.profile, Profile::default().into());
+    assert!(default_args.bin_repr.is_none() && default_args.date_repr.is_none(), "If this changes, change the pyfunction signatures");
+    assert!(default_args.opt.is_empty() && default_args.config.is_none(), "If this changes, change the pyfunction signatures");
+  }
+
+  #[test]
+  fn plugin_option_values() {
+    use PluginOptionValue::*;
+    assert_eq!(Bool(true).to_string(), "true");
+    assert_eq!(Int(5000).to_string(), "5000");
+    assert_eq!(List(vec![Str("a".into()), Int(1)]).to_string(), "a,1");
+    assert_eq!(List(vec![Str("a,b".into()), Str("c".into())]).to_string(), r"a\,b,c");
   }
 }
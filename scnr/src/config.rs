@@ -0,0 +1,459 @@
+//! Configuration file (toml or yaml) of the command line and the python bindings
+//!
+//! ```toml
+//! [options]
+//! zip.passwords = ["infected", "s3cr3t"]
+//!
+//! [options.sqlite]
+//! json_array_limit = 5000
+//!
+//! [profiles.triage]
+//! inherits = "sysdiagnose"
+//! cfg = ["*.sqlite-wal=bin", "**/Preferences/*=plist"]
+//! fallback = ["json=text", "text=bin"]
+//! filter = ["*.db", "*.plist"]
+//! timestamps = "add"
+//! options = { "sqlite.json_array_limit" = 1000 }
+//! ```
+//!
+//! Profiles inherit from a builtin profile (`standard` by default) or from another profile of the file.
+//! Lists (cfg, fallbacks, starters, filters, timestamp rules) are appended to the inherited ones, the command line ones coming last.
+
+use crate::options::{parse_key_val, CfgProfile, CommonArgs, OutputFormat, Plugin, Profile, TimestampEpoch, TimestampsMode};
+use clap::ValueEnum;
+use scnr_core::{plugin_options::join_list, PluginOptions};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+};
+
+/// Default config files, looked up in `$XDG_CONFIG_HOME/scnr` (or `~/.config/scnr`)
+pub const DEFAULT_CONFIG_FILE_NAMES: [&str; 3] = ["config.toml", "config.yaml", "config.yml"];
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+  #[error("Unable to read config file `{path}`: {source}")]
+  Io { path: String, source: std::io::Error },
+  #[error("Invalid config file `{path}`: {message}")]
+  Parse { path: String, message: String },
+  #[error("Unsupported value for option `{0}`, expected a string, a number, a boolean or an array of those")]
+  UnsupportedOptionValue(String),
+  #[error("Unknown profile `{0}`, it is neither a builtin profile nor defined in the config file")]
+  UnknownProfile(String),
+  #[error("Profile `{0}` inherits from itself")]
+  ProfileCycle(String),
+  #[error("Invalid `{field}` value `{value}` in profile `{profile}`: {message}")]
+  InvalidProfileValue { profile: String, field: &'static str, value: String, message: String },
+}
+
+/// The builtin profile at the root of an inheritance chain, and the named profiles from the oldest ancestor
+type ProfileChain<'c> = (CfgProfile, Vec<(&'c str, &'c ProfileConfig)>);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+  /// Plugin options, nested tables are flattened into dotted keys (`[options.sqlite]` holds the `sqlite.*` options)
+  #[serde(default)]
+  pub options: BTreeMap<String, Value>,
+
+  #[serde(default)]
+  pub profiles: BTreeMap<String, ProfileConfig>,
+}
+
+/// A named profile, all fields are optional and the command line args take precedence
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+  /// Builtin profile (`standard`, `sysdiagnose`, `nothing`) or profile of the config file, `standard` by default
+  pub inherits: Option<String>,
+  /// `glob=plugin` entries, same as the `--cfg` args
+  #[serde(default)]
+  pub cfg: Vec<String>,
+  /// `plugin=fallback` entries, same as the `--fallback` args
+  #[serde(default)]
+  pub fallback: Vec<String>,
+  #[serde(default)]
+  pub starter: Vec<String>,
+  #[serde(default)]
+  pub filter: Vec<String>,
+  #[serde(default)]
+  pub options: BTreeMap<String, Value>,
+
+  pub print_file_names: Option<bool>,
+  pub pretty_print: Option<bool>,
+  pub output_format: Option<String>,
+  pub bin_repr: Option<String>,
+  pub date_repr: Option<String>,
+  pub timestamps: Option<String>,
+  /// `glob=epoch` entries, same as the `--timestamp-rule` args
+  #[serde(default)]
+  pub timestamp_rule: Vec<String>,
+}
+
+impl Config {
+  /// Loads a yaml file if the extension is `yaml` or `yml`, a toml file otherwise
+  pub fn load(path: &Path) -> Result<Self, ConfigError> {
+    let display = path.display().to_string();
+    let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Io { path: display.clone(), source })?;
+    let is_yaml = path
+      .extension()
+      .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"));
+    let config = if is_yaml { Self::from_yaml(&content) } else { Self::from_toml(&content) };
+    config.map_err(|message| ConfigError::Parse { path: display, message })
+  }
+
+  pub fn from_toml(content: &str) -> Result<Self, String> {
+    toml::from_str(content).map_err(|e| e.to_string())
+  }
+
+  pub fn from_yaml(content: &str) -> Result<Self, String> {
+    serde_yml::from_str(content).map_err(|e| e.to_string())
+  }
+
+  pub fn plugin_options(&self) -> Result<PluginOptions, ConfigError> {
+    to_plugin_options(&self.options)
+  }
+
+  /// Merges the config into the command line args: plugin options, and the named profile (replaced by its builtin ancestor)
+  pub fn apply(&self, mut args: CommonArgs) -> Result<CommonArgs, ConfigError> {
+    let mut options = self.plugin_options()?;
+
+    if let Profile::Named(name) = &args.profile {
+      let (builtin, chain) = self.profile_chain(name)?;
+      let mut merged = CommonArgs { profile: Profile::Builtin(builtin), ..Default::default() };
+      for (name, profile) in chain {
+        profile.merge_into(name, &mut merged, &mut options)?;
+      }
+
+      merged.cfg.append(&mut args.cfg);
+      merged.fallback.append(&mut args.fallback);
+      merged.starter.append(&mut args.starter);
+      merged.filter.append(&mut args.filter);
+      merged.timestamp_rule.append(&mut args.timestamp_rule);
+
+      args = CommonArgs {
+        profile: merged.profile,
+        cfg: merged.cfg,
+        fallback: merged.fallback,
+        starter: merged.starter,
+        filter: merged.filter,
+        timestamp_rule: merged.timestamp_rule,
+        print_file_names: args.print_file_names || merged.print_file_names,
+        pretty_print: args.pretty_print || merged.pretty_print,
+        output_format: args.output_format.or(merged.output_format),
+        bin_repr: args.bin_repr.or(merged.bin_repr),
+        date_repr: args.date_repr.or(merged.date_repr),
+        timestamps: args.timestamps.or(merged.timestamps),
+        ..args
+      };
+    }
+
+    options.extend(args.opt.iter().cloned().collect());
+    args.opt = options.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+
+    Ok(args)
+  }
+
+  /// Returns the builtin profile at the root of the inheritance chain, and the profiles from the oldest ancestor to `name`
+  fn profile_chain<'c>(&'c self, name: &'c str) -> Result<ProfileChain<'c>, ConfigError> {
+    let mut chain: Vec<(&str, &ProfileConfig)> = vec![];
+    let mut current = name;
+
+    let builtin = loop {
+      if let Ok(builtin) = CfgProfile::from_str(current, true) {
+        break builtin;
+      }
+      if chain.iter().any(|(visited, _)| *visited == current) {
+        return Err(ConfigError::ProfileCycle(current.to_string()));
+      }
+      let profile = self
+        .profiles
+        .get(current)
+        .ok_or_else(|| ConfigError::UnknownProfile(current.to_string()))?;
+      chain.push((current, profile));
+      match &profile.inherits {
+        Some(parent) => current = parent,
+        None => break CfgProfile::default(),
+      }
+    };
+
+    chain.reverse();
+    Ok((builtin, chain))
+  }
+}
+
+impl ProfileConfig {
+  fn merge_into(&self, name: &str, args: &mut CommonArgs, options: &mut PluginOptions) -> Result<(), ConfigError> {
+    let invalid = |field: &'static str, value: &str, message: String| ConfigError::InvalidProfileValue {
+      profile: name.to_string(),
+      field,
+      value: value.to_string(),
+      message,
+    };
+
+    for cfg in &self.cfg {
+      args
+        .cfg
+        .push(parse_key_val::<String, Plugin>(cfg).map_err(|e| invalid("cfg", cfg, e.to_string()))?);
+    }
+    for fallback in &self.fallback {
+      args
+        .fallback
+        .push(parse_key_val::<Plugin, Plugin>(fallback).map_err(|e| invalid("fallback", fallback, e.to_string()))?);
+    }
+    for starter in &self.starter {
+      args
+        .starter
+        .push(Plugin::from_str(starter, true).map_err(|e| invalid("starter", starter, e))?);
+    }
+    args.filter.extend(self.filter.iter().cloned());
+    for rule in &self.timestamp_rule {
+      args
+        .timestamp_rule
+        .push(parse_key_val::<String, TimestampEpoch>(rule).map_err(|e| invalid("timestamp_rule", rule, e.to_string()))?);
+    }
+    options.extend(to_plugin_options(&self.options)?);
+
+    if let Some(print_file_names) = self.print_file_names {
+      args.print_file_names = print_file_names;
+    }
+    if let Some(pretty_print) = self.pretty_print {
+      args.pretty_print = pretty_print;
+    }
+    if let Some(output_format) = &self.output_format {
+      args.output_format = Some(OutputFormat::from_str(output_format, true).map_err(|e| invalid("output_format", output_format, e))?);
+    }
+    if let Some(bin_repr) = &self.bin_repr {
+      let parsed = bin_repr
+        .parse()
+        .map_err(|e: scnr_core::bin_repr::BinReprError| invalid("bin_repr", bin_repr, e.to_string()))?;
+      args.bin_repr = Some(parsed);
+    }
+    if let Some(date_repr) = &self.date_repr {
+      let parsed = date_repr
+        .parse()
+        .map_err(|e: scnr_core::date_repr::DateReprError| invalid("date_repr", date_repr, e.to_string()))?;
+      args.date_repr = Some(parsed);
+    }
+    if let Some(timestamps) = &self.timestamps {
+      args.timestamps = Some(TimestampsMode::from_str(timestamps, true).map_err(|e| invalid("timestamps", timestamps, e))?);
+    }
+
+    Ok(())
+  }
+}
+
+/// The first existing file of [`DEFAULT_CONFIG_FILE_NAMES`] in `$XDG_CONFIG_HOME/scnr` (or `~/.config/scnr`)
+#[must_use]
+pub fn default_config_path() -> Option<PathBuf> {
+  let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+    .filter(|dir| !dir.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+  DEFAULT_CONFIG_FILE_NAMES
+    .iter()
+    .map(|name| config_dir.join("scnr").join(name))
+    .find(|path| path.is_file())
+}
+
+fn to_plugin_options(values: &BTreeMap<String, Value>) -> Result<PluginOptions, ConfigError> {
+  let mut options = PluginOptions::new();
+  for (key, value) in values {
+    flatten_option(&mut options, key, value)?;
+  }
+  Ok(options)
+}
+
+fn flatten_option(options: &mut PluginOptions, key: &str, value: &Value) -> Result<(), ConfigError> {
+  if let Value::Object(map) = value {
+    for (sub_key, value) in map {
+      flatten_option(options, &format!("{key}.{sub_key}"), value)?;
+    }
+    return Ok(());
+  }
+  options.set(key, option_value_to_string(key, value)?);
+  Ok(())
+}
+
+/// Plugin options are strings, arrays are joined with commas (escaped in the values, see [`join_list`])
+fn option_value_to_string(key: &str, value: &Value) -> Result<String, ConfigError> {
+  Ok(match value {
+    Value::String(s) => s.clone(),
+    Value::Number(n) => n.to_string(),
+    Value::Bool(b) => b.to_string(),
+    Value::Array(values) => join_list(
+      values
+        .iter()
+        .map(|value| match value {
+          Value::Array(_) | Value::Object(_) => Err(ConfigError::UnsupportedOptionValue(key.to_string())),
+          value => option_value_to_string(key, value),
+        })
+        .collect::<Result<Vec<_>, _>>()?,
+    ),
+    Value::Null | Value::Object(_) => return Err(ConfigError::UnsupportedOptionValue(key.to_string())),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+  use scnr_core::BinRepr;
+
+  const TOML_CONFIG: &str = r#"
+    [options]
+    zip.passwords = ["infected", "s3cr3t"]
+    "xml.attribute_prefix" = "_"
+
+    [options.sqlite]
+    json_array_limit = 100
+
+    [profiles.apple]
+    inherits = "sysdiagnose"
+    cfg = ["*.sqlite-wal=bin"]
+    filter = ["*.db"]
+    bin_repr = "hex"
+    options = { "sqlite.json_array_limit" = 10 }
+
+    [profiles.triage]
+    inherits = "apple"
+    cfg = ["**/Preferences/*=plist"]
+    starter = ["file-system"]
+    filter = ["*.plist"]
+    pretty_print = true
+    output_format = "jsonl"
+    timestamps = "add"
+    timestamp_rule = ["Z*DATE=cocoa"]
+
+    [profiles.loop_a]
+    inherits = "loop_b"
+
+    [profiles.loop_b]
+    inherits = "loop_a"
+  "#;
+
+  fn args(profile: &str, cli: &[&str]) -> CommonArgs {
+    CommonArgs { profile: profile.parse().unwrap(), cfg: cli.iter().map(|cfg| parse_key_val(cfg).unwrap()).collect(), ..Default::default() }
+  }
+
+  #[test]
+  fn options_are_flattened() -> anyhow::Result<()> {
+    let config = Config::from_toml(TOML_CONFIG).map_err(anyhow::Error::msg)?;
+
+    let options = config.plugin_options()?;
+    assert_eq!(
+      options.iter().collect::<Vec<_>>(),
+      [("sqlite.json_array_limit", "100"), ("xml.attribute_prefix", "_"), ("zip.passwords", "infected,s3cr3t")]
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn nested_arrays_are_refused() -> anyhow::Result<()> {
+    let config = Config::from_toml("options.zip.passwords = [[\"a\"]]").map_err(anyhow::Error::msg)?;
+    assert!(matches!(config.plugin_options(), Err(ConfigError::UnsupportedOptionValue(key)) if key == "zip.passwords"));
+    Ok(())
+  }
+
+  #[test]
+  fn list_values_keep_their_commas() -> anyhow::Result<()> {
+    let config = Config::from_toml(r#"options.zip.passwords = ["a,b", "c"]"#).map_err(anyhow::Error::msg)?;
+    assert_eq!(config.plugin_options()?.get_list("zip.passwords"), ["a,b", "c"]);
+    Ok(())
+  }
+
+  #[test]
+  fn profiles_are_inherited() -> anyhow::Result<()> {
+    let config = Config::from_toml(TOML_CONFIG).map_err(anyhow::Error::msg)?;
+
+    let mut cli_args = args("triage", &["*.json=text"]);
+    cli_args.opt = vec![("zip.passwords".into(), "cli".into())];
+    let args = config.apply(cli_args)?;
+
+    assert_eq!(args.profile, Profile::Builtin(CfgProfile::Sysdiagnose));
+    assert_eq!(
+      args.cfg,
+      [("*.sqlite-wal".into(), Plugin::Bin), ("**/Preferences/*".into(), Plugin::Plist), ("*.json".into(), Plugin::Text)]
+    );
+    assert_eq!(args.starter, [Plugin::FileSystem]);
+    assert_eq!(args.filter, ["*.db", "*.plist"]);
+    assert_eq!(args.bin_repr(), BinRepr::Hex);
+    assert!(args.pretty_print);
+    assert!(!args.print_file_names);
+    assert_eq!(args.output_format(), OutputFormat::Jsonl);
+    assert_eq!(args.timestamps(), TimestampsMode::Add);
+    assert_eq!(args.timestamp_rule, [("Z*DATE".into(), TimestampEpoch::Cocoa)]);
+    assert_eq!(
+      args.opt,
+      [
+        ("sqlite.json_array_limit".into(), "10".into()),
+        ("xml.attribute_prefix".into(), "_".into()),
+        ("zip.passwords".into(), "cli".into())
+      ]
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn explicit_defaults_override_profiles() -> anyhow::Result<()> {
+    let config = Config::from_toml(TOML_CONFIG).map_err(anyhow::Error::msg)?;
+
+    let mut cli_args = args("triage", &[]);
+    cli_args.bin_repr = Some(BinRepr::default());
+    cli_args.timestamps = Some(TimestampsMode::Off);
+    let args = config.apply(cli_args)?;
+
+    assert_eq!((args.bin_repr, args.timestamps), (Some(BinRepr::Base64), Some(TimestampsMode::Off)));
+    assert_eq!(args.output_format(), OutputFormat::Jsonl);
+    assert_eq!(args.date_repr, None);
+
+    Ok(())
+  }
+
+  #[test]
+  fn yaml_profiles() -> anyhow::Result<()> {
+    let config = Config::from_yaml(
+      r"
+      profiles:
+        quick:
+          inherits: nothing
+          starter: [file-system]
+          cfg: ['*.json=json']
+      ",
+    )
+    .map_err(anyhow::Error::msg)?;
+
+    let args = config.apply(args("quick", &[]))?;
+    assert_eq!(args.profile, Profile::Builtin(CfgProfile::Nothing));
+    assert_eq!(args.cfg, [("*.json".into(), Plugin::Json)]);
+    assert_eq!(args.starter, [Plugin::FileSystem]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn builtin_profiles_are_untouched() -> anyhow::Result<()> {
+    let config = Config::from_toml(TOML_CONFIG).map_err(anyhow::Error::msg)?;
+    let args = config.apply(args("sysdiagnose", &["*.json=text"]))?;
+    assert_eq!(args.profile, Profile::Builtin(CfgProfile::Sysdiagnose));
+    assert_eq!(args.cfg, [("*.json".into(), Plugin::Text)]);
+    assert_eq!(args.filter, Vec::<String>::new());
+    Ok(())
+  }
+
+  #[test]
+  fn invalid_profiles() -> anyhow::Result<()> {
+    let config = Config::from_toml(TOML_CONFIG).map_err(anyhow::Error::msg)?;
+    assert!(matches!(config.apply(args("unknown", &[])), Err(ConfigError::UnknownProfile(name)) if name == "unknown"));
+    assert!(matches!(config.apply(args("loop_a", &[])), Err(ConfigError::ProfileCycle(_))));
+
+    let config = Config::from_toml("[profiles.bad]\ncfg = [\"*.json=nope\"]").map_err(anyhow::Error::msg)?;
+    assert!(matches!(config.apply(args("bad", &[])), Err(ConfigError::InvalidProfileValue { field: "cfg", .. })));
+    Ok(())
+  }
+}
@@ -0,0 +1,799 @@
+//! Writing of the converted contents by `scnr extract`: in a directory, or straight into a `.zip` / `.tar.gz` archive.
+//! The contents are written one by one as they are scanned, under their path made of the unescaped segment names
+//! (`a.zip/b.db/users` for `a.zip!/b.db#users`) stripped of its `..` and root components. A path already taken by a file
+//! or by a directory (the parts of a split table, a zip with both `db` and `db/table` members, a real `a.zip` directory
+//! next to the `a.zip` archive...) is renamed with a `~2` suffix, or skipped, or fails the extraction ([`CollisionPolicy`]).
+//! The extraction comes with a chain of custody report: `manifest.jsonl` starts with the run parameters ([`RunInfo`]), then
+//! lists every written file with its source and the sizes and hashes of the original and written bytes, and every skipped
+//! content and scan error ([`ManifestEntry`], also written in `manifest.csv`), and ends with the end date.
+//! The manifest of an output directory is written as the extraction goes, the one of an archive is added at its end.
+//! A failed extraction still writes it, with the failure as last error entry.
+
+use crate::options::{ExtractArgs, Profile};
+use clap::ValueEnum;
+use flate2::{write::GzEncoder, Compression};
+pub use scnr_core::scan_path::sanitize_path;
+use scnr_core::{date_repr::DateReprError, listing::short_plugin_name, Content, DateRepr, ScanContent, ScanError};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+  collections::{HashMap, HashSet},
+  fs::File,
+  io::Write,
+  path::{Path, PathBuf},
+  time::SystemTime,
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// Name of the manifest written at the root of the output
+pub const MANIFEST_FILE_NAME: &str = "manifest.jsonl";
+/// Same entries as the manifest, without the run parameters
+pub const MANIFEST_CSV_FILE_NAME: &str = "manifest.csv";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExtractError {
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Zip error: {0}")]
+  Zip(#[from] zip::result::ZipError),
+  #[error("Json error: {0}")]
+  Json(#[from] serde_json::Error),
+  #[error("Csv error: {0}")]
+  Csv(#[from] csv::Error),
+  #[error("Date error: {0}")]
+  Date(#[from] DateReprError),
+  #[error("`{0}` is already taken in the output")]
+  Collision(String),
+}
+
+/// Parameters of the extraction, first line of the manifest
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct RunInfo {
+  pub scnr_version: String,
+  pub command_line: Vec<String>,
+  pub inputs: Vec<String>,
+  /// The profile asked for, a builtin one or a profile of the config file
+  pub profile: String,
+  pub config: Option<String>,
+  pub config_sha256: Option<String>,
+  /// `glob=plugin` rules
+  pub cfg: Vec<String>,
+  /// `plugin=fallback` rules
+  pub fallbacks: Vec<String>,
+  pub filters: Vec<String>,
+  /// `key=value` plugin options
+  pub options: Vec<String>,
+  pub output: String,
+  pub raw: bool,
+  pub on_collision: String,
+  pub started_at: String,
+}
+
+impl RunInfo {
+  /// The common args should be resolved first (profile and options of the config file, see
+  /// [`crate::resolve_common_args`]), `profile` is the one given before their resolution.
+  /// The start date is set by the [`Extractor`].
+  pub fn from_args(args: &ExtractArgs, profile: &Profile, command_line: Vec<String>) -> Result<Self, ExtractError> {
+    let plugin_name = |plugin: &crate::options::Plugin| {
+      plugin
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+    };
+    let common = &args.common;
+    let config_sha256 = match &common.config {
+      Some(config) => Some(format!("{:x}", Sha256::digest(std::fs::read(config)?))),
+      None => None,
+    };
+    Ok(Self {
+      scnr_version: env!("CARGO_PKG_VERSION").to_string(),
+      command_line,
+      inputs: common.input.clone(),
+      profile: profile.to_string(),
+      config: common.config.as_ref().map(|config| config.display().to_string()),
+      config_sha256,
+      cfg: common
+        .cfg
+        .iter()
+        .map(|(glob, plugin)| format!("{glob}={}", plugin_name(plugin)))
+        .collect(),
+      fallbacks: common
+        .fallback
+        .iter()
+        .map(|(plugin, fallback)| format!("{}={}", plugin_name(plugin), plugin_name(fallback)))
+        .collect(),
+      filters: common.filter.clone(),
+      options: common.opt.iter().map(|(key, value)| format!("{key}={value}")).collect(),
+      output: args.output.display().to_string(),
+      raw: args.raw,
+      on_collision: args.on_collision.to_string(),
+      started_at: String::new(),
+    })
+  }
+}
+
+#[derive(Serialize)]
+struct RunLine<'r> {
+  #[serde(rename = "type")]
+  kind: &'static str,
+  #[serde(flatten)]
+  run: &'r RunInfo,
+}
+
+/// Last line of the manifest
+#[derive(Serialize)]
+struct EndLine {
+  #[serde(rename = "type")]
+  kind: &'static str,
+  finished_at: String,
+  files: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+  File,
+  /// Content not written, its path was already taken
+  Skipped,
+  /// Scan error, or failure of the extraction (without source)
+  Error,
+}
+
+/// One line of the manifest
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManifestEntry {
+  #[serde(rename = "type")]
+  pub kind: EntryKind,
+  /// Path of the file in the output
+  pub path: Option<String>,
+  /// Nested path of the content (`a.zip!/b.db#users`)
+  pub source: Option<String>,
+  pub plugin: Option<String>,
+  /// The file holds the original bytes of the node (`--raw`)
+  pub raw: bool,
+  /// Size and sha256 of the original bytes of the node the content comes from
+  pub original_size: Option<u64>,
+  pub original_sha256: Option<String>,
+  /// Size and sha256 of the written bytes
+  pub size: Option<usize>,
+  pub sha256: Option<String>,
+  pub extracted_at: String,
+  pub error: Option<String>,
+}
+
+/// Is the output path an archive to write into (`.zip`, `.tar.gz` or `.tgz`), rather than a directory
+#[must_use]
+pub fn is_archive_output(output: &Path) -> bool {
+  archive_kind(output).is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArchiveKind {
+  Zip,
+  TarGz,
+}
+
+fn archive_kind(output: &Path) -> Option<ArchiveKind> {
+  let name = PathBuf::from(output.file_name()?.to_ascii_lowercase());
+  let is_tar = |stem: &Path| stem.extension().is_some_and(|extension| extension == "tar");
+  match name.extension()?.to_str()? {
+    "zip" => Some(ArchiveKind::Zip),
+    "tgz" => Some(ArchiveKind::TarGz),
+    "gz" if is_tar(Path::new(name.file_stem()?)) => Some(ArchiveKind::TarGz),
+    _ => None,
+  }
+}
+
+enum ExtractSink {
+  Directory(PathBuf),
+  Zip(Box<ZipWriter<File>>),
+  TarGz(tar::Builder<GzEncoder<File>>),
+}
+
+impl ExtractSink {
+  fn write(&mut self, path: &Path, data: &[u8]) -> Result<(), ExtractError> {
+    match self {
+      ExtractSink::Directory(root) => {
+        let extract_path = root.join(path);
+        if let Some(extract_dir) = extract_path.parent() {
+          if !extract_dir.exists() {
+            tracing::debug!("Creating folder {extract_dir}", extract_dir = extract_dir.display());
+            std::fs::create_dir_all(extract_dir)?;
+          }
+        }
+        File::create(extract_path)?.write_all(data)?;
+      }
+      ExtractSink::Zip(zip) => {
+        let options = SimpleFileOptions::default()
+          .compression_method(CompressionMethod::Deflated)
+          .unix_permissions(0o644)
+          .large_file(u32::try_from(data.len()).is_err());
+        zip.start_file(archive_name(path), options)?;
+        zip.write_all(data)?;
+      }
+      ExtractSink::TarGz(tar) => {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(
+          SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs()),
+        );
+        tar.append_data(&mut header, archive_name(path), data)?;
+      }
+    }
+    Ok(())
+  }
+
+  fn finish(self) -> Result<(), ExtractError> {
+    match self {
+      ExtractSink::Directory(_) => {}
+      ExtractSink::Zip(zip) => {
+        zip.finish()?;
+      }
+      ExtractSink::TarGz(tar) => {
+        tar.into_inner()?.finish()?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Archive member names always use `/`
+fn archive_name(path: &Path) -> String {
+  path
+    .components()
+    .map(|component| component.as_os_str().to_string_lossy())
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+/// The manifest lines, written in the files of an output directory as they come, kept until the end for an archive
+enum ManifestWriter {
+  Files(File, csv::Writer<File>),
+  Buffers(Vec<u8>, csv::Writer<Vec<u8>>),
+}
+
+impl ManifestWriter {
+  fn line(&mut self, line: &impl Serialize) -> Result<(), ExtractError> {
+    let mut data = serde_json::to_vec(line)?;
+    data.push(b'\n');
+    match self {
+      ManifestWriter::Files(jsonl, _) => jsonl.write_all(&data)?,
+      ManifestWriter::Buffers(jsonl, _) => jsonl.append(&mut data),
+    }
+    Ok(())
+  }
+
+  fn entry(&mut self, entry: &ManifestEntry) -> Result<(), ExtractError> {
+    self.line(entry)?;
+    match self {
+      ManifestWriter::Files(_, csv) => {
+        csv.serialize(entry)?;
+        csv.flush()?;
+      }
+      ManifestWriter::Buffers(_, csv) => csv.serialize(entry)?,
+    }
+    Ok(())
+  }
+
+  /// Writes the kept lines in the archive
+  fn finish(self, sink: &mut ExtractSink) -> Result<(), ExtractError> {
+    match self {
+      ManifestWriter::Files(..) => {}
+      ManifestWriter::Buffers(jsonl, csv) => {
+        sink.write(Path::new(MANIFEST_FILE_NAME), &jsonl)?;
+        sink.write(Path::new(MANIFEST_CSV_FILE_NAME), &csv.into_inner().map_err(csv::IntoInnerError::into_error)?)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// What to do with a content whose path is already taken, by a file or by a directory
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum CollisionPolicy {
+  /// Adds a `~2`, `~3`... suffix to the colliding file or directory name
+  #[default]
+  Rename,
+  /// The content is not written
+  Skip,
+  /// The extraction fails
+  Error,
+}
+
+impl std::fmt::Display for CollisionPolicy {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let dbg = format!("{self:?}").to_lowercase();
+    write!(f, "{dbg}")
+  }
+}
+
+/// Gives every written file a sanitized path that is neither an already written file nor the directory of one
+#[derive(Debug, Default)]
+pub struct ExtractPaths {
+  files: HashSet<PathBuf>,
+  dirs: HashSet<PathBuf>,
+  /// Directory of the output of every source directory, all the children of a renamed directory go in the same one
+  source_dirs: HashMap<PathBuf, PathBuf>,
+}
+
+impl ExtractPaths {
+  /// The path will not be given to a content (e.g. the manifest)
+  pub fn reserve(&mut self, path: &Path) {
+    self.files.insert(path.to_path_buf());
+  }
+
+  /// Returns `None` when the path is taken and the policy skips the content
+  pub fn allocate(&mut self, path: &Path, policy: CollisionPolicy) -> Result<Option<PathBuf>, ExtractError> {
+    let path = sanitize_path(path);
+    let mut source = PathBuf::new();
+    let mut allocated = PathBuf::new();
+    let mut new_dirs = vec![];
+    let mut components = path.components().peekable();
+
+    while let Some(component) = components.next() {
+      source.push(component);
+      let wanted = allocated.join(component);
+      let resolved = if components.peek().is_none() {
+        resolve_collision(wanted, policy, |path| self.files.contains(path) || self.dirs.contains(path))?
+      } else if let Some(dir) = self.source_dirs.get(&source) {
+        Some(dir.clone())
+      } else {
+        let dir = resolve_collision(wanted, policy, |path| self.files.contains(path))?;
+        new_dirs.extend(dir.clone().map(|dir| (source.clone(), dir)));
+        dir
+      };
+      let Some(resolved) = resolved else { return Ok(None) };
+      allocated = resolved;
+    }
+
+    for (source, dir) in new_dirs {
+      self.dirs.insert(dir.clone());
+      self.source_dirs.insert(source, dir);
+    }
+    self.files.insert(allocated.clone());
+    Ok(Some(allocated))
+  }
+}
+
+fn resolve_collision(path: PathBuf, policy: CollisionPolicy, is_taken: impl Fn(&Path) -> bool) -> Result<Option<PathBuf>, ExtractError> {
+  if !is_taken(&path) {
+    return Ok(Some(path));
+  }
+  match policy {
+    CollisionPolicy::Rename => Ok(Some(free_path(&path, is_taken))),
+    CollisionPolicy::Skip => Ok(None),
+    CollisionPolicy::Error => Err(ExtractError::Collision(path.display().to_string())),
+  }
+}
+
+/// The first free one of `path~2`, `path~3`...
+fn free_path(path: &Path, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+  let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+  let mut n = 2;
+  loop {
+    let candidate = path.with_file_name(format!("{name}~{n}"));
+    if !is_taken(&candidate) {
+      return candidate;
+    }
+    n += 1;
+  }
+}
+
+/// Writes the converted contents in a directory or an archive, and their manifest
+pub struct Extractor {
+  output: PathBuf,
+  sink: ExtractSink,
+  paths: ExtractPaths,
+  collisions: CollisionPolicy,
+  raw: bool,
+  run: RunInfo,
+  date_repr: DateRepr,
+  started: SystemTime,
+  manifest: ManifestWriter,
+  /// The run line starts the manifest, it is written before the first entry
+  run_written: bool,
+  files: usize,
+}
+
+impl Extractor {
+  /// Writes in an archive if the output is one (see [`is_archive_output`]), else in a directory (which is created)
+  pub fn create(output: &Path) -> Result<Self, ExtractError> {
+    let (sink, manifest) = if let Some(kind) = archive_kind(output) {
+      let file = File::create(output)?;
+      let sink = match kind {
+        ArchiveKind::Zip => ExtractSink::Zip(Box::new(ZipWriter::new(file))),
+        ArchiveKind::TarGz => ExtractSink::TarGz(tar::Builder::new(GzEncoder::new(file, Compression::default()))),
+      };
+      (sink, ManifestWriter::Buffers(vec![], csv::Writer::from_writer(vec![])))
+    } else {
+      std::fs::create_dir_all(output)?;
+      let manifest =
+        ManifestWriter::Files(File::create(output.join(MANIFEST_FILE_NAME))?, csv::Writer::from_path(output.join(MANIFEST_CSV_FILE_NAME))?);
+      (ExtractSink::Directory(output.to_path_buf()), manifest)
+    };
+    let mut paths = ExtractPaths::default();
+    paths.reserve(Path::new(MANIFEST_FILE_NAME));
+    paths.reserve(Path::new(MANIFEST_CSV_FILE_NAME));
+    Ok(Self {
+      output: output.to_path_buf(),
+      sink,
+      paths,
+      collisions: CollisionPolicy::default(),
+      raw: false,
+      run: RunInfo::default(),
+      date_repr: DateRepr::default(),
+      started: SystemTime::now(),
+      manifest,
+      run_written: false,
+      files: 0,
+    })
+  }
+
+  #[must_use]
+  pub fn with_collisions(mut self, policy: CollisionPolicy) -> Self {
+    self.collisions = policy;
+    self
+  }
+
+  /// The raw contents (see [`scnr_core::Scanner::with_raw_nodes`]) are written at the node path, and the converted ones
+  /// next to them with their type as extension (`a.plist` and `a.plist.json`)
+  #[must_use]
+  pub fn with_raw(mut self) -> Self {
+    self.raw = true;
+    self
+  }
+
+  /// Parameters written at the start of the manifest
+  #[must_use]
+  pub fn with_run_info(mut self, run: RunInfo) -> Self {
+    self.run = run;
+    self
+  }
+
+  /// Representation of the dates of the manifest
+  #[must_use]
+  pub fn with_date_repr(mut self, date_repr: DateRepr) -> Self {
+    self.date_repr = date_repr;
+    self
+  }
+
+  /// Writes the content and returns the path it was written at (`None` when skipped), json contents are pretty printed.
+  /// The original size and sha256 are the ones of [`ScanContent::origin`] (see [`scnr_core::Scanner::with_node_digests`]).
+  pub fn add(&mut self, content: &ScanContent) -> Result<Option<PathBuf>, ExtractError> {
+    let data = match &content.content {
+      Content::Json(json) => serde_json::to_vec_pretty(json)?,
+      Content::Text(text) => text.as_bytes().to_vec(),
+      Content::Bytes(bytes) => bytes.clone(),
+    };
+    let mut wanted = content.path.to_path_buf().into_os_string();
+    match (self.raw, content.raw, &content.content) {
+      (false, _, _) | (true, true, _) => {}
+      (true, false, Content::Json(_)) => wanted.push(".json"),
+      (true, false, Content::Text(_)) => wanted.push(".txt"),
+      (true, false, Content::Bytes(_)) => wanted.push(".bin"),
+    }
+    let wanted = PathBuf::from(wanted);
+    let mut entry = ManifestEntry {
+      kind: EntryKind::File,
+      path: None,
+      source: Some(content.path.to_string()),
+      plugin: content.plugin.map(|plugin| short_plugin_name(plugin).to_string()),
+      raw: content.raw,
+      original_size: content.origin.as_ref().map(|origin| origin.size),
+      original_sha256: content.origin.as_ref().map(|origin| origin.sha256.clone()),
+      size: Some(data.len()),
+      sha256: Some(format!("{:x}", Sha256::digest(&data))),
+      extracted_at: self.date_repr.to_string(SystemTime::now())?,
+      error: None,
+    };
+
+    let Some(path) = self.paths.allocate(&wanted, self.collisions)? else {
+      let wanted = wanted.display();
+      tracing::warn!("`{wanted}` is already taken in the output, `{}` is skipped", content.path);
+      entry.kind = EntryKind::Skipped;
+      entry.error = Some(format!("`{wanted}` is already taken in the output"));
+      self.write_entry(&entry)?;
+      return Ok(None);
+    };
+
+    self.sink.write(&path, &data)?;
+    entry.path = Some(archive_name(&path));
+    self.files += 1;
+    self.write_entry(&entry)?;
+    Ok(Some(path))
+  }
+
+  /// Records a scan error in the manifest
+  pub fn add_error(&mut self, error: &ScanError) -> Result<(), ExtractError> {
+    let entry = self.error_entry(error.path().map(ToString::to_string), error.plugin(), error.to_string())?;
+    self.write_entry(&entry)
+  }
+
+  fn error_entry(&self, source: Option<String>, plugin: Option<&str>, error: String) -> Result<ManifestEntry, ExtractError> {
+    Ok(ManifestEntry {
+      kind: EntryKind::Error,
+      path: None,
+      source,
+      plugin: plugin.map(|plugin| short_plugin_name(plugin).to_string()),
+      raw: false,
+      original_size: None,
+      original_sha256: None,
+      size: None,
+      sha256: None,
+      extracted_at: self.date_repr.to_string(SystemTime::now())?,
+      error: Some(error),
+    })
+  }
+
+  fn write_entry(&mut self, entry: &ManifestEntry) -> Result<(), ExtractError> {
+    self.write_run_line()?;
+    self.manifest.entry(entry)
+  }
+
+  fn write_run_line(&mut self) -> Result<(), ExtractError> {
+    if !self.run_written {
+      self.run.started_at = self.date_repr.to_string(self.started)?;
+      self.manifest.line(&RunLine { kind: "run", run: &self.run })?;
+      self.run_written = true;
+    }
+    Ok(())
+  }
+
+  /// Ends the manifest and closes the archives, returns the number of written files
+  pub fn finish(mut self) -> Result<usize, ExtractError> {
+    self.write_run_line()?;
+    let finished_at = self.date_repr.to_string(SystemTime::now())?;
+    self.manifest.line(&EndLine { kind: "end", finished_at, files: self.files })?;
+    self.manifest.finish(&mut self.sink)?;
+    self.sink.finish()?;
+    Ok(self.files)
+  }
+
+  /// Ends a failed extraction like [`Extractor::finish`], with the failure as last error entry of the manifest: the
+  /// archive holds the files written so far and their manifest. An archive that cannot be closed is removed rather than
+  /// left truncated.
+  pub fn abort(mut self, error: &dyn std::fmt::Display) -> Result<(), ExtractError> {
+    let archive = archive_kind(&self.output).map(|_| self.output.clone());
+    let failure = self
+      .error_entry(None, None, format!("The extraction failed: {error}"))
+      .and_then(|entry| self.write_entry(&entry));
+    let Err(err) = failure.and_then(|()| self.finish()) else { return Ok(()) };
+    if let Some(archive) = archive {
+      tracing::warn!("Removing {}, it could not be closed", archive.display());
+      std::fs::remove_file(archive)?;
+    }
+    Err(err)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+  use scnr_core::{tests_helpers::scan_content, ScanPath};
+  use std::{collections::BTreeSet, io::Read};
+
+  #[test]
+  fn collisions() {
+    let mut paths = ExtractPaths::default();
+    paths.reserve(Path::new(MANIFEST_FILE_NAME));
+    let mut allocate = |path: &str| match paths.allocate(Path::new(path), CollisionPolicy::Rename) {
+      Ok(Some(path)) => path.display().to_string(),
+      other => format!("{other:?}"),
+    };
+
+    assert_eq!(allocate("x.db/t"), "x.db/t");
+    assert_eq!(allocate("x.db/t"), "x.db/t~2");
+    assert_eq!(allocate("x.db/t"), "x.db/t~3");
+    assert_eq!(allocate("manifest.jsonl"), "manifest.jsonl~2");
+    // a file, then a directory of the same name
+    assert_eq!(allocate("a.zip/db"), "a.zip/db");
+    assert_eq!(allocate("a.zip/db/table"), "a.zip/db~2/table");
+    assert_eq!(allocate("a.zip/db/other"), "a.zip/db~2/other");
+    // a directory, then a file of the same name
+    assert_eq!(allocate("b/c/d"), "b/c/d");
+    assert_eq!(allocate("b/c"), "b/c~2");
+    assert_eq!(allocate("b/c/e"), "b/c/e");
+    // nothing is written outside of the output
+    assert_eq!(allocate("a.zip/../../etc/passwd"), "a.zip/etc/passwd");
+    assert_eq!(allocate("/abs/./x"), "abs/x");
+    assert_eq!(allocate(".."), "_");
+  }
+
+  #[test]
+  fn collision_policies() -> anyhow::Result<()> {
+    let mut paths = ExtractPaths::default();
+    assert_eq!(paths.allocate(Path::new("a/b"), CollisionPolicy::Skip)?, Some(PathBuf::from("a/b")));
+    assert_eq!(paths.allocate(Path::new("a/b"), CollisionPolicy::Skip)?, None);
+    assert_eq!(paths.allocate(Path::new("a/b/c"), CollisionPolicy::Skip)?, None);
+    assert!(matches!(paths.allocate(Path::new("a"), CollisionPolicy::Error), Err(ExtractError::Collision(path)) if path == "a"));
+    // the skipped directory was not taken
+    assert_eq!(paths.allocate(Path::new("a/c"), CollisionPolicy::Error)?, Some(PathBuf::from("a/c")));
+    Ok(())
+  }
+
+  #[test]
+  fn raw_contents() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut extractor = Extractor::create(dir.path())?.with_raw();
+    let mut raw = scan_content("a.plist", Content::Bytes(b"bplist".to_vec()));
+    raw.raw = true;
+    assert_eq!(extractor.add(&raw)?, Some(PathBuf::from("a.plist")));
+    assert_eq!(extractor.add(&scan_content("a.plist", Content::Json(serde_json::json!({}))))?, Some(PathBuf::from("a.plist.json")));
+    assert_eq!(extractor.add(&scan_content("b.log", Content::Text("b".into())))?, Some(PathBuf::from("b.log.txt")));
+    extractor.finish()?;
+    assert_eq!(std::fs::read(dir.path().join("a.plist"))?, b"bplist");
+    assert_eq!(std::fs::read_to_string(dir.path().join("a.plist.json"))?, "{}");
+    Ok(())
+  }
+
+  #[test]
+  fn archives() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let contents = [
+      scan_content("a.zip!/b.json", Content::Json(serde_json::json!({ "a": 1 }))),
+      scan_content("a.zip!/b.json", Content::Text("again".into())),
+      scan_content("c.bin", Content::Bytes(vec![0, 1, 2])),
+      // written under the unescaped names
+      scan_content("d\\!.zip!/notes\\#1.txt", Content::Text("notes".into())),
+    ];
+    let expected_names = ["a.zip/b.json", "a.zip/b.json~2", "c.bin", "d!.zip/notes#1.txt", MANIFEST_FILE_NAME, MANIFEST_CSV_FILE_NAME];
+
+    for (name, is_zip) in [("out.zip", true), ("out.tar.gz", false)] {
+      let output = dir.path().join(name);
+      let mut extractor = Extractor::create(&output)?;
+      for content in &contents {
+        extractor.add(content)?;
+      }
+      extractor.finish()?;
+
+      let mut files = vec![];
+      if is_zip {
+        let mut zip = zip::ZipArchive::new(File::open(&output)?)?;
+        for i in 0..zip.len() {
+          let mut file = zip.by_index(i)?;
+          let mut data = String::new();
+          file.read_to_string(&mut data).ok();
+          files.push((file.name().to_string(), data));
+        }
+      } else {
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(File::open(&output)?));
+        for entry in tar.entries()? {
+          let mut entry = entry?;
+          let mut data = String::new();
+          entry.read_to_string(&mut data).ok();
+          files.push((entry.path()?.display().to_string(), data));
+        }
+      }
+
+      assert_eq!(files.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), expected_names);
+      assert_eq!(files[1].1, "again");
+      let manifest = files[4].1.lines().collect::<Vec<_>>();
+      assert_eq!(manifest.len(), 6);
+      assert!(manifest[1].starts_with(r#"{"type":"file","path":"a.zip/b.json","source":"a.zip!/b.json","plugin":null,"raw":false,"#));
+      assert_eq!(files[5].1.lines().count(), 5);
+    }
+
+    Ok(())
+  }
+
+  #[test]
+  fn aborted_archives() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let output = dir.path().join("out.zip");
+    let mut extractor = Extractor::create(&output)?.with_collisions(CollisionPolicy::Error);
+    extractor.add(&scan_content("a.json", Content::Text("a".into())))?;
+    assert!(matches!(extractor.add(&scan_content("a.json", Content::Text("b".into()))), Err(ExtractError::Collision(_))));
+    extractor.abort(&"`a.json` is already taken in the output")?;
+
+    let mut zip = zip::ZipArchive::new(File::open(&output)?)?;
+    assert_eq!(zip.file_names().collect::<BTreeSet<_>>(), BTreeSet::from(["a.json", MANIFEST_FILE_NAME, MANIFEST_CSV_FILE_NAME]));
+    let mut manifest = String::new();
+    zip.by_name(MANIFEST_FILE_NAME)?.read_to_string(&mut manifest)?;
+    let lines = manifest.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[2].contains(r#""error":"The extraction failed: `a.json` is already taken in the output""#));
+    assert!(lines[3].starts_with(r#"{"type":"end","#));
+    Ok(())
+  }
+
+  #[test]
+  fn streamed_manifest() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut extractor = Extractor::create(dir.path())?;
+    extractor.add(&scan_content("a.json", Content::Text("a".into())))?;
+    // written before the end of the extraction
+    let manifest = std::fs::read_to_string(dir.path().join(MANIFEST_FILE_NAME))?;
+    assert_eq!(manifest.lines().count(), 2);
+    assert_eq!(std::fs::read_to_string(dir.path().join(MANIFEST_CSV_FILE_NAME))?.lines().count(), 2);
+
+    extractor.abort(&"stopped")?;
+    let manifest = std::fs::read_to_string(dir.path().join(MANIFEST_FILE_NAME))?;
+    assert_eq!(manifest.lines().count(), 4);
+    Ok(())
+  }
+
+  #[test]
+  fn manifest() -> anyhow::Result<()> {
+    use clap::Parser;
+    let dir = tempfile::tempdir()?;
+    let config = dir.path().join("config.toml");
+    std::fs::write(&config, "[profiles.triage]\ninherits = \"sysdiagnose\"\n")?;
+    let opts = crate::options::Opts::parse_from(
+      format!(
+        "scnr extract -i evidence -o out -p triage --config {} -f *.plist --cfg *.bin=text --opt zip.passwords=x --raw",
+        config.display()
+      )
+      .split(' '),
+    );
+    let Some(crate::options::Command::Extract(mut args)) = opts.cmd else { anyhow::bail!("not an extract command") };
+    let profile = args.common.profile.clone();
+    args.common = crate::resolve_common_args(&args.common)?;
+    let run = RunInfo::from_args(&args, &profile, vec!["scnr".into(), "extract".into()])?;
+    assert_eq!(
+      (run.config.as_deref(), run.config_sha256.as_deref()),
+      (Some(config.display().to_string().as_str()), Some(format!("{:x}", Sha256::digest(std::fs::read(&config)?)).as_str()))
+    );
+    assert_eq!(
+      (run.inputs.as_slice(), run.profile.as_str(), run.cfg.as_slice(), run.filters.as_slice(), run.options.as_slice()),
+      (
+        ["evidence".to_string()].as_slice(),
+        "triage",
+        ["*.bin=text".to_string()].as_slice(),
+        ["*.plist".to_string()].as_slice(),
+        ["zip.passwords=x".to_string()].as_slice()
+      )
+    );
+
+    let mut extractor = Extractor::create(&dir.path().join("out"))?
+      .with_run_info(run)
+      .with_date_repr(DateRepr::UnixSeconds)
+      .with_collisions(CollisionPolicy::Skip);
+    let mut converted = scan_content("a.plist", Content::Json(serde_json::json!({})));
+    converted.plugin = Some("scnr_plist::PlistPlugin");
+    converted.origin = Some(scnr_core::dedup::NodeDigest { size: 6, sha256: "0a1b".into() });
+    extractor.add(&converted)?;
+    extractor.add(&converted)?;
+    extractor.add_error(&ScanError::Unreadable { path: ScanPath::parse("b.plist"), message: "denied".into() })?;
+    assert_eq!(extractor.finish()?, 1);
+
+    let manifest = std::fs::read_to_string(dir.path().join("out").join(MANIFEST_FILE_NAME))?;
+    let lines = manifest
+      .lines()
+      .map(serde_json::from_str)
+      .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(lines.len(), 5);
+    assert_eq!(
+      (&lines[0]["type"], &lines[0]["scnr_version"], &lines[0]["raw"]),
+      (&"run".into(), &env!("CARGO_PKG_VERSION").into(), &true.into())
+    );
+    assert!(lines[0]["started_at"].as_str().is_some_and(|date| date.parse::<f64>().is_ok()));
+    assert_eq!(
+      (
+        &lines[1]["type"],
+        &lines[1]["path"],
+        &lines[1]["plugin"],
+        &lines[1]["original_size"],
+        &lines[1]["original_sha256"],
+        &lines[1]["size"]
+      ),
+      (&"file".into(), &"a.plist".into(), &"PlistPlugin".into(), &6.into(), &"0a1b".into(), &2.into())
+    );
+    assert_eq!((&lines[2]["type"], &lines[2]["path"]), (&"skipped".into(), &serde_json::Value::Null));
+    assert_eq!(
+      (&lines[3]["type"], &lines[3]["source"], &lines[3]["error"]),
+      (&"error".into(), &"b.plist".into(), &"Unable to read `b.plist`: denied".into())
+    );
+    assert_eq!((&lines[4]["type"], &lines[4]["files"]), (&"end".into(), &1.into()));
+    assert!(lines[4]["finished_at"].as_str().is_some_and(|date| date.parse::<f64>().is_ok()));
+
+    let csv = std::fs::read_to_string(dir.path().join("out").join(MANIFEST_CSV_FILE_NAME))?;
+    assert_eq!(csv.lines().next(), Some("type,path,source,plugin,raw,original_size,original_sha256,size,sha256,extracted_at,error"));
+    assert_eq!(csv.lines().count(), 4);
+
+    Ok(())
+  }
+}
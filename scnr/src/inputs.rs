@@ -0,0 +1,122 @@
+//! Parsing of the `-i` args: `path`, `label=path` and `@list.txt` (one input per line)
+
+use scnr_core::ScanInput;
+use std::{collections::HashSet, path::Path};
+
+/// Prefix of the inputs read from a list file
+pub const LIST_FILE_PREFIX: char = '@';
+
+#[derive(thiserror::Error, Debug)]
+pub enum InputsError {
+  #[error("Unable to read input list `{path}`: {source}")]
+  ListFile { path: String, source: std::io::Error },
+  #[error("Input list `{0}` is empty")]
+  EmptyList(String),
+}
+
+/// Expands the list files and labels the inputs.
+/// When there are several inputs, the unlabeled ones are labeled with their file name, so that their contents do not mix.
+pub fn parse_inputs(args: &[String]) -> Result<Vec<ScanInput>, InputsError> {
+  let mut inputs = vec![];
+  for arg in args {
+    match arg.strip_prefix(LIST_FILE_PREFIX) {
+      Some(list_path) => {
+        let list = std::fs::read_to_string(list_path).map_err(|source| InputsError::ListFile { path: list_path.to_string(), source })?;
+        let before = inputs.len();
+        inputs.extend(parse_list(&list).map(parse_input));
+        if inputs.len() == before {
+          return Err(InputsError::EmptyList(list_path.to_string()));
+        }
+      }
+      None => inputs.push(parse_input(arg)),
+    }
+  }
+
+  if inputs.len() > 1 {
+    label_inputs(&mut inputs);
+  }
+
+  Ok(inputs)
+}
+
+/// Non empty lines of a list file, `#` starts a comment line
+fn parse_list(list: &str) -> impl Iterator<Item = &str> {
+  list
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// `label=path` is labeled unless the whole arg is an existing path (or the label looks like a path)
+fn parse_input(arg: &str) -> ScanInput {
+  match arg.split_once('=') {
+    Some((label, start)) if !label.is_empty() && !label.contains(['/', '\\']) && !Path::new(arg).exists() => {
+      ScanInput::labeled(label, start)
+    }
+    _ => ScanInput::new(arg),
+  }
+}
+
+fn label_inputs(inputs: &mut [ScanInput]) {
+  let mut used = inputs.iter().filter_map(|input| input.label.clone()).collect::<HashSet<_>>();
+
+  for input in inputs.iter_mut().filter(|input| input.label.is_none()) {
+    let name = Path::new(&input.start)
+      .file_name()
+      .map_or_else(|| "input".to_string(), |name| name.to_string_lossy().to_string());
+
+    let mut label = name.clone();
+    let mut index = 1;
+    while used.contains(&label) {
+      index += 1;
+      label = format!("{name}-{index}");
+    }
+
+    used.insert(label.clone());
+    input.label = Some(label);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(ToString::to_string).collect()
+  }
+
+  #[test]
+  fn single_input_is_not_labeled() -> anyhow::Result<()> {
+    assert_eq!(parse_inputs(&strings(&["/data/host"]))?, [ScanInput::new("/data/host")]);
+    assert_eq!(parse_inputs(&strings(&["host=/data/host"]))?, [ScanInput::labeled("host", "/data/host")]);
+    Ok(())
+  }
+
+  #[test]
+  fn multiple_inputs_are_labeled() -> anyhow::Result<()> {
+    let inputs = parse_inputs(&strings(&["/data/a/host", "baseline=/data/b/host", "/data/c/host", "./x=y", "."]))?;
+    assert_eq!(
+      inputs,
+      [
+        ScanInput::labeled("host", "/data/a/host"),
+        ScanInput::labeled("baseline", "/data/b/host"),
+        ScanInput::labeled("host-2", "/data/c/host"),
+        ScanInput::labeled("x=y", "./x=y"),
+        ScanInput::labeled("input", "."),
+      ]
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn list_lines() {
+    let list = "# devices\n/data/a\n\n  phone=/data/b  \n";
+    assert_eq!(parse_list(list).map(parse_input).collect::<Vec<_>>(), [ScanInput::new("/data/a"), ScanInput::labeled("phone", "/data/b")]);
+  }
+
+  #[test]
+  fn missing_list() {
+    assert!(matches!(parse_inputs(&strings(&["@/does/not/exist.txt"])), Err(InputsError::ListFile { .. })));
+  }
+}
@@ -1,21 +1,158 @@
-use options::CommonArgs;
-use scnr_core::{filter::Glob, Scanner, ScannerOptions};
+use options::{CommonArgs, DedupArg, Profile, TimestampEpoch, TimestampsMode, UnchangedMode};
+use scnr_core::{
+  dedup::{DedupMode, Deduplicator},
+  filter::Glob,
+  manifest::{Incremental, Manifest, UnchangedFiles},
+  plugins::stdin,
+  timestamps::{TimestampMode, TimestampNormalizer},
+  DateRepr, PluginOptions, Scanner, ScannerOptions,
+};
 
+use std::{sync::Arc, time::Duration};
+
+pub mod config;
+pub mod extract;
+pub mod inputs;
 pub mod options;
+pub mod output;
 pub mod profiles;
 pub use scnr_core as core;
 
 pub fn get_scanner_from_options(common_args: &CommonArgs) -> Result<Scanner, anyhow::Error> {
-  let options = ScannerOptions::default();
-  let picker = profiles::get_plugin_picker(common_args.profile, &common_args.cfg, &common_args.starter, &options)?;
-  let scanner = Scanner::new(&common_args.input, picker);
+  let common_args = &resolve_common_args(common_args)?;
+  let Profile::Builtin(profile) = common_args.profile else {
+    return Err(anyhow::anyhow!("Profile `{}` has not been resolved", common_args.profile));
+  };
+  let mut plugin_options: PluginOptions = common_args.opt.iter().cloned().collect();
+  if let Some(name) = &common_args.name {
+    plugin_options.set(stdin::NAME_OPTION, name);
+  }
+  let options = ScannerOptions {
+    plugin_options,
+    bin_repr: common_args.bin_repr(),
+    date_repr: common_args.date_repr(),
+    run_params: Some(run_params(common_args)),
+  };
+  let picker = profiles::get_plugin_picker(profile, &common_args.cfg, &common_args.fallback, &common_args.starter)?;
+  let scanner = Scanner::new_multi(inputs::parse_inputs(&common_args.input)?, picker).with_options(options);
   let scanner = config_scanner_filter(scanner, &common_args.filter)?;
+  let scanner = config_scanner_timestamps(scanner, common_args.timestamps(), &common_args.timestamp_rule)?;
+  let scanner = config_scanner_incremental(scanner, common_args)?;
+  let scanner = config_scanner_dedup(scanner, common_args);
+  let scanner = match common_args.node_timeout {
+    Some(seconds) => scanner.with_node_timeout(Duration::from_secs(seconds)),
+    None => scanner,
+  };
   Ok(scanner)
 }
 
+/// Applies the `--config` file (if any) to the args: plugin options and named profile.
+/// The resolved args hold a builtin profile, resolving them again gives the same args.
+/// The default config file is not looked up here, only the command line does it ([`config::default_config_path`]).
+pub fn resolve_common_args(common_args: &CommonArgs) -> anyhow::Result<CommonArgs> {
+  let config = match &common_args.config {
+    Some(path) => config::Config::load(path)?,
+    None => config::Config::default(),
+  };
+  Ok(config.apply(common_args.clone())?)
+}
+
+/// Parameters the converted contents depend on (see [`ScannerOptions::run_params`]), the args should be resolved first.
+/// `index.root` only tells where the indexed input is, it is left out. A json object, the filters are under the
+/// [`scnr_sqlite::index::RUN_PARAMS_FILTERS`] key.
+#[must_use]
+pub fn run_params(common_args: &CommonArgs) -> String {
+  let mut timestamp_rules = common_args
+    .timestamp_rule
+    .iter()
+    .map(|(pattern, epoch)| format!("{pattern}={epoch:?}"))
+    .collect::<Vec<_>>();
+  timestamp_rules.sort();
+  let mut options = common_args
+    .opt
+    .iter()
+    .filter(|(key, _)| key != scnr_sqlite::index::ROOT_OPTION)
+    .map(|(key, value)| format!("{key}={value}"))
+    .collect::<Vec<_>>();
+  options.sort();
+  serde_json::json!({
+    "profile": common_args.profile.to_string(),
+    "cfg": common_args.cfg.iter().map(|(glob, plugin)| format!("{glob}={plugin:?}")).collect::<Vec<_>>(),
+    "fallbacks": common_args.fallback.iter().map(|(plugin, fallback)| format!("{plugin:?}={fallback:?}")).collect::<Vec<_>>(),
+    scnr_sqlite::index::RUN_PARAMS_FILTERS: common_args.filter,
+    "bin_repr": format!("{}", common_args.bin_repr()),
+    "date_repr": format!("{}", common_args.date_repr()),
+    "timestamps": common_args.timestamps().to_string(),
+    "timestamp_rules": timestamp_rules,
+    "dedup": dedup_mode(common_args).map(|mode| format!("{mode:?}")),
+    "options": options,
+  })
+  .to_string()
+}
+
 pub fn config_scanner_filter(mut scanner: Scanner, filter: &[String]) -> anyhow::Result<Scanner> {
   if !filter.is_empty() {
     scanner = scanner.with_filter(Glob::multi(filter)?);
   }
   Ok(scanner)
 }
+
+/// Incremental scan when a manifest is read or written, a missing `--manifest` file means that all the files are new
+pub fn config_scanner_incremental(scanner: Scanner, common_args: &CommonArgs) -> anyhow::Result<Scanner> {
+  if common_args.manifest.is_none() && common_args.write_manifest.is_none() {
+    return Ok(scanner);
+  }
+
+  let previous = match &common_args.manifest {
+    Some(path) if path.exists() => Manifest::load(path)?,
+    _ => Manifest::default(),
+  };
+  let unchanged = match common_args.unchanged {
+    UnchangedMode::Skip => UnchangedFiles::Skip,
+    UnchangedMode::Mark => UnchangedFiles::Mark,
+  };
+
+  Ok(scanner.with_incremental(Arc::new(Incremental::new(previous, unchanged))))
+}
+
+/// Dedup scan when asked, or when the duplicate groups are written
+#[must_use]
+pub fn config_scanner_dedup(scanner: Scanner, common_args: &CommonArgs) -> Scanner {
+  match dedup_mode(common_args) {
+    Some(mode) => scanner.with_dedup(Arc::new(Deduplicator::new(mode))),
+    None => scanner,
+  }
+}
+
+fn dedup_mode(common_args: &CommonArgs) -> Option<DedupMode> {
+  match (common_args.dedup, &common_args.duplicates) {
+    (Some(DedupArg::Reference), _) | (None, Some(_)) => Some(DedupMode::Reference),
+    (Some(DedupArg::Omit), _) => Some(DedupMode::Omit),
+    (None, None) => None,
+  }
+}
+
+pub fn config_scanner_timestamps(scanner: Scanner, mode: TimestampsMode, rules: &[(String, TimestampEpoch)]) -> anyhow::Result<Scanner> {
+  let mode = match mode {
+    TimestampsMode::Off => return Ok(scanner),
+    TimestampsMode::Add => TimestampMode::Add,
+    TimestampsMode::Replace => TimestampMode::Replace,
+  };
+  Ok(scanner.with_timestamps(timestamp_normalizer(mode, rules)?))
+}
+
+/// The timestamp rules, then the heuristics
+pub fn timestamp_normalizer(mode: TimestampMode, rules: &[(String, TimestampEpoch)]) -> anyhow::Result<TimestampNormalizer> {
+  let mut normalizer = TimestampNormalizer::new(mode);
+  for (pattern, epoch) in rules {
+    let epoch = match epoch {
+      TimestampEpoch::Unix => Some(DateRepr::UnixSeconds),
+      TimestampEpoch::UnixMs => Some(DateRepr::UnixMillis),
+      TimestampEpoch::Cocoa => Some(DateRepr::CocoaSeconds),
+      TimestampEpoch::Webkit => Some(DateRepr::WebKitMicros),
+      TimestampEpoch::Guess => None,
+    };
+    normalizer = normalizer.with_rule(pattern, epoch)?;
+  }
+  Ok(normalizer.with_heuristics()?)
+}
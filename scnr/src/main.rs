@@ -1,10 +1,29 @@
 #![allow(clippy::default_trait_access, clippy::module_name_repetitions, clippy::wildcard_imports)]
 #![deny(clippy::expect_used, clippy::unwrap_used, clippy::panic)]
 
-use scnr_core::{bin_repr, jq, Content, Scanner};
-use std::{io::Write, path::Path};
-
-use scnr::options::*;
+use scnr_core::{
+  dedup::Deduplicator,
+  explain::NodeKind,
+  jq,
+  listing::NodeInfo,
+  manifest::Incremental,
+  plugins::stdin::STDIN_START_PARAM,
+  timeline::{self, Timeline},
+  timestamps::TimestampMode,
+  Content, ScanContent, ScanInput, ScanPath, Scanner,
+};
+use scnr_parquet::ParquetExport;
+use scnr_sqlite::{export::SqliteExport, index::IndexWriter};
+use std::{
+  io::Write,
+  path::{Path, PathBuf},
+};
+
+use scnr::{
+  extract::{is_archive_output, Extractor, RunInfo, MANIFEST_FILE_NAME},
+  options::*,
+  output::JsonlRecord,
+};
 
 fn main() -> anyhow::Result<()> {
   let opts = scnr::options::get_options();
@@ -12,27 +31,92 @@ fn main() -> anyhow::Result<()> {
     pretty_env_logger::try_init()?;
   }
 
-  let command = opts.cmd.unwrap_or_default();
-  let common_args = command.common();
+  let mut command = opts.cmd.unwrap_or_default();
+  match &mut command {
+    Command::Cat(args) => args.rebase_absolute_path(),
+    Command::Timeline(args) => args.enable_metadata(),
+    _ => {}
+  }
+  // only the command line reads the default config file, the library and the python bindings read the given one
+  let common = command.common_mut();
+  if common.config.is_none() {
+    common.config = scnr::config::default_config_path();
+  }
+  // the run info of the extraction records the profile asked for
+  let requested_profile = command.common().profile.clone();
+  *command.common_mut() = scnr::resolve_common_args(command.common())?;
+
+  let scanner = scnr::get_scanner_from_options(command.common())?;
+  let (incremental, dedup) = (scanner.incremental(), scanner.dedup());
+  let common = command.common().clone();
+
+  let result = match command {
+    scnr::options::Command::Scan(args) => scan(scanner, args),
+    scnr::options::Command::Extract(args) => extract(scanner, args, &requested_profile),
+    scnr::options::Command::Jq(args) => jq(scanner, args),
+    scnr::options::Command::Cat(args) => cat(scanner, &args),
+    scnr::options::Command::Index(args) => index(scanner, &args),
+    scnr::options::Command::Ls(args) => ls(scanner, &args),
+    scnr::options::Command::Explain(args) => explain(&scanner, &args),
+    scnr::options::Command::Timeline(args) => timeline(scanner, &args),
+    scnr::options::Command::Export(args) => export(scanner, &args),
+  };
+
+  match result {
+    // the output is piped to a command that stopped reading it (`scnr scan | head`), this is not an error
+    Err(err) if is_broken_pipe(&err) => Ok(()),
+    Ok(()) => write_scan_reports(incremental.as_deref(), dedup.as_deref(), &common),
+    result => result,
+  }
+}
 
-  let scanner = scnr::get_scanner_from_options(common_args)?;
+/// Writes the manifest and the duplicate groups, once the scan is over
+fn write_scan_reports(incremental: Option<&Incremental>, dedup: Option<&Deduplicator>, common: &CommonArgs) -> anyhow::Result<()> {
+  if let (Some(incremental), Some(path)) = (incremental, &common.write_manifest) {
+    let removed = incremental.removed_files();
+    if !removed.is_empty() {
+      tracing::info!("{} files of the previous manifest were not found or not scanned: {removed:?}", removed.len());
+    }
+    incremental.manifest().save(path)?;
+  }
 
-  match command {
-    scnr::options::Command::Scan(args) => scan(scanner, args)?,
-    scnr::options::Command::Extract(args) => extract(scanner, args)?,
-    scnr::options::Command::Jq(args) => jq(scanner, args)?,
+  if let (Some(dedup), Some(path)) = (dedup, &common.duplicates) {
+    let groups = dedup.duplicate_groups();
+    tracing::info!("{} groups of duplicates", groups.len());
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, &groups)?;
+    writer.flush()?;
   }
 
   Ok(())
 }
 
-fn print_path(out: &mut impl Write, path: &Path, options: &CommonArgs) -> anyhow::Result<()> {
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+  err.chain().any(|cause| {
+    let kind = match cause.downcast_ref::<serde_json::Error>() {
+      Some(json_err) => json_err.io_error_kind(),
+      None => cause.downcast_ref::<std::io::Error>().map(std::io::Error::kind),
+    };
+    kind == Some(std::io::ErrorKind::BrokenPipe)
+  })
+}
+
+fn print_path(out: &mut impl Write, content: &ScanContent, options: &CommonArgs) -> anyhow::Result<()> {
   if options.print_file_names {
-    writeln!(out, "{}", path.display())?;
+    match content.change {
+      Some(change) => writeln!(out, "{} [{change}]", content.path)?,
+      None => writeln!(out, "{}", content.path)?,
+    }
   }
   Ok(())
 }
 
+fn print_record(out: &mut impl Write, record: &JsonlRecord) -> anyhow::Result<()> {
+  serde_json::to_writer(&mut *out, record)?;
+  writeln!(out)?;
+  Ok(())
+}
+
 fn print_content(out: &mut impl Write, content: &Content, options: &CommonArgs) -> anyhow::Result<()> {
   match &content {
     scnr_core::Content::Json(json) => {
@@ -44,7 +128,7 @@ fn print_content(out: &mut impl Write, content: &Content, options: &CommonArgs)
       }
     }
     scnr_core::Content::Text(text) => writeln!(out, "{text}")?,
-    scnr_core::Content::Bytes(bytes) => writeln!(out, "{}", bin_repr::BinRepr::Base64.to_string(bytes))?,
+    scnr_core::Content::Bytes(bytes) => writeln!(out, "{}", options.bin_repr().to_string(bytes))?,
   }
 
   writeln!(out)?;
@@ -59,13 +143,21 @@ fn scan(scanner: Scanner, args: ScanArgs) -> anyhow::Result<()> {
 
   let iter = scanner.scan()?;
 
+  let jsonl = args.common.output_format() == OutputFormat::Jsonl;
+
   for content in iter {
     match content {
+      Ok(content) if jsonl => print_record(&mut lock, &JsonlRecord::from_content(&content, args.common.bin_repr()))?,
       Ok(content) => {
-        print_path(&mut lock, &content.rel_path, &args.common)?;
+        print_path(&mut lock, &content, &args.common)?;
         print_content(&mut lock, &content.content, &args.common)?;
       }
-      Err(err) => tracing::error!("{err:?}"),
+      Err(err) => {
+        tracing::error!("{err:?}");
+        if jsonl {
+          print_record(&mut lock, &JsonlRecord::from_error(&err))?;
+        }
+      }
     }
   }
 
@@ -80,18 +172,33 @@ fn jq(scanner: Scanner, args: JqArgs) -> anyhow::Result<()> {
   let jq_filter = jq::JqFilter::new(&args.query)?;
 
   let iter = scanner.scan()?;
+  let jsonl = args.common.output_format() == OutputFormat::Jsonl;
 
   for content in iter {
     match content {
-      Ok(content) => {
-        if let Some(json) = content.content.json() {
-          print_path(&mut lock, &content.rel_path, &args.common)?;
+      // references to duplicates would only add noise to the query results
+      Ok(content) if content.duplicate_of.is_some() => {}
+      Ok(mut content) => {
+        if let Content::Json(json) = &mut content.content {
+          let json = json.take();
+          if !jsonl {
+            print_path(&mut lock, &content, &args.common)?;
+          }
           for element in jq_filter.run(json)? {
-            print_content(&mut lock, &Content::Json(element), &args.common)?;
+            if jsonl {
+              print_record(&mut lock, &JsonlRecord::from_json(&content, element))?;
+            } else {
+              print_content(&mut lock, &Content::Json(element), &args.common)?;
+            }
           }
         }
       }
-      Err(err) => tracing::error!("{err:?}"),
+      Err(err) => {
+        tracing::error!("{err:?}");
+        if jsonl {
+          print_record(&mut lock, &JsonlRecord::from_error(&err))?;
+        }
+      }
     }
   }
 
@@ -99,49 +206,333 @@ fn jq(scanner: Scanner, args: JqArgs) -> anyhow::Result<()> {
 }
 
 #[tracing::instrument(skip(scanner), err)]
-fn extract(scanner: Scanner, args: ExtractArgs) -> anyhow::Result<()> {
-  let output = args.output;
+fn cat(scanner: Scanner, args: &CatArgs) -> anyhow::Result<()> {
+  let stdout = std::io::stdout();
+  let mut lock = stdout.lock();
 
-  if !args.force && output.exists() && output.is_dir() && output.read_dir()?.next().is_some() {
-    return Err(anyhow::anyhow!("Output directory is not empty"));
+  if args.raw {
+    let content = scanner.open_raw(&args.path)?;
+    if let Content::Bytes(bytes) = content.content {
+      lock.write_all(&bytes)?;
+    }
+  } else {
+    let content = scanner.open(&args.path)?;
+    print_path(&mut lock, &content, &args.common)?;
+    print_content(&mut lock, &content.content, &args.common)?;
   }
 
-  std::fs::create_dir_all(&output)?;
+  Ok(())
+}
 
-  let iter = scanner.scan()?;
+#[tracing::instrument(skip(scanner), err)]
+fn index(scanner: Scanner, args: &IndexArgs) -> anyhow::Result<()> {
+  let root = match scnr::inputs::parse_inputs(&args.common.input)?.as_slice() {
+    [ScanInput { label: None, start }] if start != STDIN_START_PARAM => PathBuf::from(start),
+    _ => return Err(anyhow::anyhow!("An index is built from a single unlabeled file or directory input")),
+  };
 
-  for content in iter {
+  prepare_output_file(&args.output, args.force, std::slice::from_ref(&root), "Index")?;
+
+  let mut writer = IndexWriter::create(&args.output, &root)?.with_run_params(Some(&scnr::run_params(&args.common)));
+  for content in scanner.scan()? {
+    match content {
+      Ok(content) => writer.add(&content)?,
+      Err(err) => {
+        tracing::error!("{err:?}");
+        writer.add_error(&err)?;
+      }
+    }
+  }
+  let (files, contents) = writer.finish()?;
+  tracing::info!("Indexed {contents} contents of {files} files in {}", args.output.display());
+
+  Ok(())
+}
+
+/// Checks that the output file can be created: it does not exist (or is removed with `force`), and it is not written in an input
+/// directory (it would be scanned while it is written)
+fn prepare_output_file(output: &Path, force: bool, inputs: &[PathBuf], kind: &str) -> anyhow::Result<()> {
+  if output.exists() {
+    if !force {
+      return Err(anyhow::anyhow!("{kind} file `{}` already exists", output.display()));
+    }
+    std::fs::remove_file(output)?;
+  }
+
+  let output_dir = output.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+  check_outside_inputs(output_dir, inputs, kind)
+}
+
+fn prepare_output_dir(output: &Path, force: bool, inputs: &[PathBuf], kind: &str) -> anyhow::Result<()> {
+  if !force && output.is_dir() && output.read_dir()?.next().is_some() {
+    return Err(anyhow::anyhow!("{kind} directory `{}` is not empty", output.display()));
+  }
+  std::fs::create_dir_all(output)?;
+  check_outside_inputs(output, inputs, kind)
+}
+
+fn check_outside_inputs(output_dir: &Path, inputs: &[PathBuf], kind: &str) -> anyhow::Result<()> {
+  let output_dir = std::fs::canonicalize(output_dir)?;
+  for input in inputs.iter().filter(|input| input.is_dir()) {
+    if output_dir.starts_with(std::fs::canonicalize(input)?) {
+      return Err(anyhow::anyhow!("The {} file must be written outside of the input directory", kind.to_lowercase()));
+    }
+  }
+  Ok(())
+}
+
+#[tracing::instrument(skip(scanner), err)]
+fn export(scanner: Scanner, args: &ExportArgs) -> anyhow::Result<()> {
+  let inputs = scnr::inputs::parse_inputs(&args.common.input)?
+    .into_iter()
+    .filter(|input| input.start != STDIN_START_PARAM)
+    .map(|input| PathBuf::from(input.start))
+    .collect::<Vec<_>>();
+
+  match args.to {
+    ExportFormat::Sqlite => {
+      prepare_output_file(&args.output, args.force, &inputs, "Export")?;
+      export_sqlite(scanner, args)
+    }
+    ExportFormat::Parquet => {
+      prepare_output_dir(&args.output, args.force, &inputs, "Export")?;
+      export_parquet(scanner, args)
+    }
+  }
+}
+
+fn export_sqlite(scanner: Scanner, args: &ExportArgs) -> anyhow::Result<()> {
+  let mut export = SqliteExport::create(&args.output)?;
+  if args.materialize_tables {
+    export = export.with_materialized_tables();
+  }
+
+  for content in scanner.scan()? {
+    match content {
+      Ok(content) => export.add(&content)?,
+      Err(err) => {
+        tracing::error!("{err:?}");
+        export.add_error(&err)?;
+      }
+    }
+  }
+  let nodes = export.finish()?;
+  tracing::info!("Exported {nodes} contents in {}", args.output.display());
+
+  Ok(())
+}
+
+fn export_parquet(scanner: Scanner, args: &ExportArgs) -> anyhow::Result<()> {
+  let mut export = ParquetExport::create(&args.output)?;
+  for (name, glob) in &args.group {
+    export = export.with_group(name, glob)?;
+  }
+
+  for content in scanner.scan()? {
+    match content.map(|content| export.add(&content)) {
+      Ok(Ok(_)) => {}
+      Ok(Err(err @ scnr_parquet::ExportError::Mismatch { .. })) => tracing::error!("{err}"),
+      Ok(Err(err)) => return Err(err.into()),
+      Err(err) => tracing::error!("{err:?}"),
+    }
+  }
+  for (file, rows) in export.finish()? {
+    tracing::info!("Exported {rows} rows in {}", file.display());
+  }
+
+  Ok(())
+}
+
+#[tracing::instrument(skip(scanner), err)]
+fn ls(scanner: Scanner, args: &LsArgs) -> anyhow::Result<()> {
+  let stdout = std::io::stdout();
+  let mut lock = stdout.lock();
+  let mut tree = TreePrinter::default();
+
+  for content in scanner.list()? {
     match content {
       Ok(content) => {
-        let rel_path = content.rel_path;
-        let content_type = content.content.to_string();
-        let extract_path = output.join(&rel_path);
-
-        tracing::info!(
-          "Extracting {rel_path} as {content_type} in {extract_path}",
-          rel_path = rel_path.display(),
-          extract_path = extract_path.display()
-        );
-
-        if let Some(extract_dir) = extract_path.parent() {
-          if !extract_dir.exists() {
-            tracing::debug!("Creating folder {extract_dir}", extract_dir = extract_dir.display());
-            std::fs::create_dir_all(extract_dir)?;
-          }
+        let Some(node) = NodeInfo::from_content(&content) else { continue };
+        let included = if node.included { "included" } else { "excluded" };
+        if args.tree {
+          tree.print(&mut lock, &content.path, &format!("({} bytes, {}, {included})", node.size, node.plugin))?;
+        } else {
+          writeln!(lock, "{}\t{}\t{}\t{included}", content.path, node.size, node.plugin)?;
         }
+      }
+      Err(err) => tracing::error!("{err:?}"),
+    }
+  }
 
-        let mut file = std::fs::File::create(extract_path)?;
+  Ok(())
+}
 
-        match content.content {
-          scnr_core::Content::Json(json) => serde_json::to_writer_pretty(file, &json)?,
-          scnr_core::Content::Text(text) => file.write_all(text.as_bytes())?,
-          scnr_core::Content::Bytes(bytes) => file.write_all(&bytes)?,
-        }
+/// Prints the listed nodes (depth first) as an indented tree, their parent directories are printed once
+#[derive(Default)]
+struct TreePrinter {
+  previous: Vec<String>,
+}
+
+impl TreePrinter {
+  fn print(&mut self, out: &mut impl Write, path: &ScanPath, details: &str) -> anyhow::Result<()> {
+    let components = path
+      .segments()
+      .iter()
+      .flat_map(|segment| segment.name.split(['/', '\\']))
+      .filter(|name| !name.is_empty())
+      .map(ToString::to_string)
+      .collect::<Vec<_>>();
+    let common = self.previous.iter().zip(&components).take_while(|(a, b)| a == b).count();
+
+    for (depth, name) in components.iter().enumerate().skip(common) {
+      let indent = "  ".repeat(depth);
+      if depth + 1 == components.len() {
+        writeln!(out, "{indent}{name} {details}")?;
+      } else {
+        writeln!(out, "{indent}{name}/")?;
+      }
+    }
+
+    self.previous = components;
+    Ok(())
+  }
+}
+
+#[tracing::instrument(skip(scanner), err)]
+fn explain(scanner: &Scanner, args: &ExplainArgs) -> anyhow::Result<()> {
+  let stdout = std::io::stdout();
+  let mut lock = stdout.lock();
+
+  for node in scanner.explain(&ScanPath::parse(&args.path)) {
+    let kind = match node.kind {
+      NodeKind::Directory => "directory",
+      NodeKind::File => "file",
+      NodeKind::ArchiveMember => "archive member",
+      NodeKind::PluginChild => "plugin child",
+    };
+    writeln!(lock, "{} ({kind})", node.path)?;
+    if !node.reached {
+      writeln!(lock, "  not reached: the scan does not go through the node before")?;
+    }
+
+    let picked = node.rules.iter().position(|rule| rule.matches);
+    for (i, rule) in node.rules.iter().enumerate() {
+      let mark = match (Some(i) == picked, rule.matches) {
+        (true, _) => "=>",
+        (false, true) => " +",
+        (false, false) => "  ",
+      };
+      let glob = rule.glob.as_deref().unwrap_or("(starter)");
+      write!(lock, "  {mark} {:>2}. {glob:<30} {}", i + 1, rule.plugin)?;
+      if !rule.fallbacks.is_empty() {
+        write!(lock, " (then {})", rule.fallbacks.join(", "))?;
+      }
+      if rule.priority != 0 {
+        write!(lock, " [priority {}]", rule.priority)?;
       }
+      writeln!(lock)?;
+    }
+
+    let decision = match (node.kind, &node.plugin, node.wanted()) {
+      (NodeKind::Directory, _, true) => "descended".to_string(),
+      (NodeKind::Directory, _, false) => "not descended".to_string(),
+      (NodeKind::PluginChild, _, _) => "sent by the plugin of its parent (not filtered)".to_string(),
+      (_, None, _) => "no plugin matches, not scanned".to_string(),
+      (_, Some(plugin), true) if node.container => format!("opened by {plugin}"),
+      (_, Some(plugin), true) => format!("scanned by {plugin}"),
+      (_, Some(_), false) if node.container => "filtered out, not opened".to_string(),
+      (_, Some(_), false) => "filtered out".to_string(),
+    };
+    writeln!(lock, "  filter: should_scan={} can_recurse={} => {decision}", node.should_scan, node.can_recurse)?;
+  }
+
+  Ok(())
+}
+
+#[tracing::instrument(skip(scanner), err)]
+fn timeline(scanner: Scanner, args: &TimelineArgs) -> anyhow::Result<()> {
+  let normalizer = scnr::timestamp_normalizer(TimestampMode::Add, &args.common.timestamp_rule)?;
+  let mut timeline = Timeline::new(normalizer, args.common.date_repr());
+
+  for content in scanner.scan()? {
+    match content {
+      // the events of a duplicate are the ones of its first occurrence
+      Ok(content) if content.duplicate_of.is_some() => {}
+      Ok(content) => timeline.add(&content),
       Err(err) => tracing::error!("{err:?}"),
     }
   }
+  let events = timeline.into_events();
+  tracing::info!("{} timeline events", events.len());
+
+  let out: Box<dyn Write> = match &args.output {
+    Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+    None => Box::new(std::io::stdout().lock()),
+  };
+  match args.format {
+    TimelineFormat::Bodyfile => timeline::write_bodyfile(&events, out)?,
+    TimelineFormat::Csv => timeline::write_csv(&events, out)?,
+    TimelineFormat::Jsonl => timeline::write_jsonl(&events, out)?,
+  }
+
+  Ok(())
+}
+
+#[tracing::instrument(skip(scanner), err)]
+fn extract(scanner: Scanner, args: ExtractArgs, profile: &Profile) -> anyhow::Result<()> {
+  let output = args.output.clone();
+
+  if is_archive_output(&output) {
+    let inputs = scnr::inputs::parse_inputs(&args.common.input)?
+      .into_iter()
+      .filter(|input| input.start != STDIN_START_PARAM)
+      .map(|input| PathBuf::from(input.start))
+      .collect::<Vec<_>>();
+    prepare_output_file(&output, args.force, &inputs, "Output")?;
+  } else if !args.force && output.exists() && output.is_dir() && output.read_dir()?.next().is_some() {
+    return Err(anyhow::anyhow!("Output directory is not empty"));
+  }
+
+  let run = RunInfo::from_args(&args, profile, std::env::args().collect())?;
+  let mut extractor = Extractor::create(&output)?
+    .with_collisions(args.on_collision)
+    .with_date_repr(args.common.date_repr())
+    .with_run_info(run);
+  let scanner = scanner.with_node_digests();
+  let scanner = if args.raw {
+    extractor = extractor.with_raw();
+    scanner.with_raw_nodes()
+  } else {
+    scanner
+  };
+
+  if let Err(err) = extract_contents(scanner, &mut extractor, &output) {
+    if let Err(abort_err) = extractor.abort(&err) {
+      tracing::error!("Unable to close {}: {abort_err}", output.display());
+    }
+    return Err(err);
+  }
+
+  let files = extractor.finish()?;
+  tracing::info!("Extracted {files} files in {}, see {}", output.display(), MANIFEST_FILE_NAME);
+
+  Ok(())
+}
 
+fn extract_contents(scanner: Scanner, extractor: &mut Extractor, output: &Path) -> anyhow::Result<()> {
+  for content in scanner.scan()? {
+    match content {
+      Ok(content) => {
+        if let Some(extract_path) = extractor.add(&content)? {
+          tracing::info!("Extracted {} as {} in {}", content.path, content.content, output.join(&extract_path).display());
+        }
+      }
+      Err(err) => {
+        tracing::error!("{err:?}");
+        extractor.add_error(&err)?;
+      }
+    }
+  }
   Ok(())
 }
 
@@ -149,7 +540,8 @@ fn extract(scanner: Scanner, args: ExtractArgs) -> anyhow::Result<()> {
 mod tests {
   use clap::Parser;
   use scnr::options::Opts;
-  use scnr_core::{tests_helpers::get_samples_path, Content, ScanContent, Scanner};
+  use scnr_core::{tests_helpers::get_samples_path, Content, ScanContent, ScanError, Scanner};
+  use std::collections::BTreeSet;
 
   fn create_scanner(args: &str) -> anyhow::Result<Scanner> {
     let opts = Opts::parse_from(args.split(' '));
@@ -159,16 +551,53 @@ mod tests {
     Ok(scanner)
   }
 
+  #[test]
+  fn incremental_scans() -> anyhow::Result<()> {
+    let input = tempfile::tempdir()?;
+    let manifest_dir = tempfile::tempdir()?;
+    let manifest = manifest_dir.path().join("manifest.json");
+    std::fs::write(input.path().join("a.json"), r#"{"a": 1}"#)?;
+    std::fs::write(input.path().join("b.txt"), "b")?;
+
+    let scan = |args: &str| -> anyhow::Result<Vec<String>> {
+      let command_line = format!("scnr scan -i {} --manifest {} {args}", input.path().display(), manifest.display());
+      let scanner = create_scanner(command_line.trim_end())?;
+      let incremental = scanner.incremental().ok_or_else(|| anyhow::anyhow!("not an incremental scan"))?;
+      let changes = scanner
+        .get_all_oks()?
+        .into_iter()
+        .map(|content| format!("{} {:?}", content.path, content.change))
+        .collect();
+      incremental.manifest().save(&manifest)?;
+      Ok(changes)
+    };
+
+    assert_eq!(scan("")?, ["a.json Some(New)", "b.txt Some(New)"]);
+    std::fs::write(input.path().join("b.txt"), "bb")?;
+    assert_eq!(scan("")?, ["b.txt Some(Modified)"]);
+    assert_eq!(scan("--unchanged mark")?, ["a.json Some(Unchanged)", "b.txt Some(Unchanged)"]);
+    // the files excluded by the filter are not hashed, nor recorded as scanned
+    assert_eq!(scan("--unchanged mark -f *.json")?, ["a.json Some(Unchanged)"]);
+    let scanned = scnr_core::manifest::Manifest::load(&manifest)?
+      .files
+      .into_keys()
+      .collect::<Vec<_>>();
+    assert_eq!(scanned, ["a.json"]);
+    assert_eq!(scan("")?, ["b.txt Some(New)"]);
+
+    Ok(())
+  }
+
   #[test]
   fn sample_to_console() -> anyhow::Result<()> {
     let samples = get_samples_path()?;
-    test_scnr_scan_output(&format!("scnr scan -i {samples}"), 40, 7, 4, 2)
+    test_scnr_scan_output(&format!("scnr scan -i {samples}"), 53, 7, 4, 2)
   }
 
   #[test]
   fn sample_to_console_sysdiag_profil() -> anyhow::Result<()> {
     let samples = get_samples_path()?;
-    test_scnr_scan_output(&format!("scnr scan -i {samples} -p sysdiagnose"), 55, 7, 1, 3)
+    test_scnr_scan_output(&format!("scnr scan -i {samples} -p sysdiagnose"), 69, 7, 1, 3)
   }
 
   fn test_scnr_scan_output(
@@ -228,7 +657,78 @@ mod tests {
 
     let results = create_scanner(&command_line)?.scan()?.to_vec();
     assert_eq!(results.len(), 1);
-    assert!(matches!(&results[0], Ok(ScanContent { rel_path, content: Content::Json(_json) }) if rel_path.as_os_str() == "json.json"));
+    assert!(matches!(&results[0], Ok(ScanContent { rel_path, content: Content::Json(_json), .. }) if rel_path.as_os_str() == "json.json"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn multiple_labeled_inputs() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let command_line = format!("scnr scan -i host={samples}/json.json -i {samples}/toml.toml -i baseline={samples}/json.json");
+
+    let paths = create_scanner(&command_line)?
+      .get_all_oks()?
+      .into_iter()
+      .map(|content| content.rel_path.display().to_string())
+      .collect::<Vec<_>>();
+    assert_eq!(paths, ["host/json.json", "toml.toml/toml.toml", "baseline/json.json"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn nested_paths_are_rendered() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let command_line = format!("scnr scan -i {samples} -f *.tar.gz!/**/*.json -f sakila_country_only.db");
+
+    let paths = create_scanner(&command_line)?
+      .get_all_oks()?
+      .into_iter()
+      .map(|content| content.path.to_string())
+      .collect::<BTreeSet<_>>();
+    assert_eq!(
+      paths,
+      BTreeSet::from(["sakila_country_only.db#__schema".into(), "sakila_country_only.db#country".into(), "w.tar.gz!/w/e.json".into()])
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn cat_nested_paths() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+
+    let content = create_scanner(&format!("scnr scan -i {samples}"))?.open("w.tar.gz/w/e.json")?;
+    assert!(matches!(content.content, Content::Json(_)));
+
+    let content = create_scanner(&format!("scnr scan -i {samples}"))?.open("sakila_country_only.db/country")?;
+    assert!(matches!(content.content, Content::Json(json) if json.as_array().is_some_and(|rows| rows.len() == 109)));
+
+    let content = create_scanner(&format!("scnr scan -i {samples}"))?.open_raw("z.zip")?;
+    assert_eq!(content.content, Content::Bytes(std::fs::read(format!("{samples}/z.zip"))?));
+
+    let not_raw = create_scanner(&format!("scnr scan -i {samples}"))?.open("z.zip");
+    assert!(matches!(not_raw, Err(ScanError::NotFound(path)) if path.as_os_str() == "z.zip"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn scanned_paths_can_be_opened() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let scanned =
+      create_scanner(&format!("scnr scan -i {samples} -f *.zip!/** -f *.tar.*!/** -f sakila_country_only.db"))?.get_all_oks()?;
+    assert!(scanned.len() > 3);
+
+    for content in scanned {
+      let rendered = content.path.to_string();
+      let opened = create_scanner(&format!("scnr scan -i {samples}"))?.open(&rendered)?;
+      assert_eq!((opened.path.to_string(), opened.content), (rendered, content.content));
+    }
+
+    let raw = create_scanner(&format!("scnr scan -i {samples}"))?.open_raw("w.tar.gz!/w/e.json")?;
+    assert!(matches!(raw.content, Content::Bytes(_)));
 
     Ok(())
   }
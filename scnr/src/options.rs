@@ -1,4 +1,6 @@
+use crate::extract::CollisionPolicy;
 use clap::{Args, Parser, Subcommand, ValueEnum};
+use scnr_core::{BinRepr, DateRepr};
 use std::{error::Error, path::PathBuf};
 
 pub const DEFAULT_INPUT: &str = ".";
@@ -23,8 +25,19 @@ pub struct Opts {
 
 #[derive(Debug, Clone, Args, PartialEq)]
 pub struct CommonArgs {
-  #[arg(short, long, default_value = DEFAULT_INPUT, help = "Input file or directory to start scanning")]
-  pub input: String,
+  #[arg(
+    short,
+    long,
+    default_value = DEFAULT_INPUT,
+    help = "Input file or directory to start scanning, can be repeated. Contents paths are prefixed by the input label when there are several inputs (e.g. -i host=/data/host -i baseline=/data/baseline).\n@<file> reads the inputs from a file, one per line"
+  )]
+  pub input: Vec<String>,
+
+  #[arg(
+    long,
+    help = "Name of the content read from stdin (-i -), used to pick its plugin (e.g. cat x.tar.gz | scnr scan -i - --name x.tar.gz).\nWithout it, the type is guessed from the first bytes"
+  )]
+  pub name: Option<String>,
 
   #[arg(short, long, help = "Included glob patterns")]
   pub filter: Vec<String>,
@@ -40,35 +53,164 @@ pub struct CommonArgs {
     short,
     long,
     help =
-    "Override default settings by allowing named plugins to handle specific files using glob patterns (e.g. --cfg *.json=json --cfg *data*.sql=sqlite --cfg **/do_not_deser.json=bin).\nPlugins are added in the inverse order of the command line, but the more precise glob patterns in the end.",
+    "Override default settings by allowing named plugins to handle specific files using glob patterns (e.g. --cfg *.json=json --cfg *data*.sql=sqlite --cfg **/do_not_deser.json=bin).\nThe cfg rules take precedence over the profile ones, then the most specific glob pattern wins (`**/do_not_deser.json` over `*.json`), then the last one on the command line (`scnr explain` shows the order).",
     value_parser = parse_key_val::<String, Plugin>
   )]
   pub cfg: Vec<(String, Plugin)>,
 
-  #[arg(short, long, default_value_t = CfgProfile::default(),  help = "Plugins configuration profile to start with. Profiles are cfg bundles and can be then overridden by cfg args")]
-  pub profile: CfgProfile,
+  #[arg(
+    long,
+    help = "Plugin tried when another one fails to scan a node, instead of losing its content (e.g. --fallback json=text --fallback text=bin, chains are followed)",
+    value_parser = parse_key_val::<Plugin, Plugin>
+  )]
+  pub fallback: Vec<(Plugin, Plugin)>,
+
+  #[arg(
+    short,
+    long,
+    default_value_t = Profile::default(),
+    help = "Plugins configuration profile to start with: standard, sysdiagnose, nothing or a profile of the config file. Profiles are cfg bundles and can be then overridden by cfg args"
+  )]
+  pub profile: Profile,
 
   #[arg(long, short = 'n', help = "DO print the file names (before the content)")]
   pub print_file_names: bool,
 
   #[arg(long, short = 'b', help = "DO pretty(beautiful) print the output")]
   pub pretty_print: bool,
+
+  #[arg(
+    long,
+    help = "Console output of scan and jq: raw contents (default), or one json record per line ({path, type, plugin, content, error}, errors included)"
+  )]
+  pub output_format: Option<OutputFormat>,
+
+  #[arg(
+    long,
+    help = "Converts epoch timestamps (unix, cocoa, webkit...) found in json contents to dates (off by default), the epochs are guessed from the key names and the values unless a timestamp rule matches"
+  )]
+  pub timestamps: Option<TimestampsMode>,
+
+  #[arg(
+    long,
+    help = "Explicit timestamp rule, keys matching the glob pattern are converted from the given epoch (e.g. --timestamp-rule ZDATE*=cocoa --timestamp-rule last_visit_time=webkit).\nPossible epochs: unix, unix-ms, cocoa, webkit, guess",
+    value_parser = parse_key_val::<String, TimestampEpoch>
+  )]
+  pub timestamp_rule: Vec<(String, TimestampEpoch)>,
+
+  #[arg(
+    long,
+    help = "Binary data representation (in json contents and console output).\nPossible values: base64 (url safe, default), base64-std, hex, hexdump (preview), omit"
+  )]
+  pub bin_repr: Option<BinRepr>,
+
+  #[arg(
+    long,
+    help = "Dates representation (in json contents).\nPossible values: rfc3339 (default), unix, unix-ms, cocoa, webkit, iso:+HH:MM (fixed timezone), format:<strftime format> (e.g. 'format:%Y-%m-%d %H:%M:%S')"
+  )]
+  pub date_repr: Option<DateRepr>,
+
+  #[arg(
+    long,
+    help = "Plugin option, keys are prefixed by the plugin name (e.g. --opt sqlite.json_array_limit=5000 --opt zip.passwords=infected,s3cr3t --opt xml.attribute_prefix=_).\nOverrides the options of the config file",
+    value_parser = parse_key_val::<String, String>
+  )]
+  pub opt: Vec<(String, String)>,
+
+  #[arg(
+    long,
+    help = "Toml or yaml config file holding plugin options ([options] table) and named profiles ([profiles.<name>] tables).\nDefaults to ~/.config/scnr/config.toml (or config.yaml) if it exists"
+  )]
+  pub config: Option<PathBuf>,
+
+  #[arg(
+    long,
+    help = "Manifest of a previous scan (written by --write-manifest), only the files that are new or modified since are scanned (see --unchanged)"
+  )]
+  pub manifest: Option<PathBuf>,
+
+  #[arg(long, help = "Writes the manifest of the scanned files (path, size, modification time and sha256) at the end of the scan")]
+  pub write_manifest: Option<PathBuf>,
+
+  #[arg(
+    long,
+    default_value_t = UnchangedMode::default(),
+    help = "What to do with the files that did not change since the --manifest"
+  )]
+  pub unchanged: UnchangedMode,
+
+  #[arg(
+    long,
+    num_args = 0..=1,
+    default_missing_value = "reference",
+    help = "Scans only once the nodes with the same bytes (files, archive members...), the next occurrences are replaced by a {\"duplicate_of\": <path>} reference, or omitted"
+  )]
+  pub dedup: Option<DedupArg>,
+
+  #[arg(long, help = "Writes the groups of duplicate nodes (json) at the end of the scan, implies --dedup")]
+  pub duplicates: Option<PathBuf>,
+
+  #[arg(
+    long,
+    value_name = "SECONDS",
+    help = "Gives up the files (and archive members) not converted within this time, an error is output for them and the scan goes on"
+  )]
+  pub node_timeout: Option<u64>,
 }
 
 impl Default for CommonArgs {
   fn default() -> Self {
     CommonArgs {
-      input: DEFAULT_INPUT.to_string(),
+      input: vec![DEFAULT_INPUT.to_string()],
+      name: None,
       filter: vec![],
-      profile: CfgProfile::default(),
+      profile: Profile::default(),
       cfg: vec![],
+      fallback: vec![],
       starter: vec![],
       print_file_names: false,
       pretty_print: false,
+      output_format: None,
+      timestamps: None,
+      timestamp_rule: vec![],
+      bin_repr: None,
+      date_repr: None,
+      opt: vec![],
+      config: None,
+      manifest: None,
+      write_manifest: None,
+      unchanged: UnchangedMode::default(),
+      dedup: None,
+      duplicates: None,
+      node_timeout: None,
     }
   }
 }
 
+/// The representations and modes are `None` when neither the command line nor the profile set them, so that a value
+/// given on the command line always wins over the profile one, even if it is the default one
+impl CommonArgs {
+  #[must_use]
+  pub fn output_format(&self) -> OutputFormat {
+    self.output_format.unwrap_or_default()
+  }
+
+  #[must_use]
+  pub fn timestamps(&self) -> TimestampsMode {
+    self.timestamps.unwrap_or_default()
+  }
+
+  #[must_use]
+  pub fn bin_repr(&self) -> BinRepr {
+    self.bin_repr.unwrap_or_default()
+  }
+
+  #[must_use]
+  pub fn date_repr(&self) -> DateRepr {
+    self.date_repr.clone().unwrap_or_default()
+  }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Default)]
 pub enum CfgProfile {
   #[default]
@@ -84,10 +226,42 @@ impl std::fmt::Display for CfgProfile {
   }
 }
 
+/// A builtin profile, or the name of a profile defined in the config file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Profile {
+  Builtin(CfgProfile),
+  Named(String),
+}
+
+impl Default for Profile {
+  fn default() -> Self {
+    Profile::Builtin(CfgProfile::default())
+  }
+}
+
+impl std::str::FromStr for Profile {
+  type Err = std::convert::Infallible;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(CfgProfile::from_str(s, true).map_or_else(|_| Profile::Named(s.to_string()), Profile::Builtin))
+  }
+}
+
+impl std::fmt::Display for Profile {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Profile::Builtin(profile) => write!(f, "{profile}"),
+      Profile::Named(name) => write!(f, "{name}"),
+    }
+  }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum, PartialEq, strum::EnumString)]
 #[strum(serialize_all = "lowercase")]
 pub enum Plugin {
   FileSystem,
+  Stdin,
+  Index,
   Json,
   Ips,
   Zip,
@@ -99,6 +273,74 @@ pub enum Plugin {
   Bin,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Default)]
+pub enum TimestampsMode {
+  /// Timestamps are left untouched
+  #[default]
+  Off,
+  /// Adds a `<key>__date` entry next to the timestamp
+  Add,
+  /// Replaces the timestamp by the date
+  Replace,
+}
+
+impl std::fmt::Display for TimestampsMode {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let dbg = format!("{self:?}").to_lowercase();
+    write!(f, "{dbg}")
+  }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Default)]
+pub enum OutputFormat {
+  /// Json, text and binary contents as is (file names with -n)
+  #[default]
+  Raw,
+  /// One json record per content or error
+  Jsonl,
+}
+
+impl std::fmt::Display for OutputFormat {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let dbg = format!("{self:?}").to_lowercase();
+    write!(f, "{dbg}")
+  }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Default)]
+pub enum UnchangedMode {
+  /// Unchanged files are not scanned
+  #[default]
+  Skip,
+  /// Unchanged files are scanned, their file names are followed by `[unchanged]` (new and modified files by `[new]` and `[modified]`)
+  Mark,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+pub enum DedupArg {
+  /// Duplicates are replaced by a reference to their first occurrence
+  Reference,
+  /// Duplicates are not output
+  Omit,
+}
+
+impl std::fmt::Display for UnchangedMode {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let dbg = format!("{self:?}").to_lowercase();
+    write!(f, "{dbg}")
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, strum::EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum TimestampEpoch {
+  Unix,
+  UnixMs,
+  Cocoa,
+  Webkit,
+  Guess,
+}
+
 impl Opts {}
 
 #[derive(Subcommand, Debug, Clone, PartialEq)]
@@ -110,6 +352,26 @@ pub enum Command {
   Extract(ExtractArgs),
   #[command(about = "Scan, execute jq filter on all possible results and output to the console")]
   Jq(JqArgs),
+  #[command(
+    about = "Output the content of one nested path (e.g. bundle.tar.gz/logs/app.zip/config.plist), only opening the needed containers"
+  )]
+  Cat(CatArgs),
+  #[command(about = "Scan and store all the contents in an index file, later scans of the index only rescan the files that changed")]
+  Index(IndexArgs),
+  #[command(
+    about = "Dry run: lists the nodes (containers are opened) with their size, the plugin that handles them and whether the filter includes them"
+  )]
+  Ls(LsArgs),
+  #[command(
+    about = "Explains the scan decisions for a nested path: picker rules in evaluation order, picked plugin and filter decisions of every node leading to it"
+  )]
+  Explain(ExplainArgs),
+  #[command(
+    about = "Scan and output a timeline sorted by date: file system and archive member dates, and the timestamps found in the contents (epoch values, dates, dated log lines)"
+  )]
+  Timeline(TimelineArgs),
+  #[command(about = "Scan and write all the contents and errors in a database file (e.g. scnr export --to sqlite out.db)")]
+  Export(ExportArgs),
 }
 
 impl Default for Command {
@@ -125,6 +387,26 @@ impl Command {
       Command::Scan(c) => &c.common,
       Command::Extract(c) => &c.common,
       Command::Jq(c) => &c.common,
+      Command::Cat(c) => &c.common,
+      Command::Index(c) => &c.common,
+      Command::Ls(c) => &c.common,
+      Command::Explain(c) => &c.common,
+      Command::Timeline(c) => &c.common,
+      Command::Export(c) => &c.common,
+    }
+  }
+
+  pub fn common_mut(&mut self) -> &mut CommonArgs {
+    match self {
+      Command::Scan(c) => &mut c.common,
+      Command::Extract(c) => &mut c.common,
+      Command::Jq(c) => &mut c.common,
+      Command::Cat(c) => &mut c.common,
+      Command::Index(c) => &mut c.common,
+      Command::Ls(c) => &mut c.common,
+      Command::Explain(c) => &mut c.common,
+      Command::Timeline(c) => &mut c.common,
+      Command::Export(c) => &mut c.common,
     }
   }
 }
@@ -140,10 +422,17 @@ pub struct ExtractArgs {
   #[command(flatten)]
   pub common: CommonArgs,
 
-  #[arg(short, long, help = "Output directory to extrat all files")]
+  #[arg(short, long, help = "Output directory to extrat all files, or `.zip` / `.tar.gz` archive to write them into")]
   pub output: PathBuf,
-  #[arg(long, help = "Force extraction even if the output directory is not empty")]
+  #[arg(long, help = "Force extraction even if the output directory is not empty, or overwrite the output archive")]
   pub force: bool,
+  #[arg(long, default_value_t = CollisionPolicy::default(), help = "What to do when the path of a content is already taken in the output")]
+  pub on_collision: CollisionPolicy,
+  #[arg(
+    long,
+    help = "Also extracts the original bytes of the files, the converted contents are written next to them (`a.plist` and `a.plist.json`)"
+  )]
+  pub raw: bool,
 }
 
 #[derive(Args, Debug, Clone, Default, PartialEq)]
@@ -155,12 +444,145 @@ pub struct JqArgs {
   pub query: String,
 }
 
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct CatArgs {
+  #[command(flatten)]
+  pub common: CommonArgs,
+
+  #[arg(help = "Nested path to output, relative to the input (or absolute): plain (a.zip/b.txt) or as printed by the scan (a.zip!/b.txt)")]
+  pub path: String,
+
+  #[arg(long, help = "Output the raw bytes of the path (which can be a container) instead of its converted content")]
+  pub raw: bool,
+}
+
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct IndexArgs {
+  #[command(flatten)]
+  pub common: CommonArgs,
+
+  #[arg(short, long, help = "Index file to create (outside of the input), scan it with -i <index file>")]
+  pub output: PathBuf,
+  #[arg(long, help = "Overwrite the index file if it exists")]
+  pub force: bool,
+}
+
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct LsArgs {
+  #[command(flatten)]
+  pub common: CommonArgs,
+
+  #[arg(long, short, help = "Outputs a tree (indented names) instead of one tab separated line per node")]
+  pub tree: bool,
+}
+
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct ExplainArgs {
+  #[command(flatten)]
+  pub common: CommonArgs,
+
+  #[arg(help = "Nested path to explain, relative to the input (e.g. `logs/a.zip!/b.db#table`), it does not have to exist")]
+  pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Default)]
+pub enum TimelineFormat {
+  /// Mactime bodyfile, one line per file or member (with its dates) and per content timestamp
+  Bodyfile,
+  /// Csv: date, path, source, field, value
+  #[default]
+  Csv,
+  /// One json event per line
+  Jsonl,
+}
+
+impl std::fmt::Display for TimelineFormat {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let dbg = format!("{self:?}").to_lowercase();
+    write!(f, "{dbg}")
+  }
+}
+
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct TimelineArgs {
+  #[command(flatten)]
+  pub common: CommonArgs,
+
+  #[arg(long, default_value_t = TimelineFormat::default(), help = "Output format of the timeline")]
+  pub format: TimelineFormat,
+
+  #[arg(short, long, help = "Output file, the timeline is written to the console otherwise")]
+  pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Default)]
+pub enum ExportFormat {
+  /// `nodes` and `errors` tables, json contents are queryable with the sqlite json functions
+  #[default]
+  Sqlite,
+  /// One parquet file per tabular node (database table, json array of objects, csv file) in an output directory
+  Parquet,
+}
+
+impl std::fmt::Display for ExportFormat {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let dbg = format!("{self:?}").to_lowercase();
+    write!(f, "{dbg}")
+  }
+}
+
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct ExportArgs {
+  #[command(flatten)]
+  pub common: CommonArgs,
+
+  #[arg(long, default_value_t = ExportFormat::default(), help = "Format of the export file")]
+  pub to: ExportFormat,
+
+  #[arg(help = "Export file (sqlite) or directory (parquet) to create, outside of the input")]
+  pub output: PathBuf,
+
+  #[arg(long, help = "Overwrite the export file if it exists, or write in a non empty export directory")]
+  pub force: bool,
+
+  #[arg(long, help = "Also writes the tables of the scanned databases as real tables, named after their node (e.g. `a.zip!/b.db#users`)")]
+  pub materialize_tables: bool,
+
+  #[arg(
+    long,
+    help = "Parquet: writes all the nodes matching a glob in one `<name>.parquet` file (e.g. `--group logs=**/*.db#logs`)",
+    value_parser = parse_key_val::<String, String>
+  )]
+  pub group: Vec<(String, String)>,
+}
+
+impl TimelineArgs {
+  /// The file system and archive plugins send the metadata of the nodes, unless the options are set otherwise
+  pub fn enable_metadata(&mut self) {
+    for key in [scnr_core::plugins::file_system::METADATA_OPTION, scnr_core::plugins::MEMBER_METADATA_OPTION] {
+      if !self.common.opt.iter().any(|(opt, _)| opt == key) {
+        self.common.opt.push((key.to_string(), "true".to_string()));
+      }
+    }
+  }
+}
+
+impl CatArgs {
+  /// Absolute paths are opened from the file system root
+  pub fn rebase_absolute_path(&mut self) {
+    if let Some(relative) = self.path.strip_prefix('/') {
+      self.common.input = vec!["/".to_string()];
+      self.path = relative.to_string();
+    }
+  }
+}
+
 // =================================================================================================
 // https://github.com/clap-rs/clap/blob/master/examples/typed-derive.rs
 // =================================================================================================
 
 /// Parse a single key-value pair
-fn parse_key_val<T, U>(s: &str) -> Result<(T, U), Box<dyn Error + Send + Sync + 'static>>
+pub(crate) fn parse_key_val<T, U>(s: &str) -> Result<(T, U), Box<dyn Error + Send + Sync + 'static>>
 where
   T: std::str::FromStr,
   T::Err: Error + Send + Sync + 'static,
@@ -190,26 +612,111 @@ mod tests {
     assert_eq!(opts.cmd, Some(Command::Scan(ScanArgs::default())));
   }
 
+  #[test]
+  fn parse_inputs() {
+    let opts = Opts::parse_from("scnr scan -i a=/data/a --input /data/b -i @list.txt".split(' '));
+    assert_eq!(opts.cmd.unwrap().common().input, ["a=/data/a", "/data/b", "@list.txt"]);
+    let opts = Opts::parse_from("scnr scan -i - --name x.tar.gz -s stdin".split(' '));
+    let common = opts.cmd.unwrap().common().clone();
+    assert_eq!((common.input, common.name, common.starter), (vec!["-".to_string()], Some("x.tar.gz".to_string()), vec![Plugin::Stdin]));
+  }
+
+  #[test]
+  fn parse_cat() {
+    let opts = Opts::parse_from("scnr cat /data/bundle.tar.gz/logs/app.zip --raw".split(' '));
+    let Some(Command::Cat(mut args)) = opts.cmd else { panic!("not a cat command") };
+    assert!(args.raw);
+    args.rebase_absolute_path();
+    assert_eq!((args.common.input, args.path), (vec!["/".to_string()], "data/bundle.tar.gz/logs/app.zip".to_string()));
+  }
+
+  #[test]
+  fn parse_index() {
+    let opts = Opts::parse_from("scnr index -i /data/case -o case.scnr --force".split(' '));
+    let Some(Command::Index(args)) = opts.cmd else { panic!("not an index command") };
+    assert_eq!((args.common.input, args.output, args.force), (vec!["/data/case".to_string()], PathBuf::from("case.scnr"), true));
+  }
+
+  #[test]
+  fn parse_ls() {
+    let opts = Opts::parse_from("scnr ls -i /data/case --tree -f *.plist".split(' '));
+    let Some(Command::Ls(args)) = opts.cmd else { panic!("not a ls command") };
+    assert!(args.tree);
+    assert_eq!(args.common.filter, ["*.plist"]);
+  }
+
+  #[test]
+  fn parse_explain() {
+    let opts = Opts::parse_from("scnr explain -p sysdiagnose a.tar.gz!/logs/x.plist".split(' '));
+    let Some(Command::Explain(args)) = opts.cmd else { panic!("not an explain command") };
+    assert_eq!(args.path, "a.tar.gz!/logs/x.plist");
+  }
+
+  #[test]
+  fn parse_timeline() {
+    let opts = Opts::parse_from("scnr timeline -i /data/case --format bodyfile -o case.body --opt fs.metadata=false".split(' '));
+    let Some(Command::Timeline(mut args)) = opts.cmd else { panic!("not a timeline command") };
+    assert_eq!((args.format, args.output.clone()), (TimelineFormat::Bodyfile, Some(PathBuf::from("case.body"))));
+    args.enable_metadata();
+    assert_eq!(args.common.opt, [("fs.metadata".into(), "false".into()), ("archive.metadata".into(), "true".into())]);
+  }
+
+  #[test]
+  fn parse_export() {
+    let opts = Opts::parse_from("scnr export -i /data/case --to sqlite case.db --materialize-tables".split(' '));
+    let Some(Command::Export(args)) = opts.cmd else { panic!("not an export command") };
+    assert_eq!((args.to, args.output, args.force, args.materialize_tables), (ExportFormat::Sqlite, PathBuf::from("case.db"), false, true));
+
+    let opts = Opts::parse_from("scnr export --to parquet out --group logs=**/*.db#logs --group csv=*.csv".split(' '));
+    let Some(Command::Export(args)) = opts.cmd else { panic!("not an export command") };
+    assert_eq!(args.to, ExportFormat::Parquet);
+    assert_eq!(args.group, [("logs".to_string(), "**/*.db#logs".to_string()), ("csv".to_string(), "*.csv".to_string())]);
+  }
+
+  #[test]
+  fn parse_profiles() {
+    let opts = Opts::parse_from("scnr scan -p Sysdiagnose".split(' '));
+    assert_eq!(opts.cmd.unwrap().common().profile, Profile::Builtin(CfgProfile::Sysdiagnose));
+    let opts = Opts::parse_from("scnr scan --profile triage".split(' '));
+    assert_eq!(opts.cmd.unwrap().common().profile, Profile::Named("triage".into()));
+  }
+
   #[test]
   fn parse_cmd_2() {
-    let cmd =
-      "scnr -v extract --output /tmp -f *.json --filter=**/*.xml --force -p sysdiagnose --cfg img.svg=json --cfg *.toml=text -s file-system -nb";
+    let cmd = "scnr -v extract --output /tmp -f *.json --filter=**/*.xml --force -p sysdiagnose --cfg img.svg=json --cfg *.toml=text --fallback json=text -s file-system -nb --output-format jsonl --timestamps replace --timestamp-rule Z*DATE=cocoa --bin-repr hex --date-repr iso:+02:00 --opt sqlite.json_array_limit=10 --opt zip.passwords=a,b --config scnr.toml --manifest m.json --write-manifest m.json --unchanged mark --dedup --duplicates d.json --node-timeout 30 --on-collision skip --raw";
     let opts = Opts::parse_from(cmd.split(' '));
     assert!(opts.verbose);
     assert_eq!(
       opts.cmd,
       Some(Command::Extract(ExtractArgs {
         common: CommonArgs {
-          input: DEFAULT_INPUT.to_string(),
+          input: vec![DEFAULT_INPUT.to_string()],
+          name: None,
           filter: vec!["*.json".into(), "**/*.xml".into()],
-          profile: CfgProfile::Sysdiagnose,
+          profile: Profile::Builtin(CfgProfile::Sysdiagnose),
           cfg: vec![("img.svg".into(), Plugin::Json), ("*.toml".into(), Plugin::Text)],
+          fallback: vec![(Plugin::Json, Plugin::Text)],
           starter: vec![Plugin::FileSystem],
           print_file_names: true,
-          pretty_print: true
+          pretty_print: true,
+          output_format: Some(OutputFormat::Jsonl),
+          timestamps: Some(TimestampsMode::Replace),
+          timestamp_rule: vec![("Z*DATE".into(), TimestampEpoch::Cocoa)],
+          bin_repr: Some(BinRepr::Hex),
+          date_repr: Some("iso:+02:00".parse().unwrap()),
+          opt: vec![("sqlite.json_array_limit".into(), "10".into()), ("zip.passwords".into(), "a,b".into())],
+          config: Some(PathBuf::from("scnr.toml")),
+          manifest: Some(PathBuf::from("m.json")),
+          write_manifest: Some(PathBuf::from("m.json")),
+          unchanged: UnchangedMode::Mark,
+          dedup: Some(DedupArg::Reference),
+          duplicates: Some(PathBuf::from("d.json")),
+          node_timeout: Some(30),
         },
         output: PathBuf::from("/tmp"),
         force: true,
+        on_collision: CollisionPolicy::Skip,
+        raw: true,
       }))
     );
   }
@@ -0,0 +1,91 @@
+//! Jsonl console output (`--output-format jsonl`): one json record per content or error, so that the output can be parsed
+//! line by line whatever the contents (multiline texts, binaries...).
+
+use scnr_core::{listing::short_plugin_name, BinRepr, Content, ScanContent, ScanError};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonlRecord {
+  pub path: Option<String>,
+  /// `json`, `text` or `bin`, `None` for the errors
+  #[serde(rename = "type")]
+  pub kind: Option<String>,
+  /// Short name of the plugin that converted the content, or that failed
+  pub plugin: Option<String>,
+  /// Json contents as is, texts as strings and binaries in the `--bin-repr` representation
+  pub content: Value,
+  pub error: Option<String>,
+}
+
+impl JsonlRecord {
+  #[must_use]
+  pub fn from_content(content: &ScanContent, bin_repr: BinRepr) -> Self {
+    let value = match &content.content {
+      Content::Json(json) => json.clone(),
+      Content::Text(text) => Value::String(text.clone()),
+      Content::Bytes(bytes) => Value::String(bin_repr.to_string(bytes)),
+    };
+    Self::from_json(content, value).with_kind(&content.content)
+  }
+
+  /// Record of a json computed from the content (jq results...)
+  #[must_use]
+  pub fn from_json(content: &ScanContent, json: Value) -> Self {
+    Self {
+      path: Some(content.path.to_string()),
+      kind: Some("json".to_string()),
+      plugin: content.plugin.map(|plugin| short_plugin_name(plugin).to_string()),
+      content: json,
+      error: None,
+    }
+  }
+
+  #[must_use]
+  pub fn from_error(error: &ScanError) -> Self {
+    Self {
+      path: error.path().map(ToString::to_string),
+      kind: None,
+      plugin: error.plugin().map(|plugin| short_plugin_name(plugin).to_string()),
+      content: Value::Null,
+      error: Some(error.to_string()),
+    }
+  }
+
+  fn with_kind(mut self, content: &Content) -> Self {
+    self.kind = Some(content.to_string());
+    self
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+  use scnr_core::{tests_helpers::scan_content, ScanPath};
+  use serde_json::json;
+
+  fn content(content: Content) -> ScanContent {
+    ScanContent { plugin: Some("scnr_core::plugins::text::TextPlugin"), ..scan_content("a.zip!/b", content) }
+  }
+
+  #[test]
+  fn records() -> anyhow::Result<()> {
+    let record = JsonlRecord::from_content(&content(Content::Text("line 1\nline 2".into())), BinRepr::Base64);
+    assert_eq!(
+      serde_json::to_string(&record)?,
+      r#"{"path":"a.zip!/b","type":"text","plugin":"TextPlugin","content":"line 1\nline 2","error":null}"#
+    );
+
+    let record = JsonlRecord::from_content(&content(Content::Bytes(vec![0xca, 0xfe])), BinRepr::Hex);
+    assert_eq!((record.kind.as_deref(), record.content), (Some("bin"), json!("cafe")));
+
+    let error = ScanError::Unreadable { path: ScanPath::parse("c.txt"), message: "denied".into() };
+    assert_eq!(
+      serde_json::to_value(JsonlRecord::from_error(&error))?,
+      json!({"path": "c.txt", "type": null, "plugin": null, "content": null, "error": "Unable to read `c.txt`: denied"})
+    );
+
+    Ok(())
+  }
+}
@@ -1,24 +1,29 @@
 use scnr_core::{
   plugins::{
-    bin::BinPlugin, file_system::FileSystemPlugin, ips::IpsPlugin, json::JsonPlugin, targz::TarGzPlugin, tarxz::TarXzPlugin,
-    text::TextPlugin, toml::TomlPlugin, xml::XmlPlugin, yaml::YamlPlugin, zip::ZipPlugin, DefaultPluginPicker,
+    bin::BinPlugin, file_system::FileSystemPlugin, ips::IpsPlugin, json::JsonPlugin, stdin::StdinPlugin, targz::TarGzPlugin,
+    tarxz::TarXzPlugin, text::TextPlugin, toml::TomlPlugin, xml::XmlPlugin, yaml::YamlPlugin, zip::ZipPlugin, DefaultPluginPicker,
+    PluginRule,
   },
-  ScanError, ScanPlugin, ScannerOptions,
+  ScanError, ScanPlugin,
 };
 use scnr_plist::PlistPlugin;
-use scnr_sqlite::SqlitePlugin;
+use scnr_sqlite::{index::IndexPlugin, SqlitePlugin};
+use std::sync::Arc;
 
 use crate::options::{CfgProfile, Plugin};
 
+/// Priority of the `--cfg` rules, they take precedence over the rules of the profile
+pub const CFG_PRIORITY: i32 = 1;
+
 pub fn get_plugin_picker(
   profile: CfgProfile,
   cfg: &[(String, Plugin)],
+  fallback: &[(Plugin, Plugin)],
   starter: &[Plugin],
-  options: &ScannerOptions,
 ) -> Result<DefaultPluginPicker, ScanError> {
   use scnr_core::plugins::DefaultPluginPickerBuilder;
 
-  fn add_standard_plugins(builder: DefaultPluginPickerBuilder, options: &ScannerOptions) -> Result<DefaultPluginPickerBuilder, ScanError> {
+  fn add_standard_plugins(builder: DefaultPluginPickerBuilder) -> Result<DefaultPluginPickerBuilder, ScanError> {
     builder
       .push_plugin("*.tar.gz", TarGzPlugin)?
       .push_plugin("*.tar.xz", TarXzPlugin)?
@@ -34,29 +39,44 @@ pub fn get_plugin_picker(
       .push_plugin("*.log", TextPlugin)?
       .push_plugin("*.csv*", TextPlugin)?
       .push_plugin("*.plist", PlistPlugin)?
-      .push_plugin("*.db", SqlitePlugin::new(options))?
-      .push_plugin("*.sqlite", SqlitePlugin::new(options))?
-      .push_plugin("*.sqlite3", SqlitePlugin::new(options))?
-      .push_plugin("*.sqlitedb", SqlitePlugin::new(options))
+      .push_plugin("*.db", SqlitePlugin)?
+      .push_plugin("*.sqlite", SqlitePlugin)?
+      .push_plugin("*.sqlite3", SqlitePlugin)?
+      .push_plugin("*.sqlitedb", SqlitePlugin)?
+      .push_starter_plugin(Box::new(IndexPlugin))
   }
 
   let mut builder = match profile {
-    CfgProfile::Standard => add_standard_plugins(DefaultPluginPicker::builder(), options)?,
-    CfgProfile::Sysdiagnose => add_standard_plugins(DefaultPluginPicker::builder(), options)?
+    CfgProfile::Standard => add_standard_plugins(DefaultPluginPicker::builder())?,
+    CfgProfile::Sysdiagnose => add_standard_plugins(DefaultPluginPicker::builder())?
       .push_plugin("*.stub", PlistPlugin)?
-      .push_plugin("*.plsql", SqlitePlugin::new(options))?
-      .push_plugin("*.epsql", SqlitePlugin::new(options))?
+      .push_plugin("*.plsql", SqlitePlugin)?
+      .push_plugin("*.epsql", SqlitePlugin)?
       .push_plugin("*.log*", TextPlugin)?
       .push_plugin("*.ips", IpsPlugin)?,
     CfgProfile::Nothing => DefaultPluginPicker::builder(),
   };
 
   for (pattern, plugin) in cfg {
-    builder = builder.insert_boxed_plugin(pattern.as_str(), get_plugin(*plugin, options))?;
+    builder = builder.insert_rule(PluginRule::new_boxed(pattern.as_str(), get_plugin(*plugin))?.with_priority(CFG_PRIORITY));
+  }
+
+  let mut plugins_with_fallbacks = vec![];
+  for (plugin, _) in fallback {
+    if !plugins_with_fallbacks.contains(plugin) {
+      plugins_with_fallbacks.push(*plugin);
+    }
+  }
+  for plugin in &plugins_with_fallbacks {
+    let chain = fallback_chain(*plugin, fallback)
+      .into_iter()
+      .map(|fallback| Arc::from(get_plugin(fallback)))
+      .collect::<Vec<_>>();
+    builder = builder.with_fallbacks_for(get_plugin(*plugin).name(), &chain)?;
   }
 
   for plugin in starter {
-    builder = builder.push_starter_plugin(get_plugin(*plugin, options))?;
+    builder = builder.push_starter_plugin(get_plugin(*plugin))?;
   }
 
   Ok(match profile {
@@ -65,9 +85,25 @@ pub fn get_plugin_picker(
   })
 }
 
-fn get_plugin(plugin: Plugin, options: &ScannerOptions) -> Box<dyn ScanPlugin> {
+/// Fallbacks of a plugin: its fallback, then the fallback of its fallback... (the last `--fallback` of a plugin wins)
+fn fallback_chain(plugin: Plugin, fallback: &[(Plugin, Plugin)]) -> Vec<Plugin> {
+  let mut chain = vec![];
+  let mut current = plugin;
+  while let Some((_, next)) = fallback.iter().rev().find(|(from, _)| *from == current) {
+    if *next == plugin || chain.contains(next) {
+      break;
+    }
+    chain.push(*next);
+    current = *next;
+  }
+  chain
+}
+
+fn get_plugin(plugin: Plugin) -> Box<dyn ScanPlugin> {
   match plugin {
     Plugin::FileSystem => Box::new(FileSystemPlugin),
+    Plugin::Stdin => Box::new(StdinPlugin),
+    Plugin::Index => Box::new(IndexPlugin),
     Plugin::Json => Box::new(JsonPlugin),
     Plugin::Ips => Box::new(IpsPlugin),
     Plugin::Zip => Box::new(ZipPlugin),
@@ -75,7 +111,34 @@ fn get_plugin(plugin: Plugin, options: &ScannerOptions) -> Box<dyn ScanPlugin> {
     Plugin::TarXz => Box::new(TarXzPlugin),
     Plugin::Text => Box::new(TextPlugin),
     Plugin::Plist => Box::new(PlistPlugin),
-    Plugin::Sqlite => Box::new(SqlitePlugin::new(options)),
+    Plugin::Sqlite => Box::new(SqlitePlugin),
     Plugin::Bin => Box::new(BinPlugin),
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+  use std::path::Path;
+
+  #[test]
+  fn fallback_chains() {
+    let fallback = [(Plugin::Json, Plugin::Text), (Plugin::Text, Plugin::Bin), (Plugin::Bin, Plugin::Json)];
+    assert_eq!(fallback_chain(Plugin::Json, &fallback), [Plugin::Text, Plugin::Bin]);
+    assert_eq!(fallback_chain(Plugin::Bin, &fallback), [Plugin::Json, Plugin::Text]);
+    assert_eq!(fallback_chain(Plugin::Zip, &fallback), []);
+  }
+
+  #[test]
+  fn cfg_rules_come_first() -> anyhow::Result<()> {
+    use scnr_core::plugins::PluginPicker;
+
+    let picker = get_plugin_picker(CfgProfile::Standard, &[("*".into(), Plugin::Bin)], &[(Plugin::Bin, Plugin::Text)], &[])?;
+    let rules = picker.explain_scan(Path::new("a.json"));
+    let first = rules.iter().find(|rule| rule.matches).ok_or_else(|| anyhow::anyhow!("no rule"))?;
+    assert_eq!((first.glob, first.plugin.name(), first.fallbacks.len()), (Some("*"), BinPlugin.name(), 1));
+
+    Ok(())
+  }
+}
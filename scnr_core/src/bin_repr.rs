@@ -2,16 +2,36 @@ use base64::{
   engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
   DecodeError, Engine,
 };
+use std::fmt::Write;
 
 #[derive(thiserror::Error, Debug)]
 pub enum BinReprError {
   #[error("Base64 encode error: {0}")]
   Base64Decode(#[from] DecodeError),
+  #[error("Hex decode error: {0}")]
+  HexDecode(String),
+  #[error("{0:?} binary representation cannot be decoded")]
+  NotReversible(BinRepr),
+  #[error("Unknown binary representation `{0}` (expected one of base64, base64-std, hex, hexdump, omit)")]
+  UnknownRepr(String),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Number of bytes shown by the [`BinRepr::HexDump`] preview
+pub const HEXDUMP_PREVIEW_LEN: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum BinRepr {
+  /// Url safe base64, without padding
+  #[default]
   Base64,
+  /// Standard base64, with padding
+  Base64Std,
+  /// Lower case hexadecimal
+  Hex,
+  /// `xxd` like preview of the first bytes, it cannot be decoded
+  HexDump,
+  /// Only the size of the data is kept, it cannot be decoded
+  Omit,
 }
 
 impl BinRepr {
@@ -19,15 +39,50 @@ impl BinRepr {
   pub fn to_string(&self, bytes: &[u8]) -> String {
     match self {
       BinRepr::Base64 => to_base64(bytes),
+      BinRepr::Base64Std => base64::engine::general_purpose::STANDARD.encode(bytes),
+      BinRepr::Hex => to_hex(bytes),
+      BinRepr::HexDump => to_hexdump(bytes),
+      BinRepr::Omit => format!("<{} bytes omitted>", bytes.len()),
     }
   }
   pub fn from_str(&self, s: &str) -> Result<Vec<u8>, BinReprError> {
     Ok(match self {
       BinRepr::Base64 => from_base64(s)?,
+      BinRepr::Base64Std => base64::engine::general_purpose::STANDARD.decode(s)?,
+      BinRepr::Hex => from_hex(s)?,
+      BinRepr::HexDump | BinRepr::Omit => return Err(BinReprError::NotReversible(*self)),
+    })
+  }
+}
+
+impl std::str::FromStr for BinRepr {
+  type Err = BinReprError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(match s {
+      "base64" => BinRepr::Base64,
+      "base64-std" => BinRepr::Base64Std,
+      "hex" => BinRepr::Hex,
+      "hexdump" => BinRepr::HexDump,
+      "omit" => BinRepr::Omit,
+      _ => return Err(BinReprError::UnknownRepr(s.to_string())),
     })
   }
 }
 
+impl std::fmt::Display for BinRepr {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name = match self {
+      BinRepr::Base64 => "base64",
+      BinRepr::Base64Std => "base64-std",
+      BinRepr::Hex => "hex",
+      BinRepr::HexDump => "hexdump",
+      BinRepr::Omit => "omit",
+    };
+    write!(f, "{name}")
+  }
+}
+
 pub const NO_PAD_BUT_CAN_DECODE: GeneralPurposeConfig = GeneralPurposeConfig::new()
   .with_encode_padding(false)
   .with_decode_padding_mode(DecodePaddingMode::Indifferent);
@@ -42,6 +97,60 @@ pub fn from_base64<T: AsRef<[u8]>>(data: T) -> Result<Vec<u8>, DecodeError> {
   URL_SAFE_ENGINE.decode(data)
 }
 
+pub fn to_hex<T: AsRef<[u8]>>(data: T) -> String {
+  let data = data.as_ref();
+  let mut hex = String::with_capacity(data.len() * 2);
+  for byte in data {
+    let _ = write!(hex, "{byte:02x}");
+  }
+  hex
+}
+
+pub fn from_hex<T: AsRef<[u8]>>(data: T) -> Result<Vec<u8>, BinReprError> {
+  let data = data.as_ref();
+  if data.len() % 2 != 0 {
+    return Err(BinReprError::HexDecode(format!("odd number of digits ({})", data.len())));
+  }
+  data
+    .chunks(2)
+    .map(|pair| {
+      std::str::from_utf8(pair)
+        .ok()
+        .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        .ok_or_else(|| BinReprError::HexDecode(format!("invalid digits `{}`", String::from_utf8_lossy(pair))))
+    })
+    .collect()
+}
+
+/// `xxd` like representation of the first [`HEXDUMP_PREVIEW_LEN`] bytes
+pub fn to_hexdump<T: AsRef<[u8]>>(data: T) -> String {
+  let data = data.as_ref();
+  let mut dump = String::new();
+
+  for (line, chunk) in data.chunks(16).take(HEXDUMP_PREVIEW_LEN / 16).enumerate() {
+    let _ = write!(dump, "{:08x}:", line * 16);
+    for (i, byte) in chunk.iter().enumerate() {
+      let separator = if i % 2 == 0 { " " } else { "" };
+      let _ = write!(dump, "{separator}{byte:02x}");
+    }
+    let missing = 16 - chunk.len();
+    dump.push_str(&" ".repeat(missing * 2 + missing / 2));
+    dump.push_str("  ");
+    dump.extend(
+      chunk
+        .iter()
+        .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }),
+    );
+    dump.push('\n');
+  }
+
+  if data.len() > HEXDUMP_PREVIEW_LEN {
+    let _ = writeln!(dump, "... ({} bytes)", data.len());
+  }
+
+  dump
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -62,9 +171,40 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn test_other_reprs() {
+    assert_eq!(Base64Std.to_string("hello".as_bytes()), "aGVsbG8=");
+    assert_eq!(Hex.to_string("hello".as_bytes()), "68656c6c6f");
+    assert_eq!(HexDump.to_string("hello".as_bytes()), "00000000: 6865 6c6c 6f                             hello\n");
+    assert_eq!(Omit.to_string("hello".as_bytes()), "<5 bytes omitted>");
+  }
+
+  #[test]
+  fn test_hexdump_preview() {
+    let bytes = vec![0_u8; HEXDUMP_PREVIEW_LEN + 1];
+    let dump = HexDump.to_string(&bytes);
+    assert_eq!(dump.lines().count(), HEXDUMP_PREVIEW_LEN / 16 + 1);
+    assert!(dump.starts_with("00000000: 0000 0000 0000 0000 0000 0000 0000 0000  ................\n"));
+    assert!(dump.ends_with(&format!("... ({} bytes)\n", HEXDUMP_PREVIEW_LEN + 1)));
+  }
+
+  #[test]
+  fn test_not_reversible() {
+    assert!(matches!(HexDump.from_str(""), Err(BinReprError::NotReversible(HexDump))));
+    assert!(matches!(Omit.from_str(""), Err(BinReprError::NotReversible(Omit))));
+    assert!(matches!(Hex.from_str("abc"), Err(BinReprError::HexDecode(_))));
+    assert!(matches!(Hex.from_str("zz"), Err(BinReprError::HexDecode(_))));
+  }
+
+  #[test_matrix([Base64, Base64Std, Hex, HexDump, Omit])]
+  fn parse_names(repr: BinRepr) -> anyhow::Result<()> {
+    assert_eq!(format!("{repr}").parse::<BinRepr>()?, repr);
+    Ok(())
+  }
+
   #[test_matrix(
     ["", "hello", "zeliu<>z,.||kj_(){{}[)c){(m\n\t\0.,()", "診断", "れい"],
-    [Base64]
+    [Base64, Base64Std, Hex]
   )]
 
   fn a_lot_more_tests(input: &str, repr: BinRepr) -> anyhow::Result<()> {
@@ -1,6 +1,6 @@
-use std::time::SystemTime;
-use time::format_description::well_known::Rfc3339 as Rfc3339Format;
-use time::OffsetDateTime;
+use std::{sync::Arc, time::SystemTime};
+use time::format_description::{well_known::Rfc3339 as Rfc3339Format, OwnedFormatItem};
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
 
 #[derive(thiserror::Error, Debug)]
 pub enum DateReprError {
@@ -8,30 +8,186 @@ pub enum DateReprError {
   FormatError(#[from] time::error::Format),
   #[error("Date parse error: {0}")]
   ParseError(#[from] time::error::Parse),
+  #[error("Number parse error: {0}")]
+  NumberParseError(#[from] std::num::ParseFloatError),
+  #[error("Date out of range: {0}")]
+  OutOfRange(#[from] time::error::ComponentRange),
+  #[error("{0:?} is not an epoch based representation")]
+  NotAnEpoch(DateRepr),
+  #[error("Invalid date format description: {0}")]
+  InvalidFormat(#[from] time::error::InvalidFormatDescription),
+  #[error("Invalid timezone offset `{0}` (expected Z or +HH:MM)")]
+  InvalidOffset(String),
+  #[error("Unknown date representation `{0}` (expected one of rfc3339, unix, unix-ms, cocoa, webkit, iso:+HH:MM, format:<strftime>)")]
+  UnknownRepr(String),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Seconds between 1601-01-01 (`WebKit` epoch) and 1970-01-01
+const WEBKIT_EPOCH_OFFSET: i128 = -11_644_473_600;
+/// Seconds between 2001-01-01 (Cocoa / Mac absolute time epoch) and 1970-01-01
+const COCOA_EPOCH_OFFSET: i128 = 978_307_200;
+
+const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub enum DateRepr {
+  #[default]
   Rfc3339,
+  /// Seconds since 1970-01-01
+  UnixSeconds,
+  /// Milliseconds since 1970-01-01
+  UnixMillis,
+  /// Seconds since 2001-01-01 (Cocoa / Mac absolute time, used in Apple databases)
+  CocoaSeconds,
+  /// Microseconds since 1601-01-01 (`WebKit` / Chrome timestamps)
+  WebKitMicros,
+  /// Rfc3339 (ISO 8601) in a fixed timezone
+  IsoOffset(UtcOffset),
+  /// Custom strftime like format (e.g. `%Y-%m-%d %H:%M:%S`), dates are written in UTC
+  Custom(Arc<DateFormat>),
+}
+
+/// A parsed strftime like format description
+#[derive(Debug)]
+pub struct DateFormat {
+  pattern: String,
+  items: OwnedFormatItem,
 }
 
+impl DateFormat {
+  pub fn new(pattern: &str) -> Result<Self, DateReprError> {
+    Ok(Self { pattern: pattern.to_string(), items: time::format_description::parse_strftime_owned(pattern)? })
+  }
+}
+
+impl PartialEq for DateFormat {
+  fn eq(&self, other: &Self) -> bool {
+    self.pattern == other.pattern
+  }
+}
+
+impl Eq for DateFormat {}
+
 impl DateRepr {
+  /// All the epoch based representations
+  pub const EPOCHS: [DateRepr; 4] = [DateRepr::UnixSeconds, DateRepr::UnixMillis, DateRepr::CocoaSeconds, DateRepr::WebKitMicros];
+
   pub fn to_string(&self, datetime: SystemTime) -> Result<String, DateReprError> {
-    Ok(match self {
-      DateRepr::Rfc3339 => Into::<OffsetDateTime>::into(datetime).format(&Rfc3339Format)?,
-    })
+    let datetime: OffsetDateTime = datetime.into();
+    let Some((offset, unit)) = self.epoch() else {
+      return Ok(match self {
+        DateRepr::IsoOffset(offset) => datetime.to_offset(*offset).format(&Rfc3339Format)?,
+        DateRepr::Custom(format) => datetime.format(&format.items)?,
+        _ => datetime.format(&Rfc3339Format)?,
+      });
+    };
+
+    let nanos = datetime.unix_timestamp_nanos() - offset * NANOS_PER_SECOND;
+    if nanos % unit == 0 {
+      Ok((nanos / unit).to_string())
+    } else {
+      #[allow(clippy::cast_precision_loss)]
+      let value = nanos as f64 / unit as f64;
+      Ok(value.to_string())
+    }
   }
+
   pub fn parse(&self, s: &str) -> Result<SystemTime, DateReprError> {
     Ok(match self {
-      DateRepr::Rfc3339 => OffsetDateTime::parse(s, &Rfc3339Format)?.into(),
+      DateRepr::Rfc3339 | DateRepr::IsoOffset(_) => OffsetDateTime::parse(s, &Rfc3339Format)?.into(),
+      DateRepr::Custom(format) => match OffsetDateTime::parse(s, &format.items) {
+        Ok(datetime) => datetime.into(),
+        Err(_) => PrimitiveDateTime::parse(s, &format.items)?.assume_utc().into(),
+      },
+      _ => self.from_timestamp(s.trim().parse()?)?,
+    })
+  }
+
+  /// Converts a number expressed in this epoch representation to a date
+  pub fn from_timestamp(&self, timestamp: f64) -> Result<SystemTime, DateReprError> {
+    let (offset, unit) = self.epoch().ok_or_else(|| DateReprError::NotAnEpoch(self.clone()))?;
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    let nanos = (timestamp * unit as f64) as i128 + offset * NANOS_PER_SECOND;
+    Ok(OffsetDateTime::from_unix_timestamp_nanos(nanos)?.into())
+  }
+
+  #[must_use]
+  pub fn is_epoch(&self) -> bool {
+    self.epoch().is_some()
+  }
+
+  /// Returns the epoch offset from 1970-01-01 in seconds, and the unit in nanoseconds
+  fn epoch(&self) -> Option<(i128, i128)> {
+    match self {
+      DateRepr::Rfc3339 | DateRepr::IsoOffset(_) | DateRepr::Custom(_) => None,
+      DateRepr::UnixSeconds => Some((0, NANOS_PER_SECOND)),
+      DateRepr::UnixMillis => Some((0, 1_000_000)),
+      DateRepr::CocoaSeconds => Some((COCOA_EPOCH_OFFSET, NANOS_PER_SECOND)),
+      DateRepr::WebKitMicros => Some((WEBKIT_EPOCH_OFFSET, 1_000)),
+    }
+  }
+}
+
+impl std::str::FromStr for DateRepr {
+  type Err = DateReprError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if let Some(offset) = s.strip_prefix("iso:") {
+      return Ok(DateRepr::IsoOffset(parse_offset(offset)?));
+    }
+    if let Some(pattern) = s.strip_prefix("format:") {
+      return Ok(DateRepr::Custom(Arc::new(DateFormat::new(pattern)?)));
+    }
+    Ok(match s {
+      "rfc3339" => DateRepr::Rfc3339,
+      "unix" => DateRepr::UnixSeconds,
+      "unix-ms" => DateRepr::UnixMillis,
+      "cocoa" => DateRepr::CocoaSeconds,
+      "webkit" => DateRepr::WebKitMicros,
+      _ => return Err(DateReprError::UnknownRepr(s.to_string())),
     })
   }
 }
 
+impl std::fmt::Display for DateRepr {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      DateRepr::Rfc3339 => write!(f, "rfc3339"),
+      DateRepr::UnixSeconds => write!(f, "unix"),
+      DateRepr::UnixMillis => write!(f, "unix-ms"),
+      DateRepr::CocoaSeconds => write!(f, "cocoa"),
+      DateRepr::WebKitMicros => write!(f, "webkit"),
+      DateRepr::IsoOffset(offset) => {
+        let (hours, minutes, _) = offset.as_hms();
+        let sign = if offset.is_negative() { '-' } else { '+' };
+        write!(f, "iso:{sign}{:02}:{:02}", hours.abs(), minutes.abs())
+      }
+      DateRepr::Custom(format) => write!(f, "format:{}", format.pattern),
+    }
+  }
+}
+
+/// Parses `Z`, `+HH:MM`, `-HH:MM` or `+HH` offsets
+fn parse_offset(s: &str) -> Result<UtcOffset, DateReprError> {
+  let invalid = || DateReprError::InvalidOffset(s.to_string());
+  if s == "Z" {
+    return Ok(UtcOffset::UTC);
+  }
+  let (sign, hm) = match s.split_at_checked(1) {
+    Some(("+", hm)) => (1, hm),
+    Some(("-", hm)) => (-1, hm),
+    _ => return Err(invalid()),
+  };
+  let (hours, minutes) = hm.split_once(':').unwrap_or((hm, "0"));
+  let hours: i8 = hours.parse().map_err(|_| invalid())?;
+  let minutes: i8 = minutes.parse().map_err(|_| invalid())?;
+  UtcOffset::from_hms(sign * hours, sign * minutes, 0).map_err(|_| invalid())
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  use test_case::test_matrix;
+  use test_case::{test_case, test_matrix};
   use DateRepr::*;
 
   #[test]
@@ -49,12 +205,81 @@ mod tests {
 
   #[test_matrix(
     [("1970-01-01T00:00:00Z", "1970-01-01T00:00:00Z"), ("2021-01-01T00:00:00Z", "2021-01-01T00:00:00Z"), ("2021-01-01T00:00:00+02:12", "2020-12-31T21:48:00Z")],
-    [Rfc3339]
+    [&Rfc3339]
   )]
-  fn test_rfc3339_parse((s, expected): (&str, &str), repr: DateRepr) -> anyhow::Result<()> {
+  fn test_rfc3339_parse((s, expected): (&str, &str), repr: &DateRepr) -> anyhow::Result<()> {
     let datetime = repr.parse(s)?;
     let str_repr = repr.to_string(datetime)?;
     assert_eq!(str_repr, expected);
     Ok(())
   }
+
+  #[test_case(&UnixSeconds, 1_609_459_200.0 ; "unix seconds")]
+  #[test_case(&UnixMillis, 1_609_459_200_000.0 ; "unix millis")]
+  #[test_case(&CocoaSeconds, 631_152_000.0 ; "cocoa")]
+  #[test_case(&WebKitMicros, 13_253_932_800_000_000.0 ; "webkit")]
+  fn test_epochs(repr: &DateRepr, timestamp: f64) -> anyhow::Result<()> {
+    let datetime = repr.from_timestamp(timestamp)?;
+    assert_eq!(Rfc3339.to_string(datetime)?, "2021-01-01T00:00:00Z");
+    assert_eq!(repr.to_string(datetime)?, timestamp.to_string());
+    assert_eq!(repr.parse(&timestamp.to_string())?, datetime);
+    Ok(())
+  }
+
+  #[test]
+  fn test_epoch_fractions() -> anyhow::Result<()> {
+    let datetime = CocoaSeconds.from_timestamp(0.5)?;
+    assert_eq!(Rfc3339.to_string(datetime)?, "2001-01-01T00:00:00.5Z");
+    assert_eq!(CocoaSeconds.to_string(datetime)?, "0.5");
+    Ok(())
+  }
+
+  #[test]
+  fn test_iso_offset() -> anyhow::Result<()> {
+    let repr: DateRepr = "iso:+02:30".parse()?;
+    let datetime = repr.parse("2021-01-01T00:00:00Z")?;
+    assert_eq!(repr.to_string(datetime)?, "2021-01-01T02:30:00+02:30");
+    assert_eq!(format!("{repr}"), "iso:+02:30");
+
+    let repr: DateRepr = "iso:-05".parse()?;
+    assert_eq!(repr.to_string(datetime)?, "2020-12-31T19:00:00-05:00");
+    assert_eq!(format!("{repr}"), "iso:-05:00");
+    Ok(())
+  }
+
+  #[test]
+  fn test_custom_format() -> anyhow::Result<()> {
+    let repr: DateRepr = "format:%Y-%m-%d %H:%M:%S".parse()?;
+    let datetime = Rfc3339.parse("2021-01-01T10:20:30Z")?;
+    assert_eq!(repr.to_string(datetime)?, "2021-01-01 10:20:30");
+    assert_eq!(repr.parse("2021-01-01 10:20:30")?, datetime);
+    assert_eq!(format!("{repr}"), "format:%Y-%m-%d %H:%M:%S");
+    Ok(())
+  }
+
+  #[test_case("rfc3339")]
+  #[test_case("unix")]
+  #[test_case("unix-ms")]
+  #[test_case("cocoa")]
+  #[test_case("webkit")]
+  #[test_case("iso:+00:00")]
+  #[test_case("format:%d/%m/%Y")]
+  fn parse_names(name: &str) -> anyhow::Result<()> {
+    assert_eq!(format!("{}", name.parse::<DateRepr>()?), name);
+    Ok(())
+  }
+
+  #[test_case("nope")]
+  #[test_case("iso:0200")]
+  #[test_case("iso:+26:00")]
+  #[test_case("format:%Q")]
+  fn parse_bad_names(name: &str) {
+    assert!(name.parse::<DateRepr>().is_err());
+  }
+
+  #[test]
+  fn rfc3339_is_not_an_epoch() {
+    assert!(!Rfc3339.is_epoch());
+    assert!(matches!(Rfc3339.from_timestamp(0.0), Err(DateReprError::NotAnEpoch(Rfc3339))));
+  }
 }
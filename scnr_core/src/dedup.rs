@@ -0,0 +1,315 @@
+//! Duplicate nodes detection: the original bytes of every scanned node are hashed, a node already seen in the scan
+//! (the same plist in several backups...) is not scanned again.
+//!
+//! A node whose scan fails is not the first occurrence of its bytes: the next copy is scanned in its place.
+//!
+//! The hash is known before the scan of the node: a node that is only streamed (an archive member) is read in memory
+//! first, and hashed there. The files and the leaf members are deduplicated, not the archives nested in other archives
+//! (they would be held in memory whole), only their members are.
+
+use crate::{read::SeekableScanReader, ScanPath};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{
+  collections::HashMap,
+  io::{Seek, SeekFrom},
+  sync::{Mutex, PoisonError},
+};
+
+/// Key of the reference contents sent in place of the duplicates
+pub const DUPLICATE_OF_KEY: &str = "duplicate_of";
+
+/// What is sent in place of a duplicate node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupMode {
+  /// A json reference to the first occurrence: `{"duplicate_of": "<path>", "sha256": "<hash>"}`
+  #[default]
+  Reference,
+  /// Nothing
+  Omit,
+}
+
+/// Nodes sharing the same bytes, the one that was scanned first, then the others in scan order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateGroup {
+  pub sha256: String,
+  pub size: u64,
+  pub paths: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Deduplicator {
+  mode: DedupMode,
+  /// Occurrences by hash, in scan order
+  seen: Mutex<HashMap<String, Occurrences>>,
+}
+
+#[derive(Debug)]
+struct Occurrences {
+  size: u64,
+  paths: Vec<ScanPath>,
+  /// Index of the occurrence that is scanned, the others refer to it
+  first: Option<usize>,
+}
+
+impl Deduplicator {
+  #[must_use]
+  pub fn new(mode: DedupMode) -> Self {
+    Self { mode, seen: Mutex::default() }
+  }
+
+  #[must_use]
+  pub fn mode(&self) -> DedupMode {
+    self.mode
+  }
+
+  /// Records the node, returns the path of its first occurrence if it was already seen (`None` when the node is the
+  /// first occurrence, it should be scanned)
+  pub fn check(&self, sha256: &str, size: u64, path: &ScanPath) -> Option<ScanPath> {
+    let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+    let occurrences = seen
+      .entry(sha256.to_string())
+      .or_insert_with(|| Occurrences { size, paths: vec![], first: None });
+    occurrences.paths.push(path.clone());
+    if let Some(first) = occurrences.first {
+      return Some(occurrences.paths[first].clone());
+    }
+    occurrences.first = Some(occurrences.paths.len() - 1);
+    None
+  }
+
+  /// The scan of the first occurrence failed, the next copy will be the first occurrence
+  pub fn release(&self, sha256: &str, path: &ScanPath) {
+    let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(occurrences) = seen.get_mut(sha256) {
+      if occurrences.first.is_some_and(|first| occurrences.paths[first] == *path) {
+        occurrences.first = None;
+      }
+    }
+  }
+
+  /// Groups of nodes seen more than once, sorted by first path
+  #[must_use]
+  pub fn duplicate_groups(&self) -> Vec<DuplicateGroup> {
+    let seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut groups = seen
+      .iter()
+      .filter(|(_, occurrences)| occurrences.paths.len() > 1)
+      .map(|(sha256, occurrences)| {
+        let mut paths = occurrences.paths.iter().map(ToString::to_string).collect::<Vec<_>>();
+        if let Some(first) = occurrences.first {
+          paths[..=first].rotate_right(1);
+        }
+        DuplicateGroup { sha256: sha256.clone(), size: occurrences.size, paths }
+      })
+      .collect::<Vec<_>>();
+    groups.sort_by(|a, b| a.paths.cmp(&b.paths));
+    groups
+  }
+}
+
+/// Size and sha256 of the original bytes of a node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NodeDigest {
+  pub size: u64,
+  pub sha256: String,
+}
+
+/// Returns the sha256 and size of the reader bytes, and rewinds it
+pub(crate) fn hash_and_rewind(reader: &mut SeekableScanReader<'_>) -> std::io::Result<NodeDigest> {
+  let mut hasher = Sha256::new();
+  let size = std::io::copy(reader, &mut hasher)?;
+  reader.seek(SeekFrom::Start(0))?;
+  Ok(NodeDigest { size, sha256: format!("{:x}", hasher.finalize()) })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    plugins::{json::JsonPlugin, DefaultPluginPicker},
+    tests_helpers::get_samples_path,
+    Content, Scanner,
+  };
+  use pretty_assertions::assert_eq;
+  use std::sync::Arc;
+  use test_case::test_case;
+
+  #[test]
+  fn groups() {
+    let dedup = Deduplicator::default();
+    let path = |p: &str| ScanPath::parse(p);
+    assert_eq!(dedup.check("h1", 1, &path("b.zip!/x")), None);
+    assert_eq!(dedup.check("h2", 2, &path("y")), None);
+    assert_eq!(dedup.check("h1", 1, &path("a.zip!/x")), Some(path("b.zip!/x")));
+    assert_eq!(
+      dedup.duplicate_groups(),
+      [DuplicateGroup { sha256: "h1".into(), size: 1, paths: vec!["b.zip!/x".into(), "a.zip!/x".into()] }]
+    );
+  }
+
+  #[test]
+  fn failed_first_occurrences() {
+    let dedup = Deduplicator::default();
+    let path = |p: &str| ScanPath::parse(p);
+    assert_eq!(dedup.check("h1", 1, &path("a")), None);
+    dedup.release("h1", &path("a"));
+    // the next copy is scanned, the following ones refer to it
+    assert_eq!(dedup.check("h1", 1, &path("b")), None);
+    assert_eq!(dedup.check("h1", 1, &path("c")), Some(path("b")));
+    dedup.release("h1", &path("c"));
+    assert_eq!(dedup.check("h1", 1, &path("d")), Some(path("b")));
+    assert_eq!(dedup.duplicate_groups()[0].paths, ["b", "a", "c", "d"]);
+  }
+
+  /// Fails on `a.json`
+  #[derive(Debug)]
+  struct FailsOnA;
+
+  impl crate::plugins::ScanPlugin for FailsOnA {
+    fn scan(&self, context: &crate::ScanContext, reader: crate::ScanReader<'_>) -> crate::plugins::ScanPluginResult {
+      if context.current_scan_path().to_string() == "a.json" {
+        anyhow::bail!("unlucky");
+      }
+      JsonPlugin.scan(context, reader)
+    }
+  }
+
+  #[test]
+  fn copies_of_failed_nodes_are_scanned() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    for name in ["a.json", "b.json", "c.json"] {
+      std::fs::write(dir.path().join(name), r#"{"a": 1}"#)?;
+    }
+
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.json", FailsOnA)?
+      .build_with_defaults()?;
+    let results = Scanner::new(&dir.path().display(), picker)
+      .with_dedup(Arc::new(Deduplicator::new(DedupMode::Reference)))
+      .get_all()?
+      .into_iter()
+      .map(|result| result.map(|content| (content.path.to_string(), content.duplicate_of.map(|path| path.to_string()))))
+      .collect::<Vec<_>>();
+
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_err());
+    assert_eq!(
+      results[1..].iter().flatten().cloned().collect::<Vec<_>>(),
+      [("b.json".to_string(), None), ("c.json".to_string(), Some("b.json".to_string()))]
+    );
+
+    Ok(())
+  }
+
+  #[test_case(DedupMode::Reference ; "reference")]
+  #[test_case(DedupMode::Omit ; "omit")]
+  fn duplicated_files(mode: DedupMode) -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("a.json"), r#"{"a": 1}"#)?;
+    std::fs::write(dir.path().join("b.json"), r#"{"a": 1}"#)?;
+    std::fs::write(dir.path().join("c.json"), r#"{"c": 1}"#)?;
+
+    let dedup = Arc::new(Deduplicator::new(mode));
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.json", JsonPlugin)?
+      .build_with_defaults()?;
+    let contents = Scanner::new(&dir.path().display(), picker)
+      .with_dedup(dedup.clone())
+      .get_all_oks()?;
+
+    let contents = contents
+      .into_iter()
+      .map(|content| (content.path.to_string(), content.duplicate_of.map(|path| path.to_string()), content.content))
+      .collect::<Vec<_>>();
+    let sha256 = dedup.duplicate_groups()[0].sha256.clone();
+    let mut expected = vec![
+      ("a.json".to_string(), None, Content::Json(serde_json::json!({"a": 1}))),
+      ("b.json".to_string(), Some("a.json".to_string()), Content::Json(serde_json::json!({"duplicate_of": "a.json", "sha256": sha256}))),
+      ("c.json".to_string(), None, Content::Json(serde_json::json!({"c": 1}))),
+    ];
+    if mode == DedupMode::Omit {
+      expected.remove(1);
+    }
+    assert_eq!(contents, expected);
+
+    Ok(())
+  }
+
+  #[test]
+  fn duplicated_archive_is_not_opened() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let dir = tempfile::tempdir()?;
+    std::fs::copy(format!("{samples}/z.zip"), dir.path().join("1.zip"))?;
+    std::fs::copy(format!("{samples}/z.zip"), dir.path().join("2.zip"))?;
+
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.zip", crate::plugins::zip::ZipPlugin)?
+      .build_with_defaults()?;
+    let dedup = Arc::new(Deduplicator::new(DedupMode::Omit));
+    let contents = Scanner::new(&dir.path().display(), picker)
+      .with_dedup(dedup.clone())
+      .get_all_oks()?;
+
+    assert!(contents.iter().all(|content| content.path.to_string().starts_with("1.zip!/")));
+    assert_eq!(dedup.duplicate_groups()[0].paths, ["1.zip", "2.zip"]);
+
+    Ok(())
+  }
+
+  #[test]
+  fn nested_archives_are_not_buffered() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let dir = tempfile::tempdir()?;
+    let mut tar =
+      tar::Builder::new(flate2::write::GzEncoder::new(std::fs::File::create(dir.path().join("x.tar.gz"))?, flate2::Compression::default()));
+    tar.append_path_with_name(format!("{samples}/z.zip"), "1.zip")?;
+    tar.append_path_with_name(format!("{samples}/z.zip"), "2.zip")?;
+    tar.into_inner()?.finish()?;
+
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.tar.gz", crate::plugins::targz::TarGzPlugin)?
+      .push_plugin("*.zip", crate::plugins::zip::ZipPlugin)?
+      .build_with_defaults()?;
+    let dedup = Arc::new(Deduplicator::new(DedupMode::Omit));
+    let contents = Scanner::new(&dir.path().display(), picker)
+      .with_dedup(dedup.clone())
+      .get_all_oks()?;
+
+    // both archives are opened, the members of the second one are the duplicates
+    assert!(contents
+      .iter()
+      .all(|content| content.path.to_string().starts_with("x.tar.gz!/1.zip!/")));
+    let groups = dedup.duplicate_groups();
+    assert_eq!(groups.len(), contents.len());
+    assert!(groups
+      .iter()
+      .all(|group| group.paths.len() == 2 && group.paths[1].starts_with("x.tar.gz!/2.zip!/")));
+
+    Ok(())
+  }
+
+  #[test]
+  fn node_digests() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("a.json"), r#"{"a": 1}"#)?;
+    std::fs::copy(format!("{samples}/z.zip"), dir.path().join("b.zip"))?;
+
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.json", JsonPlugin)?
+      .push_plugin("*.zip", crate::plugins::zip::ZipPlugin)?
+      .build_with_defaults()?;
+    let contents = Scanner::new(&dir.path().display(), picker).with_node_digests().get_all_oks()?;
+
+    let a = contents.iter().find(|content| content.path.to_string() == "a.json");
+    let digest = NodeDigest { size: 8, sha256: "f9d86028c6e0d64e225186f96acb69338b2c59764df79162107f5c4bb34d1310".into() };
+    assert_eq!(a.and_then(|content| content.origin.clone()), Some(digest));
+    // the members are digested, not the archive
+    assert!(contents
+      .iter()
+      .all(|content| content.origin.as_ref().is_some_and(|origin| origin.sha256.len() == 64)));
+
+    Ok(())
+  }
+}
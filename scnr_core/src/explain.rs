@@ -0,0 +1,207 @@
+//! Explanation of the scan decisions for one nested path ([`crate::Scanner::explain`]): the plugin picked for each
+//! node on the way to the path (with the picker rules evaluated to pick it), and whether the filter lets the scan through.
+//! Nothing is opened, the decisions only depend on the paths.
+
+use crate::{
+  filter::ScanFilter,
+  listing::short_plugin_name,
+  plugins::{PluginPicker, ScanPlugin},
+  scan_path::{PathSegment, SegmentKind},
+  ScanPath,
+};
+use std::path::Path;
+
+/// A rule of the picker, matched against the path of a node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleExplanation {
+  /// `None` for the starter plugins, which are never picked to scan a node
+  pub glob: Option<String>,
+  pub plugin: String,
+  pub priority: i32,
+  /// Plugins tried in turn when the plugin fails
+  pub fallbacks: Vec<String>,
+  pub matches: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+  /// Directory walked by the file system starter
+  Directory,
+  File,
+  ArchiveMember,
+  /// Node sent by the plugin of its parent (database table...), children are not filtered
+  PluginChild,
+}
+
+/// Decisions of the scan for one node on the way to the explained path
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeExplanation {
+  pub path: ScanPath,
+  pub kind: NodeKind,
+  /// Rules of the picker in evaluation order (none for directories and plugin children)
+  pub rules: Vec<RuleExplanation>,
+  /// Plugin of the first matching rule
+  pub plugin: Option<String>,
+  /// The plugin opens the node to scan its members
+  pub container: bool,
+  /// [`ScanFilter::should_scan_path`], decides for the nodes converted by their plugin
+  pub should_scan: bool,
+  /// [`ScanFilter::can_recurse`], decides for the directories and containers
+  pub can_recurse: bool,
+  /// The scan gets to this node (the nodes before it are all descended)
+  pub reached: bool,
+}
+
+impl NodeExplanation {
+  /// The filter admits the node: directories and containers are descended, the other nodes are converted
+  #[must_use]
+  pub fn wanted(&self) -> bool {
+    match self.kind {
+      NodeKind::Directory => self.can_recurse,
+      NodeKind::PluginChild => true,
+      NodeKind::File | NodeKind::ArchiveMember if self.plugin.is_none() => false,
+      NodeKind::File | NodeKind::ArchiveMember if self.container => self.can_recurse,
+      NodeKind::File | NodeKind::ArchiveMember => self.should_scan,
+    }
+  }
+}
+
+/// Explains the nodes leading to `path` (rendered like `dir/a.zip!/b.db#table`, relative to the input)
+#[must_use]
+pub fn explain(picker: &dyn PluginPicker, filter: &dyn ScanFilter, path: &ScanPath) -> Vec<NodeExplanation> {
+  let mut nodes = vec![];
+  let mut current = ScanPath::default();
+
+  for PathSegment { kind, name } in path.segments() {
+    if current.is_empty() && *kind == SegmentKind::FileSystem {
+      let mut directories = Path::new(name).ancestors().skip(1).collect::<Vec<_>>();
+      directories.retain(|directory| !directory.as_os_str().is_empty());
+      for directory in directories.into_iter().rev() {
+        nodes.push(explain_node(picker, filter, ScanPath::default().join(SegmentKind::FileSystem, directory), NodeKind::Directory));
+      }
+    }
+
+    current = current.join(*kind, name);
+    let node_kind = match kind {
+      SegmentKind::FileSystem => NodeKind::File,
+      SegmentKind::ArchiveMember => NodeKind::ArchiveMember,
+      SegmentKind::PluginChild => NodeKind::PluginChild,
+    };
+    nodes.push(explain_node(picker, filter, current.clone(), node_kind));
+  }
+
+  let mut reached = true;
+  for i in 0..nodes.len() {
+    nodes[i].reached = reached;
+    let next_is_child = nodes.get(i + 1).is_some_and(|next| next.kind == NodeKind::PluginChild);
+    reached = reached && nodes[i].wanted() && (nodes[i].kind == NodeKind::Directory || nodes[i].container || next_is_child);
+  }
+
+  nodes
+}
+
+fn explain_node(picker: &dyn PluginPicker, filter: &dyn ScanFilter, path: ScanPath, kind: NodeKind) -> NodeExplanation {
+  let rel_path = path.to_path_buf();
+  let rules = match kind {
+    NodeKind::File | NodeKind::ArchiveMember => picker.explain_scan(&rel_path),
+    NodeKind::Directory | NodeKind::PluginChild => vec![],
+  };
+  let first_match = rules.iter().find(|rule| rule.matches).map(|rule| rule.plugin);
+
+  NodeExplanation {
+    kind,
+    rules: rules
+      .iter()
+      .map(|rule| RuleExplanation {
+        glob: rule.glob.map(ToString::to_string),
+        plugin: short_plugin_name(rule.plugin.name()).to_string(),
+        priority: rule.priority,
+        fallbacks: rule
+          .fallbacks
+          .iter()
+          .map(|fallback| short_plugin_name(fallback.name()).to_string())
+          .collect(),
+        matches: rule.matches,
+      })
+      .collect(),
+    plugin: first_match.map(|plugin| short_plugin_name(plugin.name()).to_string()),
+    container: first_match.is_some_and(ScanPlugin::can_recurse),
+    should_scan: filter.should_scan_path(&rel_path, &path),
+    can_recurse: filter.can_recurse(&rel_path),
+    reached: false,
+    path,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    filter::{Glob, YesMan},
+    plugins::{json::JsonPlugin, text::TextPlugin, zip::ZipPlugin, DefaultPluginPicker},
+  };
+  use pretty_assertions::assert_eq;
+
+  fn picker() -> anyhow::Result<DefaultPluginPicker> {
+    Ok(
+      DefaultPluginPicker::builder()
+        .push_plugin("*.zip", ZipPlugin)?
+        .push_plugin("*.txt", TextPlugin)?
+        .push_plugin("*.json", JsonPlugin)?
+        .build_with_defaults()?,
+    )
+  }
+
+  /// (path, plugin, wanted, reached) of the nodes
+  fn summary(nodes: &[NodeExplanation]) -> Vec<(String, Option<&str>, bool, bool)> {
+    nodes
+      .iter()
+      .map(|node| (node.path.to_string(), node.plugin.as_deref(), node.wanted(), node.reached))
+      .collect()
+  }
+
+  #[test]
+  fn nested_path() -> anyhow::Result<()> {
+    let nodes = explain(&picker()?, &YesMan, &ScanPath::parse("x/y/z.zip!/z/d.txt"));
+    assert_eq!(
+      summary(&nodes),
+      [
+        ("x".to_string(), None, true, true),
+        ("x/y".to_string(), None, true, true),
+        ("x/y/z.zip".to_string(), Some("ZipPlugin"), true, true),
+        ("x/y/z.zip!/z/d.txt".to_string(), Some("TextPlugin"), true, true),
+      ]
+    );
+
+    let rules = &nodes[3].rules;
+    assert_eq!(rules.len(), 6);
+    // `*.json` is more specific than `*.zip`, it is evaluated first
+    assert_eq!(rules[0].glob.as_deref(), Some("*.json"));
+    assert_eq!(
+      rules[2],
+      RuleExplanation { glob: Some("*.txt".into()), plugin: "TextPlugin".into(), priority: 0, fallbacks: vec![], matches: true }
+    );
+    assert_eq!(rules[3], RuleExplanation { glob: None, plugin: "StdinPlugin".into(), priority: 0, fallbacks: vec![], matches: false });
+    assert!(rules[5].matches, "the last resort plugin matches everything");
+
+    Ok(())
+  }
+
+  #[test]
+  fn filtered_path() -> anyhow::Result<()> {
+    let filter = Glob::new("**/*.json")?;
+    let nodes = explain(&picker()?, &filter, &ScanPath::parse("z.zip!/z/d.txt"));
+    assert_eq!(
+      summary(&nodes),
+      [("z.zip".to_string(), Some("ZipPlugin"), true, true), ("z.zip!/z/d.txt".to_string(), Some("TextPlugin"), false, true),]
+    );
+    assert_eq!((nodes[1].should_scan, nodes[1].can_recurse), (false, true));
+
+    // a json file is not a container, nothing is reached inside it
+    let nodes = explain(&picker()?, &filter, &ScanPath::parse("a.json!/b.json"));
+    assert_eq!(summary(&nodes)[1], ("a.json!/b.json".to_string(), Some("JsonPlugin"), true, false));
+
+    Ok(())
+  }
+}
@@ -1,7 +1,7 @@
 use glob::{MatchOptions, Pattern};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::ScanError;
+use crate::{ScanError, ScanPath};
 
 #[must_use]
 pub fn case_insensitive() -> MatchOptions {
@@ -10,6 +10,16 @@ pub fn case_insensitive() -> MatchOptions {
 
 pub trait ScanFilter: Send + Sync {
   fn should_scan(&self, path: &Path) -> bool;
+
+  /// Same as [`Self::should_scan`], with the container boundaries of the path (`a.zip!/b.txt`)
+  fn should_scan_path(&self, path: &Path, _scan_path: &ScanPath) -> bool {
+    self.should_scan(path)
+  }
+
+  /// Returns false if nothing interesting can be found under this path (directory, archive...), so it is not descended
+  fn can_recurse(&self, _path: &Path) -> bool {
+    true
+  }
 }
 
 pub struct YesMan;
@@ -45,6 +55,41 @@ impl ScanFilter for Glob {
   fn should_scan(&self, path: &Path) -> bool {
     self.globs.iter().any(|glob| glob.matches_path_with(path, case_insensitive()))
   }
+
+  /// The globs can match the plain path (`a.zip/*.txt`) or the rendered one (`a.zip!/*.txt`)
+  fn should_scan_path(&self, path: &Path, scan_path: &ScanPath) -> bool {
+    let rendered = scan_path.to_string();
+    self.should_scan(path) || self.globs.iter().any(|glob| glob.matches_with(&rendered, case_insensitive()))
+  }
+}
+
+/// Only scans one nested path (plain `a.zip/b.txt` or rendered `a.zip!/b.txt`, see [`ScanPath::designates`]), and only
+/// descends into its ancestors.
+/// Its ancestors are also scanned, for plugins sending child contents (like database tables).
+pub struct NestedPath {
+  target: ScanPath,
+  target_path: PathBuf,
+}
+
+impl NestedPath {
+  #[must_use]
+  pub fn new(target: ScanPath) -> Self {
+    Self { target_path: target.to_path_buf(), target }
+  }
+}
+
+impl ScanFilter for NestedPath {
+  fn should_scan(&self, path: &Path) -> bool {
+    self.target_path.starts_with(path)
+  }
+
+  fn should_scan_path(&self, path: &Path, scan_path: &ScanPath) -> bool {
+    self.target.designates_descendant_of(path, scan_path)
+  }
+
+  fn can_recurse(&self, path: &Path) -> bool {
+    self.target_path.starts_with(path) && path != self.target_path
+  }
 }
 
 #[cfg(test)]
@@ -84,4 +129,25 @@ mod tests {
     assert!(!filter.should_scan(Path::new("foo.bin")));
     assert!(!filter.should_scan(Path::new("plop/FOO.BIN")));
   }
+
+  #[test]
+  fn nested_path() {
+    let filter = NestedPath::new(ScanPath::parse("bundle.tar.gz/logs/app.zip/config.plist"));
+    assert!(filter.should_scan(Path::new("bundle.tar.gz/logs/./app.zip/config.plist")));
+    assert!(filter.should_scan(Path::new("bundle.tar.gz/logs/app.zip")));
+    assert!(!filter.should_scan(Path::new("bundle.tar.gz/logs/other.zip")));
+    assert!(filter.can_recurse(Path::new("bundle.tar.gz")));
+    assert!(filter.can_recurse(Path::new("bundle.tar.gz/logs/app.zip")));
+    assert!(!filter.can_recurse(Path::new("bundle.tar.gz/logs/other.zip")));
+    assert!(!filter.can_recurse(Path::new("bundle.tar.gz/logs/app.zip/config.plist")));
+  }
+
+  #[test]
+  fn glob_rendered_path() {
+    let filter = Glob::new("*.zip!/*.txt").unwrap();
+    let archive_member = ScanPath::parse("dir/a.zip!/b.txt");
+    assert!(filter.should_scan_path(&archive_member.to_path_buf(), &archive_member));
+    let real_dir = ScanPath::parse("dir/a.zip/b.txt");
+    assert!(!filter.should_scan_path(&real_dir.to_path_buf(), &real_dir));
+  }
 }
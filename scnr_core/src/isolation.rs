@@ -0,0 +1,139 @@
+//! Isolation of the plugins: a panicking plugin only fails its node, and leaf plugins can be given a time limit per node.
+
+use crate::{plugins::ScanPlugin, read::SeekableScanReader, ScanContent, ScanContext, ScanError, ScanPath, ScanPluginResult};
+use std::{
+  fs::File,
+  io::{Cursor, Read},
+  panic::AssertUnwindSafe,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+
+enum WorkerEvent {
+  Content(Result<Result<ScanContent, ScanError>, flume::RecvError>),
+  Done(Result<Result<(), ScanError>, flume::RecvError>),
+}
+
+/// The node given to a worker: a file it opens again, or the bytes of a node already in memory
+enum WorkerNode {
+  File(PathBuf),
+  Bytes(Vec<u8>),
+}
+
+/// Runs a plugin, a panic is turned into a [`ScanError::PluginPanicked`] error of the node
+pub(crate) fn catch_panic(plugin: &dyn ScanPlugin, path: &ScanPath, scan: impl FnOnce() -> ScanPluginResult) -> Result<(), ScanError> {
+  match std::panic::catch_unwind(AssertUnwindSafe(scan)) {
+    Ok(result) => Ok(result?),
+    Err(payload) => {
+      let message = payload
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string());
+      Err(ScanError::PluginPanicked { plugin: plugin.name(), path: path.clone(), message })
+    }
+  }
+}
+
+impl ScanContext {
+  /// Scans a leaf node in a worker thread, and gives up after the timeout: the contents the plugin sends afterwards are dropped.
+  /// Threads cannot be killed, the worker runs until the plugin returns, but it does not hold the scan results open.
+  /// A file (`file` is its path) is opened again by the worker, the other nodes are moved to it (archive members are
+  /// already buffered).
+  pub(crate) fn scan_with_timeout(
+    &self,
+    plugin: &dyn ScanPlugin,
+    timeout: Duration,
+    reader: &mut SeekableScanReader<'_>,
+    file: Option<&Path>,
+  ) -> Result<(), ScanError> {
+    let node = match (file, reader) {
+      (Some(file), _) => WorkerNode::File(file.to_path_buf()),
+      (None, SeekableScanReader::ReadOnly(cursor)) => WorkerNode::Bytes(std::mem::take(cursor.get_mut())),
+      (None, reader) => {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        WorkerNode::Bytes(bytes)
+      }
+    };
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, worker_contents) = flume::bounded(10);
+    let mut worker_context = self.clone();
+    worker_context.cancelled = Some(cancelled.clone());
+    worker_context.sender = sender;
+    let (done, done_receiver) = flume::bounded(1);
+
+    std::thread::spawn(move || {
+      // the worker owns a handle on the picker, it picks the plugin again
+      let picker = worker_context.plugin_picker.clone();
+      let result = match (picker.pick_scan(&worker_context), node) {
+        (None, _) => Ok(()),
+        (Some(plugin), WorkerNode::File(path)) => File::open(path)
+          .map_err(ScanError::from)
+          .and_then(|mut file| worker_context.scan_isolated(plugin, &mut SeekableScanReader::ReadSeek(&mut file))),
+        (Some(plugin), WorkerNode::Bytes(bytes)) => {
+          worker_context.scan_isolated(plugin, &mut SeekableScanReader::ReadOnly(Cursor::new(bytes)))
+        }
+      };
+      // sent before the contents channel is closed (by the drop of the context): nobody is waiting anymore if the node
+      // timed out
+      let _ = done.send(result);
+      drop(worker_context);
+    });
+
+    // forwards the contents of the worker until it is done or the node times out
+    let deadline = Instant::now() + timeout;
+    loop {
+      let event = flume::Selector::new()
+        .recv(&worker_contents, WorkerEvent::Content)
+        .recv(&done_receiver, WorkerEvent::Done)
+        .wait_deadline(deadline);
+      match event {
+        Ok(WorkerEvent::Content(Ok(content))) => self.send(content)?,
+        // the worker ended, its result is on its way
+        Ok(WorkerEvent::Content(Err(flume::RecvError::Disconnected))) => {
+          return match done_receiver.recv_deadline(deadline) {
+            Ok(result) => result,
+            Err(flume::RecvTimeoutError::Disconnected) => Err(self.worker_ended(plugin)),
+            Err(flume::RecvTimeoutError::Timeout) => Err(self.timed_out(plugin, timeout, &cancelled)),
+          };
+        }
+        Ok(WorkerEvent::Done(Ok(result))) => {
+          for content in worker_contents.drain() {
+            self.send(content)?;
+          }
+          return result;
+        }
+        Ok(WorkerEvent::Done(Err(flume::RecvError::Disconnected))) => return Err(self.worker_ended(plugin)),
+        Err(flume::select::SelectError::Timeout) => return Err(self.timed_out(plugin, timeout, &cancelled)),
+      }
+    }
+  }
+
+  fn scan_isolated(&self, plugin: &dyn ScanPlugin, reader: &mut SeekableScanReader<'_>) -> Result<(), ScanError> {
+    let fallbacks = self.plugin_picker.pick_fallbacks(self);
+    catch_panic(plugin, &self.scan_path, || self.scan_with_fallbacks(plugin, &fallbacks, reader))
+  }
+
+  fn worker_ended(&self, plugin: &dyn ScanPlugin) -> ScanError {
+    ScanError::PluginPanicked {
+      plugin: plugin.name(),
+      path: self.scan_path.clone(),
+      message: "the worker thread ended without a result".to_string(),
+    }
+  }
+
+  fn timed_out(&self, plugin: &dyn ScanPlugin, timeout: Duration, cancelled: &AtomicBool) -> ScanError {
+    cancelled.store(true, Ordering::Relaxed);
+    ScanError::Timeout { plugin: plugin.name(), path: self.scan_path.clone(), timeout }
+  }
+
+  pub(crate) fn is_cancelled(&self) -> bool {
+    self.cancelled.as_ref().is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+  }
+}
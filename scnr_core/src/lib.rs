@@ -2,26 +2,47 @@
 #![deny(clippy::expect_used, clippy::unwrap_used, clippy::panic)]
 
 use flume::Sender;
-use std::{io::Read, path::PathBuf, sync::Arc};
+use std::{
+  io::{Read, Seek, SeekFrom},
+  path::{Path, PathBuf},
+  sync::{atomic::AtomicBool, Arc},
+  time::Duration,
+};
 
 pub mod bin_repr;
 pub mod date_repr;
+pub mod dedup;
+pub mod explain;
 pub mod filter;
 pub mod helpers;
+mod isolation;
 pub mod jq;
+pub mod listing;
+pub mod magic;
+pub mod manifest;
+pub mod plugin_options;
 pub mod plugins;
 pub mod read;
 pub mod result;
+pub mod scan_path;
+pub mod timeline;
+pub mod timestamps;
 
 #[cfg(feature = "tests_helpers")]
 pub mod tests_helpers;
 
 pub use bin_repr::BinRepr;
 pub use date_repr::DateRepr;
+use dedup::{DedupMode, Deduplicator, NodeDigest};
 pub use filter::ScanFilter;
+use manifest::{FileChange, Incremental, UnchangedFiles};
+pub use plugin_options::{OptionKind, OptionSpec, PluginOptions};
 use plugins::PluginPicker;
 pub use plugins::{ScanPlugin, ScanPluginResult};
 pub use read::ScanReader;
+pub use scan_path::ScanPath;
+use scan_path::SegmentKind;
+pub use timestamps::TimestampNormalizer;
 
 #[derive(PartialEq)]
 pub enum Content {
@@ -63,12 +84,24 @@ impl Content {
 #[derive(Debug)]
 pub struct ScanContent {
   pub rel_path: PathBuf,
+  /// Same path as `rel_path`, with the container boundaries (`a.zip!/b.txt`, `x.db#table`)
+  pub path: ScanPath,
   pub content: Content,
+  /// State of the file the content comes from, compared to the previous manifest (incremental scans only)
+  pub change: Option<FileChange>,
+  /// Set when the content is a reference to the first occurrence of the same bytes (dedup scans only)
+  pub duplicate_of: Option<ScanPath>,
+  /// Name of the plugin that converted the content (`None` for the duplicate references and the raw bytes)
+  pub plugin: Option<&'static str>,
+  /// Set when the content is the original bytes of a node, sent before its conversion (raw scans only)
+  pub raw: bool,
+  /// Size and sha256 of the original bytes of the leaf node the content comes from (scans with node digests only)
+  pub origin: Option<NodeDigest>,
 }
 
 impl std::fmt::Display for ScanContent {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "{}: {}", self.rel_path.to_string_lossy(), self.content)
+    write!(f, "{}: {}", self.path, self.content)
   }
 }
 
@@ -82,10 +115,12 @@ pub enum ScanError {
   NoPluginCouldScan,
   #[error("Cannot open node.")]
   CannotOpenNode,
+  #[error("Nothing found at `{0}` (containers can only be opened raw)")]
+  NotFound(PathBuf),
   #[error("Plugin failed to scan in this context: {0}")]
   PluginFailedToScanInThisContext(&'static str),
   #[error("Unable to send content: {0}")]
-  SendError(#[from] flume::SendError<Result<ScanContent, Box<ScanError>>>),
+  SendError(#[from] Box<flume::SendError<Result<ScanContent, Box<ScanError>>>>),
   #[error("Pattern error: {0}")]
   PatternError(#[from] glob::PatternError),
   #[error("Walkdir error: {0}")]
@@ -97,35 +132,126 @@ pub enum ScanError {
   #[error(transparent)]
   DateReprError(#[from] date_repr::DateReprError),
   #[error(transparent)]
+  PluginOptionsError(#[from] plugin_options::PluginOptionsError),
+  #[error(transparent)]
+  ManifestError(#[from] manifest::ManifestError),
+  #[error("{plugin} panicked while scanning `{path}`: {message}")]
+  PluginPanicked { plugin: &'static str, path: ScanPath, message: String },
+  #[error("{plugin} did not scan `{path}` within {timeout:?}, its contents are dropped")]
+  Timeout { plugin: &'static str, path: ScanPath, timeout: Duration },
+  #[error("The scan of `{0}` was given up")]
+  Cancelled(ScanPath),
+  #[error("Unable to read `{path}`: {message}")]
+  Unreadable { path: ScanPath, message: String },
+  #[error("{plugin} failed to scan `{path}`: {message}")]
+  PluginFailed { plugin: &'static str, path: ScanPath, message: String },
+  #[error("{plugin} cannot fall back to {fallback}: containers neither have nor are fallbacks")]
+  ContainerFallback { plugin: &'static str, fallback: &'static str },
+  #[error(transparent)]
   Any(#[from] anyhow::Error),
 }
 
-#[derive(Debug)]
+impl ScanError {
+  /// The node the error is about, for the errors of a node
+  #[must_use]
+  pub fn path(&self) -> Option<&ScanPath> {
+    match self {
+      ScanError::PluginPanicked { path, .. }
+      | ScanError::Timeout { path, .. }
+      | ScanError::Cancelled(path)
+      | ScanError::Unreadable { path, .. }
+      | ScanError::PluginFailed { path, .. } => Some(path),
+      _ => None,
+    }
+  }
+
+  /// The plugin that failed, for the errors of a plugin
+  #[must_use]
+  pub fn plugin(&self) -> Option<&'static str> {
+    match self {
+      ScanError::PluginPanicked { plugin, .. } | ScanError::Timeout { plugin, .. } | ScanError::PluginFailed { plugin, .. } => Some(plugin),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ScannerOptions {
-  /// Enables tables output split for database plugins
-  pub split_tables_output: bool,
+  /// Options read by the plugins themselves (`sqlite.json_array_limit`, `zip.passwords`...)
+  pub plugin_options: PluginOptions,
+
+  /// How plugins convert binary data to strings (in json contents for instance)
+  pub bin_repr: BinRepr,
+
+  /// How plugins convert dates to strings (in json contents for instance)
+  pub date_repr: DateRepr,
 
-  /// Limit the size of json arrays output from plugins that handle this option (table in databases for instance)
-  /// Default is 5000, 0 will propably get you in troubles
-  pub json_array_limit: usize,
+  /// Description of the parameters the contents depend on (profile, rules, filters, options...), the contents stored by
+  /// a previous scan with other parameters (an index...) are not reused
+  pub run_params: Option<String>,
 }
 
-impl Default for ScannerOptions {
-  fn default() -> Self {
-    Self { split_tables_output: false, json_array_limit: 5000 }
+/// A scan start parameter, the paths of its contents are prefixed by the label (if any)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanInput {
+  pub label: Option<String>,
+  pub start: String,
+}
+
+impl ScanInput {
+  #[must_use]
+  pub fn new(start: impl Into<String>) -> Self {
+    Self { label: None, start: start.into() }
+  }
+
+  #[must_use]
+  pub fn labeled(label: impl Into<String>, start: impl Into<String>) -> Self {
+    Self { label: Some(label.into()), start: start.into() }
   }
 }
 
 pub struct Scanner {
-  root_start: String,
+  inputs: Vec<ScanInput>,
   filter: Arc<Box<dyn ScanFilter>>,
   plugin_picker: Arc<Box<dyn PluginPicker>>,
+  timestamps: Option<Arc<TimestampNormalizer>>,
+  incremental: Option<Arc<Incremental>>,
+  dedup: Option<Arc<Deduplicator>>,
+  dry_run: bool,
+  raw_nodes: bool,
+  node_digests: bool,
+  node_timeout: Option<Duration>,
+  options: ScannerOptions,
 }
 
 impl Scanner {
   #[must_use]
   pub fn new(start: &impl ToString, plugin_picker: impl PluginPicker + 'static) -> Self {
-    Self { root_start: start.to_string(), plugin_picker: Arc::new(Box::new(plugin_picker)), filter: Arc::new(Box::new(filter::YesMan)) }
+    Self::new_multi([ScanInput::new(start.to_string())], plugin_picker)
+  }
+
+  /// Scans all the inputs one after the other, their contents are sent to the same [`result::ScanResult`]
+  #[must_use]
+  pub fn new_multi(inputs: impl IntoIterator<Item = ScanInput>, plugin_picker: impl PluginPicker + 'static) -> Self {
+    Self {
+      inputs: inputs.into_iter().collect(),
+      plugin_picker: Arc::new(Box::new(plugin_picker)),
+      filter: Arc::new(Box::new(filter::YesMan)),
+      timestamps: None,
+      incremental: None,
+      dedup: None,
+      dry_run: false,
+      raw_nodes: false,
+      node_digests: false,
+      node_timeout: None,
+      options: ScannerOptions::default(),
+    }
+  }
+
+  #[must_use]
+  pub fn with_options(mut self, options: ScannerOptions) -> Self {
+    self.options = options;
+    self
   }
 
   #[must_use]
@@ -134,16 +260,93 @@ impl Scanner {
     self
   }
 
-  /// Start a thread and returns a content receiver
+  /// Converts epoch timestamps of all json contents
+  #[must_use]
+  pub fn with_timestamps(mut self, normalizer: TimestampNormalizer) -> Self {
+    self.timestamps = Some(Arc::new(normalizer));
+    self
+  }
+
+  /// Compares the scanned files with a previous manifest, keep a clone of `incremental` to get the manifest of this scan
+  #[must_use]
+  pub fn with_incremental(mut self, incremental: Arc<Incremental>) -> Self {
+    self.incremental = Some(incremental);
+    self
+  }
+
+  #[must_use]
+  pub fn incremental(&self) -> Option<Arc<Incremental>> {
+    self.incremental.clone()
+  }
+
+  /// Nodes whose bytes were already scanned are skipped, keep a clone of `dedup` to get the duplicate groups
+  #[must_use]
+  pub fn with_dedup(mut self, dedup: Arc<Deduplicator>) -> Self {
+    self.dedup = Some(dedup);
+    self
+  }
+
+  #[must_use]
+  pub fn dedup(&self) -> Option<Arc<Deduplicator>> {
+    self.dedup.clone()
+  }
+
+  /// The original bytes of every leaf node (not a container) are sent as a raw content before its conversion, the nodes
+  /// of the bin and last resort plugins are only sent raw
+  #[must_use]
+  pub fn with_raw_nodes(mut self) -> Self {
+    self.raw_nodes = true;
+    self
+  }
+
+  /// The original bytes of every leaf node (not a container) are hashed, its contents carry the size and sha256
+  /// ([`ScanContent::origin`])
+  #[must_use]
+  pub fn with_node_digests(mut self) -> Self {
+    self.node_digests = true;
+    self
+  }
+
+  /// Leaf nodes (not containers) are scanned in a worker thread, a node not scanned within the timeout fails with
+  /// [`ScanError::Timeout`] and the scan goes on with the next node
+  #[must_use]
+  pub fn with_node_timeout(mut self, timeout: Duration) -> Self {
+    self.node_timeout = Some(timeout);
+    self
+  }
+
+  /// Start a thread and returns a content receiver.
+  /// Fails if a plugin option is not declared by the plugins of the picker, or has a value of the wrong type.
   pub fn scan(self) -> Result<result::ScanResult, ScanError> {
+    if let Some(declared) = plugins::declared_options(self.plugin_picker.as_ref().as_ref()) {
+      self.options.plugin_options.check(&declared)?;
+    }
+
     // this queue is bounded to avoid building up an insane amount of memory in case of slow iteration on the results
     let (sender, receiver) = flume::bounded::<Result<ScanContent, ScanError>>(10);
 
     // scan in a thread
     let _thread = std::thread::spawn(move || {
-      let context = ScanContext::new(&self.root_start, self.plugin_picker, self.filter, sender);
-      if let Err(scan_err) = context.scan() {
-        tracing::error!("{scan_err:?}");
+      for input in self.inputs {
+        let mut context = ScanContext::new(&input.start, self.plugin_picker.clone(), self.filter.clone(), sender.clone(), &self.options);
+        context.timestamps.clone_from(&self.timestamps);
+        context.incremental.clone_from(&self.incremental);
+        context.dedup.clone_from(&self.dedup);
+        context.dry_run = self.dry_run;
+        context.raw_nodes = self.raw_nodes;
+        context.node_digests = self.node_digests;
+        context.node_timeout = self.node_timeout;
+        if let Some(label) = input.label {
+          context.rel_path = PathBuf::from(label);
+        }
+        match context.scan() {
+          Err(scan_err @ ScanError::PluginPanicked { .. }) => {
+            tracing::error!("{scan_err}");
+            let _ = sender.send(Err(scan_err));
+          }
+          Err(scan_err) => tracing::error!("{scan_err:?}"),
+          Ok(()) => {}
+        }
       }
     });
 
@@ -152,6 +355,51 @@ impl Scanner {
     Ok(iter)
   }
 
+  /// Dry run: lists the nodes with their size, plugin and filter decision ([`listing::NodeInfo`] json contents).
+  /// Containers are opened, but contents are not converted.
+  pub fn list(mut self) -> Result<result::ScanResult, ScanError> {
+    self.dry_run = true;
+    self.scan()
+  }
+
+  /// Explains the decisions of the scan for one nested path (relative to the input, prefixed by its label if any):
+  /// picker rules and filter decisions of every node leading to it. Nothing is opened.
+  #[must_use]
+  pub fn explain(&self, path: &ScanPath) -> Vec<explain::NodeExplanation> {
+    explain::explain(self.plugin_picker.as_ref().as_ref(), self.filter.as_ref().as_ref(), path)
+  }
+
+  /// Returns the content of one nested path, relative to the input: a plain path (e.g.
+  /// `bundle.tar.gz/logs/app.zip/config.plist`) or a rendered one (`bundle.tar.gz!/logs/app.zip!/config.plist`, see
+  /// [`ScanPath::designates`]). Only the containers leading to this path are opened, and the scan stops as soon as it is found.
+  pub fn open(self, path: impl AsRef<str>) -> Result<ScanContent, ScanError> {
+    self.open_with(path.as_ref(), false)
+  }
+
+  /// Same as [`Self::open`], but returns the raw bytes of the path (which can be a container)
+  pub fn open_raw(self, path: impl AsRef<str>) -> Result<ScanContent, ScanError> {
+    self.open_with(path.as_ref(), true)
+  }
+
+  fn open_with(mut self, path: &str, raw: bool) -> Result<ScanContent, ScanError> {
+    let target = ScanPath::parse(path);
+    self.filter = Arc::new(Box::new(filter::NestedPath::new(target.clone())));
+    if raw {
+      self.plugin_picker = Arc::new(Box::new(plugins::RawTargetPicker::new(self.plugin_picker, target.clone())));
+    }
+
+    let mut last_error = None;
+    for content in self.scan()? {
+      match content {
+        Ok(content) if target.designates(&content.rel_path, &content.path) => return Ok(content),
+        Ok(_) => {}
+        Err(err) => last_error = Some(err),
+      }
+    }
+
+    Err(last_error.unwrap_or(ScanError::NotFound(PathBuf::from(path))))
+  }
+
   /// Returns all results in a vec (use it only for small scans).
   /// If you want a streamed way to read contents, prefer using [`Self::scan`].
   pub fn get_all(self) -> Result<Vec<Result<ScanContent, ScanError>>, ScanError> {
@@ -187,12 +435,30 @@ impl Scanner {
   }
 }
 
+#[derive(Clone)]
 pub struct ScanContext {
   root_start: Arc<String>,
   rel_path: PathBuf,
+  scan_path: ScanPath,
   filter: Arc<Box<dyn ScanFilter>>,
   plugin_picker: Arc<Box<dyn PluginPicker>>,
   sender: Sender<Result<ScanContent, ScanError>>,
+  timestamps: Option<Arc<TimestampNormalizer>>,
+  incremental: Option<Arc<Incremental>>,
+  change: Option<FileChange>,
+  dedup: Option<Arc<Deduplicator>>,
+  dry_run: bool,
+  raw_nodes: bool,
+  node_digests: bool,
+  /// Digest of the leaf node of the context (scans with node digests only)
+  digest: Option<NodeDigest>,
+  node_timeout: Option<Duration>,
+  /// Set when the scan of the node timed out
+  cancelled: Option<Arc<AtomicBool>>,
+  /// Plugin scanning the node of the context
+  plugin: Option<&'static str>,
+  plugin_options: Arc<PluginOptions>,
+  run_params: Option<Arc<str>>,
 
   /// The binary representation of the data, it's just an helper to convert bytes to string
   pub bin_repr: BinRepr,
@@ -216,24 +482,50 @@ impl ScanContext {
       Arc::new(Box::new(plugins::DefaultPluginPicker::builder().build_with_defaults()?)),
       Arc::new(Box::new(filter::YesMan)),
       sender,
+      &ScannerOptions::default(),
     );
     Ok((context, receiver))
   }
 
+  /// Same as [`Self::new_test_context`], with some plugin options
+  #[cfg(feature = "tests_helpers")]
+  pub fn new_test_context_with_options(
+    plugin_options: PluginOptions,
+  ) -> Result<(Self, flume::Receiver<Result<ScanContent, ScanError>>), ScanError> {
+    let (mut context, receiver) = Self::new_test_context()?;
+    context.plugin_options = Arc::new(plugin_options);
+    Ok((context, receiver))
+  }
+
   fn new(
     start: &impl ToString,
     plugin_picker: Arc<Box<dyn PluginPicker>>,
     filter: Arc<Box<dyn ScanFilter>>,
     sender: Sender<Result<ScanContent, ScanError>>,
+    options: &ScannerOptions,
   ) -> Self {
     Self {
       root_start: Arc::new(start.to_string()),
       rel_path: PathBuf::new(),
+      scan_path: ScanPath::default(),
       filter,
       plugin_picker,
       sender,
-      bin_repr: BinRepr::Base64,
-      date_repr: DateRepr::Rfc3339,
+      timestamps: None,
+      incremental: None,
+      change: None,
+      dedup: None,
+      dry_run: false,
+      raw_nodes: false,
+      node_digests: false,
+      digest: None,
+      node_timeout: None,
+      cancelled: None,
+      plugin: None,
+      plugin_options: Arc::new(options.plugin_options.clone()),
+      run_params: options.run_params.as_deref().map(Arc::from),
+      bin_repr: options.bin_repr,
+      date_repr: options.date_repr.clone(),
     }
   }
 
@@ -242,16 +534,40 @@ impl ScanContext {
     &self.rel_path
   }
 
+  #[must_use]
+  pub fn current_scan_path(&self) -> &ScanPath {
+    &self.scan_path
+  }
+
+  /// The options of the plugins, keys are prefixed by the plugin name (`sqlite.json_array_limit`)
+  #[must_use]
+  pub fn options(&self) -> &PluginOptions {
+    &self.plugin_options
+  }
+
+  /// See [`ScannerOptions::run_params`]
+  #[must_use]
+  pub fn run_params(&self) -> Option<&str> {
+    self.run_params.as_deref()
+  }
+
   #[tracing::instrument(err)]
-  fn scan(self) -> Result<(), ScanError> {
+  fn scan(mut self) -> Result<(), ScanError> {
     if let Some(start_plugin) = self.plugin_picker.pick_start(&self.root_start) {
-      start_plugin.start(&self, &self.root_start)?;
-      Ok(())
+      self.plugin = Some(start_plugin.name());
+      let start_path = ScanPath::default().join(SegmentKind::FileSystem, self.root_start.as_str());
+      isolation::catch_panic(start_plugin, &start_path, || start_plugin.start(&self, &self.root_start))
     } else {
       Err(ScanError::NoPluginCouldScan)
     }
   }
 
+  /// Returns false if the filter excludes everything under this child path, plugins can skip listing it (directories...)
+  #[must_use]
+  pub fn should_descend(&self, relative_path: &Path) -> bool {
+    self.filter.can_recurse(&self.rel_path.join(relative_path))
+  }
+
   #[must_use]
   pub fn has_current_extension(&self, extension: &str) -> bool {
     self.rel_path.extension().is_some_and(|x| x.to_ascii_lowercase() == extension)
@@ -265,32 +581,182 @@ impl ScanContext {
       .is_some_and(|x| x.to_string_lossy().to_lowercase().contains(extension))
   }
 
+  /// Scans a file of the file system (starters), unchanged files are skipped or marked in incremental scans.
+  /// A file that cannot be read is reported as a [`ScanError::Unreadable`] error.
+  pub fn recurse_file(&self, relative_path: &Path, path: &Path) -> Result<(), ScanError> {
+    let file_path = ScanPath::default().join(SegmentKind::FileSystem, self.rel_path.join(relative_path));
+    let unreadable = |message: String| {
+      tracing::warn!("Unable to read `{file_path}`: {message}");
+      self.send(Err(ScanError::Unreadable { path: file_path.clone(), message }))
+    };
+
+    let mut change = None;
+    if let Some(incremental) = &self.incremental {
+      // the files the filter excludes are not hashed
+      if !self.dry_run && !self.child_context(relative_path, None).picked_and_wanted() {
+        return Ok(());
+      }
+      let key = file_path.to_string();
+      let file_change = match incremental.check_file(&key, path) {
+        Ok(file_change) => file_change,
+        Err(err) => return unreadable(err.to_string()),
+      };
+      if file_change == FileChange::Unchanged && incremental.unchanged_files() == UnchangedFiles::Skip {
+        tracing::debug!("Skipping unchanged file `{key}`");
+        return Ok(());
+      }
+      change = Some(file_change);
+    }
+
+    let mut reader = match std::fs::File::open(path) {
+      Ok(reader) => reader,
+      Err(err) => return unreadable(err.to_string()),
+    };
+    self.recurse_with_change(relative_path, ScanReader::read_seek(&mut reader), change, Some(path))
+  }
+
   #[tracing::instrument(level = "debug", skip(reader), err)]
   pub fn recurse<'r>(&self, relative_path: impl Into<PathBuf> + std::fmt::Debug, reader: ScanReader<'r>) -> Result<(), ScanError> {
-    let new_path = self.rel_path.join(relative_path.into());
+    self.recurse_with_change(&relative_path.into(), reader, self.change, None)
+  }
+
+  /// Context of a file (starters) or of a member of the current container (the other plugins)
+  fn child_context(&self, relative_path: &Path, change: Option<FileChange>) -> Self {
+    let new_path = self.rel_path.join(relative_path);
+    let scan_path = if self.scan_path.is_empty() {
+      ScanPath::default().join(SegmentKind::FileSystem, &new_path)
+    } else {
+      self.scan_path.join(SegmentKind::ArchiveMember, relative_path)
+    };
 
-    let child_context = Self {
+    Self {
       root_start: self.root_start.clone(),
       rel_path: new_path,
+      scan_path,
       filter: self.filter.clone(),
       plugin_picker: self.plugin_picker.clone(),
       sender: self.sender.clone(),
+      timestamps: self.timestamps.clone(),
+      incremental: self.incremental.clone(),
+      change,
+      dedup: self.dedup.clone(),
+      dry_run: self.dry_run,
+      raw_nodes: self.raw_nodes,
+      node_digests: self.node_digests,
+      digest: None,
+      node_timeout: self.node_timeout,
+      cancelled: self.cancelled.clone(),
+      plugin: None,
+      plugin_options: self.plugin_options.clone(),
+      run_params: self.run_params.clone(),
       bin_repr: self.bin_repr,
-      date_repr: self.date_repr,
-    };
+      date_repr: self.date_repr.clone(),
+    }
+  }
+
+  /// Whether the filter wants the node scanned by the plugin: containers are descended, the other nodes are scanned
+  fn is_wanted(&self, plugin: &dyn ScanPlugin) -> bool {
+    if plugin.can_recurse() {
+      self.filter.can_recurse(&self.rel_path)
+    } else {
+      self.filter.should_scan_path(&self.rel_path, &self.scan_path)
+    }
+  }
+
+  /// Whether a plugin is picked for the node, and the filter wants it
+  fn picked_and_wanted(&self) -> bool {
+    self.plugin_picker.pick_scan(self).is_some_and(|plugin| self.is_wanted(plugin))
+  }
+
+  /// `file` is the path of the node when it is a file of the file system
+  fn recurse_with_change(
+    &self,
+    relative_path: &Path,
+    reader: ScanReader<'_>,
+    change: Option<FileChange>,
+    file: Option<&Path>,
+  ) -> Result<(), ScanError> {
+    let mut child_context = self.child_context(relative_path, change);
 
     if let Some(plugin) = self.plugin_picker.pick_scan(&child_context) {
       let plugin_name = plugin.name();
+      child_context.plugin = Some(plugin_name);
       let display_rel = child_context.rel_path.display();
-      if !(plugin.can_recurse() || self.filter.should_scan(&child_context.rel_path)) {
+      let wanted = child_context.is_wanted(plugin);
+      if self.dry_run {
+        return child_context.list_node(plugin, wanted, reader);
+      }
+      if !wanted {
         // tracing::debug!("No recursion on {plugin_name}: {display_rel}.");
         return Ok(());
       }
 
       tracing::debug!("Recurse scan with on {plugin_name}: {display_rel}.");
-      if let Err(scan_error) = plugin.scan(&child_context, reader) {
+      let fallbacks = self.plugin_picker.pick_fallbacks(&child_context);
+      let timeout = self.node_timeout.filter(|_| !plugin.can_recurse());
+      let raw = self.raw_nodes && !plugin.can_recurse();
+      let digest = self.node_digests && !plugin.can_recurse();
+      let scan_path = &child_context.scan_path.clone();
+      // hashing a stream (an archive member) buffers it: the containers streamed from their parent are not deduplicated,
+      // their members are
+      let dedup = self
+        .dedup
+        .as_ref()
+        .filter(|_| !(plugin.can_recurse() && matches!(reader, ScanReader::ReadOnly(_))));
+      let scan_result = match (dedup, fallbacks.is_empty() && timeout.is_none() && !raw && !digest) {
+        (None, true) => isolation::catch_panic(plugin, scan_path, || plugin.scan(&child_context, reader)),
+        (dedup, _) => {
+          let mut seekable = reader.into_seekable()?;
+          let mut node_digest = None;
+          if dedup.is_some() || digest {
+            let hashed = match dedup::hash_and_rewind(&mut seekable) {
+              Ok(hashed) => hashed,
+              Err(err) => {
+                tracing::error!("Unable to hash `{display_rel}` : {err}.");
+                return self.send(Err(err.into()));
+              }
+            };
+            if let Some(dedup) = dedup {
+              if child_context.check_duplicate(dedup, &hashed)? {
+                return Ok(());
+              }
+            }
+            child_context.digest = digest.then(|| hashed.clone());
+            node_digest = Some(hashed);
+          }
+          // the next copy of a node whose scan failed is scanned in its place
+          let release_first = || {
+            if let (Some(dedup), Some(node_digest)) = (dedup, &node_digest) {
+              dedup.release(&node_digest.sha256, scan_path);
+            }
+          };
+          if raw {
+            if let Err(err) = child_context.send_raw(&mut seekable) {
+              tracing::error!("Unable to read `{display_rel}` : {err}.");
+              release_first();
+              return self.send(Err(err));
+            }
+            if [plugins::bin::BinPlugin.name(), plugins::last_resort::LastResortPlugin.name()].contains(&plugin_name) {
+              return Ok(());
+            }
+          }
+          let scan_result = match timeout {
+            Some(timeout) => child_context.scan_with_timeout(plugin, timeout, &mut seekable, file),
+            None => isolation::catch_panic(plugin, scan_path, || child_context.scan_with_fallbacks(plugin, &fallbacks, &mut seekable)),
+          };
+          if scan_result.is_err() {
+            release_first();
+          }
+          scan_result
+        }
+      };
+      if let Err(scan_error) = scan_result {
         tracing::error!("{plugin_name} failed to scan `{display_rel}` : {scan_error}.");
-        self.send(Err(scan_error.into()))?;
+        let scan_error = match scan_error {
+          ScanError::Any(err) => ScanError::PluginFailed { plugin: plugin_name, path: scan_path.clone(), message: format!("{err:#}") },
+          scan_error => scan_error,
+        };
+        self.send(Err(scan_error))?;
       }
 
       return Ok(());
@@ -299,20 +765,259 @@ impl ScanContext {
     Ok(())
   }
 
+  /// Scans the node with the plugin, then with the fallbacks in turn while they fail. With fallbacks, the contents of a
+  /// plugin are held until it succeeds and dropped when it fails (only leaf plugins have fallbacks, see
+  /// [`plugins::PluginRule::with_fallback`]). Returns the error of the picked plugin, after its contents, if they all fail.
+  fn scan_with_fallbacks(
+    &self,
+    plugin: &dyn ScanPlugin,
+    fallbacks: &[&dyn ScanPlugin],
+    reader: &mut read::SeekableScanReader<'_>,
+  ) -> ScanPluginResult {
+    if fallbacks.is_empty() {
+      return plugin.scan(self, ScanReader::read_seek(reader));
+    }
+
+    let scan_held = |plugin: &dyn ScanPlugin, reader: &mut read::SeekableScanReader<'_>| {
+      let (sender, held) = flume::unbounded();
+      let context = Self { plugin: Some(plugin.name()), sender, ..self.clone() };
+      let result = plugin.scan(&context, ScanReader::read_seek(reader));
+      drop(context);
+      (result, held)
+    };
+
+    let (result, picked_contents) = scan_held(plugin, reader);
+    let Err(error) = result else { return self.send_held(&picked_contents) };
+
+    let mut failed = (plugin.name(), error.to_string());
+    for fallback in fallbacks {
+      tracing::warn!("{} failed to scan `{}` : {}, trying {}.", failed.0, self.scan_path, failed.1, fallback.name());
+      reader.seek(SeekFrom::Start(0))?;
+      match scan_held(*fallback, reader) {
+        (Ok(()), contents) => return self.send_held(&contents),
+        (Err(fallback_error), _) => failed = (fallback.name(), fallback_error.to_string()),
+      }
+    }
+
+    self.send_held(&picked_contents)?;
+    Err(error)
+  }
+
+  fn send_held(&self, contents: &flume::Receiver<Result<ScanContent, ScanError>>) -> ScanPluginResult {
+    for content in contents.drain() {
+      self.send(content)?;
+    }
+    Ok(())
+  }
+
+  /// Sends the node infos, and lists the members of wanted containers
+  fn list_node(&self, plugin: &dyn ScanPlugin, wanted: bool, reader: ScanReader<'_>) -> Result<(), ScanError> {
+    let descend = wanted && plugin.can_recurse();
+    let mut seekable = None;
+    let size = match reader {
+      ScanReader::ReadSeek(reader) => {
+        let size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        seekable = Some(read::SeekableScanReader::ReadSeek(reader));
+        size
+      }
+      reader @ ScanReader::ReadOnly(_) if descend => {
+        let reader = seekable.insert(reader.into_seekable()?);
+        let size = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        size
+      }
+      ScanReader::ReadOnly(reader) => std::io::copy(reader, &mut std::io::sink())?,
+    };
+
+    let node = listing::NodeInfo {
+      size,
+      plugin: listing::short_plugin_name(plugin.name()).to_string(),
+      container: plugin.can_recurse(),
+      included: wanted,
+    };
+    let json = serde_json::to_value(node).map_err(anyhow::Error::from)?;
+    self.send(Ok(ScanContent {
+      rel_path: self.rel_path.clone(),
+      path: self.scan_path.clone(),
+      content: Content::Json(json),
+      change: self.change,
+      duplicate_of: None,
+      plugin: self.plugin,
+      raw: false,
+      origin: self.digest.clone(),
+    }))?;
+
+    if let (true, Some(reader)) = (descend, seekable.as_mut()) {
+      if let Err(scan_error) = isolation::catch_panic(plugin, &self.scan_path, || plugin.scan(self, ScanReader::read_seek(reader))) {
+        tracing::error!("{} failed to list `{}` : {scan_error}.", plugin.name(), self.scan_path);
+        self.send(Err(scan_error))?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Sends the original bytes of the node, the reader is rewound for its conversion
+  fn send_raw(&self, reader: &mut read::SeekableScanReader<'_>) -> Result<(), ScanError> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    reader.seek(SeekFrom::Start(0))?;
+    self.send(Ok(ScanContent {
+      rel_path: self.rel_path.clone(),
+      path: self.scan_path.clone(),
+      content: Content::Bytes(bytes),
+      change: self.change,
+      duplicate_of: None,
+      plugin: None,
+      raw: true,
+      origin: self.digest.clone(),
+    }))
+  }
+
+  /// Returns true if the node was already seen, a reference is then sent in place of its contents (unless omitted)
+  fn check_duplicate(&self, dedup: &Deduplicator, digest: &NodeDigest) -> Result<bool, ScanError> {
+    let Some(first) = dedup.check(&digest.sha256, digest.size, &self.scan_path) else {
+      return Ok(false);
+    };
+
+    tracing::debug!("`{}` is a duplicate of `{first}`", self.scan_path);
+    if dedup.mode() == DedupMode::Reference {
+      let reference = serde_json::json!({ dedup::DUPLICATE_OF_KEY: first.to_string(), "sha256": digest.sha256 });
+      let content = ScanContent {
+        rel_path: self.rel_path.clone(),
+        path: self.scan_path.clone(),
+        content: Content::Json(reference),
+        change: self.change,
+        duplicate_of: Some(first),
+        plugin: None,
+        raw: false,
+        origin: None,
+      };
+      self.send(Ok(content))?;
+    }
+    Ok(true)
+  }
+
   #[tracing::instrument(level = "debug", skip(self, content), fields(content = %content), err)]
   pub fn send_content(&self, content: Content) -> Result<(), ScanError> {
-    let content = ScanContent { rel_path: self.rel_path.clone(), content };
+    let content = ScanContent {
+      rel_path: self.rel_path.clone(),
+      path: self.scan_path.clone(),
+      content: self.normalize_timestamps(content),
+      change: self.change,
+      duplicate_of: None,
+      plugin: self.plugin,
+      raw: false,
+      origin: self.digest.clone(),
+    };
+    self.send(Ok(content))
+  }
+
+  /// Sends a content converted by a previous scan (an index...), `path` is relative to the start of this context.
+  /// The content is skipped if the filter does not want the file it comes from.
+  pub fn send_scanned_content(&self, path: &ScanPath, content: Content) -> Result<(), ScanError> {
+    let path = path.with_prefix(&self.rel_path);
+    let file_path = path.without_plugin_children();
+    if !self.filter.should_scan_path(&file_path.to_path_buf(), &file_path) {
+      return Ok(());
+    }
+    let content = ScanContent {
+      rel_path: path.to_path_buf(),
+      path,
+      content: self.normalize_timestamps(content),
+      change: self.change,
+      duplicate_of: None,
+      plugin: self.plugin,
+      raw: false,
+      origin: self.digest.clone(),
+    };
     self.send(Ok(content))
   }
 
   #[tracing::instrument(level = "debug", skip(self, content), fields(content = %content), err)]
   pub fn send_child_content(&self, content: Content, child_name: impl Into<PathBuf> + std::fmt::Debug) -> Result<(), ScanError> {
-    let child_path = self.rel_path.join(child_name.into());
-    let content = ScanContent { rel_path: child_path, content };
+    let child_name = child_name.into();
+    let child_path = self.rel_path.join(&child_name);
+    let path = self.scan_path.join(SegmentKind::PluginChild, &child_name);
+    let content = ScanContent {
+      rel_path: child_path,
+      path,
+      content: self.normalize_timestamps(content),
+      change: self.change,
+      duplicate_of: None,
+      plugin: self.plugin,
+      raw: false,
+      origin: self.digest.clone(),
+    };
     self.send(Ok(content))
   }
 
+  /// Sends the error of one child node of the current node (a table that cannot be read...), so that the plugin can go on
+  /// with the next children
+  pub fn send_child_error(&self, child_name: impl AsRef<Path>, message: impl std::fmt::Display) -> Result<(), ScanError> {
+    let path = self.scan_path.join(SegmentKind::PluginChild, child_name);
+    self.send_plugin_error(path, message)
+  }
+
+  /// Sends the error of one member of the current container (an entry that cannot be decrypted...), so that the plugin
+  /// can go on with the next members
+  pub fn send_member_error(&self, relative_path: impl AsRef<Path>, message: impl std::fmt::Display) -> Result<(), ScanError> {
+    let path = if self.scan_path.is_empty() {
+      ScanPath::default().join(SegmentKind::FileSystem, self.rel_path.join(relative_path))
+    } else {
+      self.scan_path.join(SegmentKind::ArchiveMember, relative_path)
+    };
+    self.send_plugin_error(path, message)
+  }
+
+  fn send_plugin_error(&self, path: ScanPath, message: impl std::fmt::Display) -> Result<(), ScanError> {
+    let plugin = self.plugin.unwrap_or("unknown plugin");
+    let message = message.to_string();
+    tracing::error!("{plugin} failed to scan `{path}` : {message}.");
+    self.send(Err(ScanError::PluginFailed { plugin, path, message }))
+  }
+
+  /// Sends the metadata (size, dates...) of a member of the current container as a json
+  /// [`plugins::file_system::METADATA_NODE_NAME`] child of the member (`a.zip!/b.txt#__metadata`).
+  /// The metadata is skipped if the filter does not want the member.
+  pub fn send_member_metadata(&self, relative_path: &Path, metadata: serde_json::Value) -> Result<(), ScanError> {
+    let member_rel_path = self.rel_path.join(relative_path);
+    let member_path = if self.scan_path.is_empty() {
+      ScanPath::default().join(SegmentKind::FileSystem, &member_rel_path)
+    } else {
+      self.scan_path.join(SegmentKind::ArchiveMember, relative_path)
+    };
+    if !self.filter.should_scan_path(&member_rel_path, &member_path) {
+      return Ok(());
+    }
+    let content = ScanContent {
+      rel_path: member_rel_path.join(plugins::file_system::METADATA_NODE_NAME),
+      path: member_path.join(SegmentKind::PluginChild, plugins::file_system::METADATA_NODE_NAME),
+      content: Content::Json(metadata),
+      change: self.change,
+      duplicate_of: None,
+      plugin: self.plugin,
+      raw: false,
+      origin: self.digest.clone(),
+    };
+    self.send(Ok(content))
+  }
+
+  fn normalize_timestamps(&self, content: Content) -> Content {
+    match (content, &self.timestamps) {
+      (Content::Json(mut json), Some(normalizer)) => {
+        normalizer.normalize(&mut json, &self.date_repr);
+        Content::Json(json)
+      }
+      (content, _) => content,
+    }
+  }
+
   fn send(&self, content: Result<ScanContent, ScanError>) -> Result<(), ScanError> {
+    if self.is_cancelled() {
+      return Err(ScanError::Cancelled(self.scan_path.clone()));
+    }
     let res = self.sender.send(content);
     if let Err(e) = res {
       let e = e.into_inner();
@@ -0,0 +1,79 @@
+//! Dry-run listing of the nodes ([`crate::Scanner::list`]): containers are opened, contents are not converted.
+
+use crate::{Content, ScanContent};
+use serde::{Deserialize, Serialize};
+
+/// What the scan would do with a node, sent as a json content by a listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeInfo {
+  /// Size of the original bytes
+  pub size: u64,
+  /// Short name of the plugin picked for the node
+  pub plugin: String,
+  /// The plugin opens the node to scan its members (archives, file system...)
+  pub container: bool,
+  /// The filter admits the node (a container is admitted if some of its members can be)
+  pub included: bool,
+}
+
+impl NodeInfo {
+  /// Reads back the node infos of a listing content
+  #[must_use]
+  pub fn from_content(content: &ScanContent) -> Option<Self> {
+    match &content.content {
+      Content::Json(json) => serde_json::from_value(json.clone()).ok(),
+      Content::Text(_) | Content::Bytes(_) => None,
+    }
+  }
+}
+
+/// `scnr_core::plugins::json::JsonPlugin` => `JsonPlugin`
+#[must_use]
+pub fn short_plugin_name(name: &str) -> &str {
+  name.rsplit("::").next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    plugins::{json::JsonPlugin, targz::TarGzPlugin, DefaultPluginPicker},
+    tests_helpers::get_samples_path,
+    Scanner,
+  };
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn list_nodes() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.tar.gz", TarGzPlugin)?
+      .push_plugin("*.json", JsonPlugin)?
+      .build_with_defaults()?;
+    let filter = crate::filter::Glob::multi(&["**/*.json".to_string()])?;
+
+    let nodes = Scanner::new(&format!("{samples}/w.tar.gz"), picker)
+      .with_filter(filter)
+      .list()?
+      .into_iter()
+      .map(|content| {
+        let content = content?;
+        let node = NodeInfo::from_content(&content).ok_or_else(|| anyhow::anyhow!("not a node"))?;
+        Ok((content.path.to_string(), node))
+      })
+      .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let node = |size, plugin: &str, container, included| NodeInfo { size, plugin: plugin.into(), container, included };
+    assert_eq!(nodes[0], ("w.tar.gz".to_string(), node(1_693_331, "TarGzPlugin", true, true)));
+    assert!(nodes.contains(&("w.tar.gz!/w/e.json".to_string(), node(21, "JsonPlugin", false, true))));
+    assert!(nodes.contains(&("w.tar.gz!/f.yaml".to_string(), node(0, "LastResortPlugin", false, false))));
+
+    Ok(())
+  }
+
+  #[test]
+  fn short_names() {
+    assert_eq!(short_plugin_name("scnr_core::plugins::json::JsonPlugin"), "JsonPlugin");
+    assert_eq!(short_plugin_name("Plugin"), "Plugin");
+  }
+}
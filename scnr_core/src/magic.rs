@@ -0,0 +1,90 @@
+//! Guesses the file type of a content without a name (stdin...) from its first bytes
+
+use std::io::Read;
+
+/// Number of bytes needed by [`sniff_extension`]
+pub const SNIFF_LEN: usize = 512;
+
+/// Returns the file extension matching the magic bytes of the header, so that the plugin picker globs can route the content
+#[must_use]
+pub fn sniff_extension(header: &[u8]) -> Option<&'static str> {
+  const MAGICS: [(&[u8], &str); 4] =
+    [(b"\xfd7zXZ\x00", "tar.xz"), (b"PK\x03\x04", "zip"), (b"SQLite format 3\x00", "db"), (b"bplist00", "plist")];
+
+  if header.starts_with(GZIP_MAGIC) {
+    return Some(if is_gzipped_tar(header) { "tar.gz" } else { "gz" });
+  }
+  if let Some((_, extension)) = MAGICS.iter().find(|(magic, _)| header.starts_with(magic)) {
+    return Some(extension);
+  }
+
+  let text = String::from_utf8_lossy(header);
+  let text = text.trim_start_matches('\u{feff}').trim_start();
+  if text.starts_with('<') {
+    let is_plist = text.contains("<!DOCTYPE plist") || text.contains("<plist");
+    return Some(if is_plist { "plist" } else { "xml" });
+  }
+  if text.starts_with('{') || text.starts_with('[') {
+    return Some("json");
+  }
+
+  None
+}
+
+const GZIP_MAGIC: &[u8] = b"\x1f\x8b";
+
+/// Offset of the magic of a (ustar or GNU) tar header
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Whether the decompressed start of a gzip header is a tar header.
+/// The header is usually truncated in the middle of the gzip stream, the bytes decompressed before the error are enough.
+fn is_gzipped_tar(header: &[u8]) -> bool {
+  const TAR_MAGIC: &[u8] = b"ustar";
+  let mut decompressed = Vec::new();
+  let _truncated = flate2::read::GzDecoder::new(header)
+    .take((TAR_MAGIC_OFFSET + TAR_MAGIC.len()) as u64)
+    .read_to_end(&mut decompressed);
+  decompressed
+    .get(TAR_MAGIC_OFFSET..)
+    .is_some_and(|magic| magic.starts_with(TAR_MAGIC))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use test_case::test_case;
+
+  #[test_case(b"\x1f\x8b\x08\x00", Some("gz") ; "truncated gzip")]
+  #[test_case(b"\xfd7zXZ\x00\x00", Some("tar.xz") ; "xz")]
+  #[test_case(b"PK\x03\x04\x14\x00", Some("zip") ; "zip")]
+  #[test_case(b"SQLite format 3\x00\x10\x00", Some("db") ; "sqlite")]
+  #[test_case(b"bplist00\xd1\x01", Some("plist") ; "binary plist")]
+  #[test_case(b"<?xml version=\"1.0\"?>\n<!DOCTYPE plist PUBLIC", Some("plist") ; "xml plist")]
+  #[test_case(b"<?xml version=\"1.0\"?>\n<note>", Some("xml") ; "xml")]
+  #[test_case(b"  \n{\"a\": 1}", Some("json") ; "json")]
+  #[test_case(b"hello", None ; "unknown")]
+  #[test_case(b"", None ; "empty")]
+  fn sniff(header: &[u8], expected: Option<&str>) {
+    assert_eq!(sniff_extension(header), expected);
+  }
+
+  #[test]
+  fn gzip() -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let gzip = |data: &[u8]| -> anyhow::Result<Vec<u8>> {
+      let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+      encoder.write_all(data)?;
+      Ok(encoder.finish()?)
+    };
+    let mut tar = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(7);
+    tar.append_data(&mut header, "p.json", &b"{\"a\":1}"[..])?;
+    let tar_gz = gzip(&tar.into_inner()?)?;
+
+    assert_eq!(sniff_extension(&tar_gz[..tar_gz.len().min(SNIFF_LEN)]), Some("tar.gz"));
+    assert_eq!(sniff_extension(&gzip(b"{\"a\":1}")?), Some("gz"));
+    Ok(())
+  }
+}
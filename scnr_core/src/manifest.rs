@@ -0,0 +1,199 @@
+//! Manifest of the scanned files (size, modification time and hash), a later scan given this manifest only scans
+//! the new and modified files (or marks the contents of the unchanged ones).
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+  collections::BTreeMap,
+  io::Write,
+  path::Path,
+  sync::{Mutex, PoisonError},
+  time::UNIX_EPOCH,
+};
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ManifestError {
+  #[error("Manifest IO error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Invalid manifest: {0}")]
+  Json(#[from] serde_json::Error),
+  #[error("Unsupported manifest version {0} (expected {MANIFEST_VERSION})")]
+  Version(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+  pub size: u64,
+  /// Modification time, in nanoseconds since the unix epoch
+  pub mtime_ns: u128,
+  pub sha256: String,
+}
+
+/// Scanned files, by rendered path (prefixed by the input label, if any)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+  pub version: u32,
+  pub files: BTreeMap<String, ManifestEntry>,
+}
+
+impl Default for Manifest {
+  fn default() -> Self {
+    Self { version: MANIFEST_VERSION, files: BTreeMap::new() }
+  }
+}
+
+impl Manifest {
+  pub fn load(path: &Path) -> Result<Self, ManifestError> {
+    let manifest: Manifest = serde_json::from_reader(std::io::BufReader::new(std::fs::File::open(path)?))?;
+    if manifest.version != MANIFEST_VERSION {
+      return Err(ManifestError::Version(manifest.version));
+    }
+    Ok(manifest)
+  }
+
+  pub fn save(&self, path: &Path) -> Result<(), ManifestError> {
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, self)?;
+    writer.flush()?;
+    Ok(())
+  }
+}
+
+/// State of a scanned file compared to the previous manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileChange {
+  New,
+  Modified,
+  Unchanged,
+}
+
+impl std::fmt::Display for FileChange {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let dbg = format!("{self:?}").to_lowercase();
+    write!(f, "{dbg}")
+  }
+}
+
+/// What to do with the files that did not change since the previous manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnchangedFiles {
+  /// Unchanged files are not scanned
+  #[default]
+  Skip,
+  /// Unchanged files are scanned, their contents are marked as [`FileChange::Unchanged`]
+  Mark,
+}
+
+/// Compares the scanned files with a previous manifest, and builds the manifest of the current scan
+#[derive(Debug, Default)]
+pub struct Incremental {
+  previous: Manifest,
+  unchanged: UnchangedFiles,
+  current: Mutex<Manifest>,
+}
+
+impl Incremental {
+  #[must_use]
+  pub fn new(previous: Manifest, unchanged: UnchangedFiles) -> Self {
+    Self { previous, unchanged, current: Mutex::default() }
+  }
+
+  #[must_use]
+  pub fn unchanged_files(&self) -> UnchangedFiles {
+    self.unchanged
+  }
+
+  /// Records the file in the current manifest and compares it with the previous one.
+  /// The file is only hashed when its size or modification time changed.
+  pub fn check_file(&self, key: &str, path: &Path) -> Result<FileChange, ManifestError> {
+    let metadata = std::fs::metadata(path)?;
+    let size = metadata.len();
+    let mtime_ns = metadata
+      .modified()?
+      .duration_since(UNIX_EPOCH)
+      .map_or(0, |duration| duration.as_nanos());
+
+    let previous = self.previous.files.get(key);
+    let sha256 = match previous {
+      Some(entry) if entry.size == size && entry.mtime_ns == mtime_ns => entry.sha256.clone(),
+      _ => hash_file(path)?,
+    };
+
+    let change = match previous {
+      None => FileChange::New,
+      Some(entry) if entry.sha256 == sha256 => FileChange::Unchanged,
+      Some(_) => FileChange::Modified,
+    };
+
+    let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+    current.files.insert(key.to_string(), ManifestEntry { size, mtime_ns, sha256 });
+
+    Ok(change)
+  }
+
+  /// The manifest of the files checked so far (all of them once the scan is over)
+  #[must_use]
+  pub fn manifest(&self) -> Manifest {
+    self.current.lock().unwrap_or_else(PoisonError::into_inner).clone()
+  }
+
+  /// Files of the previous manifest that were not found by the current scan
+  #[must_use]
+  pub fn removed_files(&self) -> Vec<String> {
+    let current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+    self
+      .previous
+      .files
+      .keys()
+      .filter(|key| !current.files.contains_key(*key))
+      .cloned()
+      .collect()
+  }
+}
+
+fn hash_file(path: &Path) -> Result<String, ManifestError> {
+  let mut hasher = Sha256::new();
+  std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+  Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn changes() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let file = |name: &str| dir.path().join(name);
+    std::fs::write(file("a.txt"), "a")?;
+    std::fs::write(file("b.txt"), "b")?;
+
+    let first = Incremental::default();
+    assert_eq!(first.check_file("a.txt", &file("a.txt"))?, FileChange::New);
+    assert_eq!(first.check_file("b.txt", &file("b.txt"))?, FileChange::New);
+    let manifest = first.manifest();
+    assert_eq!(manifest.files["a.txt"].sha256, "ca978112ca1bbdcafac231b39a23dc4da786eff8147c4e72b9807785afee48bb");
+
+    let manifest_path = file("manifest.json");
+    manifest.save(&manifest_path)?;
+    std::fs::write(file("b.txt"), "bb")?;
+    std::fs::write(file("c.txt"), "c")?;
+    std::fs::remove_file(file("a.txt"))?;
+    std::fs::write(file("a2.txt"), "a")?;
+
+    let second = Incremental::new(Manifest::load(&manifest_path)?, UnchangedFiles::Skip);
+    assert_eq!(second.check_file("b.txt", &file("b.txt"))?, FileChange::Modified);
+    assert_eq!(second.check_file("c.txt", &file("c.txt"))?, FileChange::New);
+    assert_eq!(second.removed_files(), ["a.txt"]);
+
+    // same content, but touched: the file is hashed again and found unchanged
+    let third = Incremental::new(second.manifest(), UnchangedFiles::Mark);
+    std::fs::write(file("c.txt"), "c")?;
+    assert_eq!(third.check_file("c.txt", &file("c.txt"))?, FileChange::Unchanged);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,219 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+#[derive(thiserror::Error, Debug)]
+pub enum PluginOptionsError {
+  #[error("Invalid value `{value}` for plugin option `{key}`: {message}")]
+  InvalidValue { key: String, value: String, message: String },
+  #[error("Unknown plugin option `{key}`, the plugins of this scan accept: {known}")]
+  UnknownKey { key: String, known: String },
+}
+
+/// Type of the value of a plugin option, checked before the scan starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+  Bool,
+  Count,
+  Text,
+  /// Comma separated values, `\,` is a comma in a value and `\\` a backslash (see [`join_list`])
+  List,
+}
+
+/// A plugin option declared by a plugin ([`crate::ScanPlugin::options`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionSpec {
+  pub key: &'static str,
+  pub kind: OptionKind,
+  pub help: &'static str,
+}
+
+impl OptionSpec {
+  #[must_use]
+  pub const fn new(key: &'static str, kind: OptionKind, help: &'static str) -> Self {
+    Self { key, kind, help }
+  }
+}
+
+/// Options of the plugins, keys are namespaced by plugin (e.g. `sqlite.json_array_limit`, `zip.passwords`).
+///
+/// Values are stored as strings and converted when read by the plugins, so that they can come from the command line,
+/// a configuration file or python kwargs without the core having to know about every plugin option.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginOptions {
+  values: BTreeMap<String, String>,
+}
+
+impl PluginOptions {
+  #[must_use]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+    self.values.insert(key.into(), value.into());
+  }
+
+  #[must_use]
+  pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+    self.set(key, value);
+    self
+  }
+
+  /// Adds all the options of `other`, overriding the existing ones
+  pub fn extend(&mut self, other: PluginOptions) {
+    self.values.extend(other.values);
+  }
+
+  #[must_use]
+  pub fn get_str(&self, key: &str) -> Option<&str> {
+    self.values.get(key).map(String::as_str)
+  }
+
+  /// Returns the option converted to `T`, or `None` if the option is not set
+  pub fn get<T>(&self, key: &str) -> Result<Option<T>, PluginOptionsError>
+  where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+  {
+    let Some(value) = self.values.get(key) else {
+      return Ok(None);
+    };
+    value
+      .trim()
+      .parse()
+      .map(Some)
+      .map_err(|e: T::Err| PluginOptionsError::InvalidValue { key: key.to_string(), value: value.clone(), message: e.to_string() })
+  }
+
+  /// Returns the option converted to `T`, or the `default` if the option is not set
+  pub fn get_or<T>(&self, key: &str, default: T) -> Result<T, PluginOptionsError>
+  where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+  {
+    Ok(self.get(key)?.unwrap_or(default))
+  }
+
+  /// Returns the comma separated values of the option (empty if not set), unescaped (see [`OptionKind::List`])
+  #[must_use]
+  pub fn get_list(&self, key: &str) -> Vec<String> {
+    let Some(value) = self.values.get(key) else { return vec![] };
+    let mut values = vec![];
+    let mut current = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+      match (c, chars.peek()) {
+        ('\\', Some(&next @ (',' | '\\'))) => {
+          current.push(next);
+          chars.next();
+        }
+        (',', _) => values.push(std::mem::take(&mut current)),
+        (c, _) => current.push(c),
+      }
+    }
+    values.push(current);
+    values.retain(|value| !value.is_empty());
+    values
+  }
+
+  /// Checks that every option is declared by one of the specs, and that its value has the declared type
+  pub fn check(&self, specs: &[OptionSpec]) -> Result<(), PluginOptionsError> {
+    for key in self.values.keys() {
+      let Some(spec) = specs.iter().find(|spec| spec.key == key) else {
+        let mut known = specs.iter().map(|spec| spec.key).collect::<Vec<_>>();
+        known.sort_unstable();
+        known.dedup();
+        return Err(PluginOptionsError::UnknownKey { key: key.clone(), known: known.join(", ") });
+      };
+      match spec.kind {
+        OptionKind::Bool => {
+          self.get::<bool>(key)?;
+        }
+        OptionKind::Count => {
+          self.get::<usize>(key)?;
+        }
+        OptionKind::Text | OptionKind::List => {}
+      }
+    }
+    Ok(())
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+  }
+}
+
+/// Joins the values of a list option, escaping their commas and backslashes
+pub fn join_list(values: impl IntoIterator<Item = impl AsRef<str>>) -> String {
+  values
+    .into_iter()
+    .map(|value| value.as_ref().replace('\\', "\\\\").replace(',', "\\,"))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for PluginOptions {
+  fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+    Self { values: iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect() }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn typed_getters() -> anyhow::Result<()> {
+    let options = PluginOptions::new()
+      .with("sqlite.json_array_limit", " 100")
+      .with("fs.follow", "true")
+      .with("zip.passwords", "a,b,");
+
+    assert_eq!(options.get::<usize>("sqlite.json_array_limit")?, Some(100));
+    assert_eq!(options.get::<usize>("sqlite.nope")?, None);
+    assert!(options.get_or("fs.follow", false)?);
+    assert_eq!(options.get_or("xml.attribute_prefix", "@".to_string())?, "@");
+    assert_eq!(options.get_list("zip.passwords"), ["a", "b"]);
+    assert_eq!(options.get_list("nope"), Vec::<String>::new());
+
+    Ok(())
+  }
+
+  #[test]
+  fn escaped_lists() {
+    let options = PluginOptions::new().with("zip.passwords", r"a\,b,c\\,d\e");
+    assert_eq!(options.get_list("zip.passwords"), ["a,b", "c\\", "d\\e"]);
+
+    let passwords = ["p,w", r"back\slash", r"\,", "plain"];
+    let options = PluginOptions::new().with("zip.passwords", join_list(passwords));
+    assert_eq!(options.get_list("zip.passwords"), passwords);
+  }
+
+  #[test]
+  fn invalid_values() {
+    let options = PluginOptions::new().with("sqlite.json_array_limit", "lots");
+    let err = options.get::<usize>("sqlite.json_array_limit").unwrap_err();
+    assert_eq!(err.to_string(), "Invalid value `lots` for plugin option `sqlite.json_array_limit`: invalid digit found in string");
+  }
+
+  #[test]
+  fn checked_keys_and_types() {
+    let specs =
+      [OptionSpec::new("sqlite.json_array_limit", OptionKind::Count, "rows"), OptionSpec::new("fs.metadata", OptionKind::Bool, "metadata")];
+    assert!(PluginOptions::new().with("fs.metadata", "true").check(&specs).is_ok());
+
+    let err = PluginOptions::new().with("sqlite.json_aray_limit", "10").check(&specs).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "Unknown plugin option `sqlite.json_aray_limit`, the plugins of this scan accept: fs.metadata, sqlite.json_array_limit"
+    );
+    let options = PluginOptions::new().with("fs.metadata", "yes");
+    assert!(matches!(options.check(&specs), Err(PluginOptionsError::InvalidValue { .. })));
+  }
+
+  #[test]
+  fn extend_overrides() {
+    let mut options: PluginOptions = [("a", "1"), ("b", "2")].into_iter().collect();
+    options.extend([("b", "3")].into_iter().collect());
+    assert_eq!(options.iter().collect::<Vec<_>>(), [("a", "1"), ("b", "3")]);
+  }
+}
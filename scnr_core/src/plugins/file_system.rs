@@ -1,11 +1,36 @@
 use super::*;
-use std::fs::File;
 use walkdir::WalkDir;
 
+/// Plugin option: follows the symbolic links (loops are reported as errors), links are skipped otherwise
+pub const FOLLOW_SYMLINKS_OPTION: &str = "fs.follow_symlinks";
+/// Plugin option: skips the files and directories whose name starts with a `.`
+pub const SKIP_HIDDEN_OPTION: &str = "fs.skip_hidden";
+/// Plugin option: skips the sockets, fifos and device files (`true` by default), they are read like files otherwise
+pub const SKIP_SPECIAL_OPTION: &str = "fs.skip_special";
+/// Plugin option: does not descend into the directories of other file systems (mount points)
+pub const SAME_DEVICE_OPTION: &str = "fs.same_device";
+/// Plugin option: sends the file system metadata of each file (and of the links and special files, which are not read)
+/// as a json [`METADATA_NODE_NAME`] child node
+pub const METADATA_OPTION: &str = "fs.metadata";
+
+/// Name of the child node holding the file system metadata of a file (`dir/a.txt#__metadata`)
+pub const METADATA_NODE_NAME: &str = "__metadata";
+
 #[derive(Debug)]
 pub struct FileSystemPlugin;
 
 impl ScanPlugin for FileSystemPlugin {
+  fn options(&self) -> &'static [OptionSpec] {
+    const OPTIONS: &[OptionSpec] = &[
+      OptionSpec::new(FOLLOW_SYMLINKS_OPTION, OptionKind::Bool, "follows the symbolic links"),
+      OptionSpec::new(SKIP_HIDDEN_OPTION, OptionKind::Bool, "skips the hidden files and directories"),
+      OptionSpec::new(SKIP_SPECIAL_OPTION, OptionKind::Bool, "skips the sockets, fifos and device files"),
+      OptionSpec::new(SAME_DEVICE_OPTION, OptionKind::Bool, "does not descend into other file systems"),
+      OptionSpec::new(METADATA_OPTION, OptionKind::Bool, "sends the file system metadata of each file"),
+    ];
+    OPTIONS
+  }
+
   #[tracing::instrument(level = "debug")]
   fn can_start(&self, start_param: &str) -> bool {
     true
@@ -13,24 +38,253 @@ impl ScanPlugin for FileSystemPlugin {
 
   #[tracing::instrument(level = "debug", err)]
   fn start(&self, context: &ScanContext, start_param: &str) -> ScanPluginResult {
-    let path = PathBuf::from(start_param);
+    for_each_file(context, Path::new(start_param), |relative_path, path| Ok(context.recurse_file(&relative_path, path)?))
+  }
+}
+
+/// Calls `on_file` with the relative and full paths of all the files under `start` (sorted by name), or of `start` if it is a file.
+/// Directories the context filter excludes are not listed, the `fs.*` options of the context are applied.
+/// Entries that cannot be read (permissions, link loops...) are reported as [`ScanError::Unreadable`] errors, `start` too.
+/// With the [`METADATA_OPTION`], the metadata of the files, links and special files are sent before them.
+pub fn for_each_file(context: &ScanContext, start: &Path, mut on_file: impl FnMut(PathBuf, &Path) -> ScanPluginResult) -> ScanPluginResult {
+  let options = context.options();
+  let follow_symlinks = options.get_or(FOLLOW_SYMLINKS_OPTION, false)?;
+  let skip_hidden = options.get_or(SKIP_HIDDEN_OPTION, false)?;
+  let skip_special = options.get_or(SKIP_SPECIAL_OPTION, true)?;
+  let same_device = options.get_or(SAME_DEVICE_OPTION, false)?;
+  let metadata = options.get_or(METADATA_OPTION, false)?;
 
-    if path.is_dir() {
-      let walk_dir = WalkDir::new(&path).sort_by(|a, b| a.file_name().cmp(b.file_name()));
-      let all_files = walk_dir.into_iter().filter_map(Result::ok).filter(|e| e.file_type().is_file());
+  if start.is_dir() {
+    let walk_dir = WalkDir::new(start)
+      .follow_links(follow_symlinks)
+      .same_file_system(same_device)
+      .sort_by(|a, b| a.file_name().cmp(b.file_name()));
+    let wanted = |entry: &walkdir::DirEntry| {
+      let hidden = skip_hidden && entry.file_name().to_string_lossy().starts_with('.');
+      entry.depth() == 0
+        || !hidden && (!entry.file_type().is_dir() || entry.path().strip_prefix(start).is_ok_and(|rel| context.should_descend(rel)))
+    };
 
-      for file in all_files {
-        let relative_path = file.path().strip_prefix(&path)?.to_path_buf();
-        let mut reader = File::open(file.path())?;
-        context.recurse(relative_path, ScanReader::read_seek(&mut reader))?;
+    for entry in walk_dir.into_iter().filter_entry(wanted) {
+      let entry = match entry {
+        Ok(entry) => entry,
+        Err(err) => {
+          send_walk_error(context, start, &err)?;
+          continue;
+        }
+      };
+
+      let file_type = entry.file_type();
+      if file_type.is_dir() {
+        continue;
+      }
+
+      let relative_path = entry.path().strip_prefix(start)?.to_path_buf();
+      if metadata {
+        send_metadata(context, &relative_path, entry.path(), follow_symlinks)?;
       }
-    } else if path.is_file() {
-      if let Some(file_name) = path.file_name() {
-        let relative_path = PathBuf::from(file_name);
-        let mut reader = File::open(&path)?;
-        context.recurse(relative_path, ScanReader::read_seek(&mut reader))?;
+      if file_type.is_symlink() || !file_type.is_file() && skip_special {
+        continue;
       }
+      on_file(relative_path, entry.path())?;
     }
+  } else if start.is_file() {
+    if let Some(file_name) = start.file_name() {
+      if metadata {
+        send_metadata(context, Path::new(file_name), start, true)?;
+      }
+      on_file(PathBuf::from(file_name), start)?;
+    }
+  } else {
+    // missing, denied, dangling link, or special file
+    let message = std::fs::metadata(start).map_or_else(|err| err.to_string(), |_| "Not a file nor a directory".to_string());
+    let path = ScanPath::default().join(SegmentKind::FileSystem, start);
+    tracing::warn!("Unable to read `{path}`: {message}");
+    context.send(Err(ScanError::Unreadable { path, message }))?;
+  }
+
+  Ok(())
+}
+
+/// Sends the [`METADATA_NODE_NAME`] child of a file, the filter applies to the file path
+fn send_metadata(context: &ScanContext, relative_path: &Path, path: &Path, follow_symlinks: bool) -> ScanPluginResult {
+  let node_path = ScanPath::default()
+    .join(SegmentKind::FileSystem, relative_path)
+    .join(SegmentKind::PluginChild, METADATA_NODE_NAME);
+  match file_metadata(path, follow_symlinks, &context.date_repr) {
+    Ok(json) => context.send_scanned_content(&node_path, Content::Json(json))?,
+    Err(err) => {
+      let path = ScanPath::default().join(SegmentKind::FileSystem, context.current_path().join(relative_path));
+      tracing::warn!("Unable to read the metadata of `{path}`: {err}");
+      context.send(Err(ScanError::Unreadable { path, message: err.to_string() }))?;
+    }
+  }
+  Ok(())
+}
+
+/// File system metadata of a file, the link or special file itself is never opened.
+/// Times the platform or the file system does not record (birth time...) are `null`.
+pub fn file_metadata(path: &Path, follow_symlinks: bool, date_repr: &DateRepr) -> anyhow::Result<serde_json::Value> {
+  let metadata = if follow_symlinks { std::fs::metadata(path)? } else { std::fs::symlink_metadata(path)? };
+  let date = |time: Option<std::time::SystemTime>| time.and_then(|time| date_repr.to_string(time).ok());
+  let symlink_target = if path.is_symlink() { Some(std::fs::read_link(path)?.display().to_string()) } else { None };
+
+  let mut json = serde_json::json!({
+    "type": file_type_name(metadata.file_type()),
+    "size": metadata.len(),
+    "atime": date(metadata.accessed().ok()),
+    "mtime": date(metadata.modified().ok()),
+    "btime": date(metadata.created().ok()),
+    "symlink_target": symlink_target,
+  });
+
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::MetadataExt;
+    let ctime = u64::try_from(metadata.ctime())
+      .ok()
+      .map(|secs| std::time::UNIX_EPOCH + std::time::Duration::new(secs, u32::try_from(metadata.ctime_nsec()).unwrap_or(0)));
+    json["ctime"] = serde_json::json!(date(ctime));
+    json["mode"] = serde_json::json!(format!("{:o}", metadata.mode()));
+    json["uid"] = metadata.uid().into();
+    json["gid"] = metadata.gid().into();
+    json["dev"] = metadata.dev().into();
+    json["inode"] = metadata.ino().into();
+    json["nlink"] = metadata.nlink().into();
+    json["rdev"] = metadata.rdev().into();
+  }
+
+  Ok(json)
+}
+
+fn file_type_name(file_type: std::fs::FileType) -> &'static str {
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_fifo() {
+      return "fifo";
+    } else if file_type.is_socket() {
+      return "socket";
+    } else if file_type.is_char_device() {
+      return "char_device";
+    } else if file_type.is_block_device() {
+      return "block_device";
+    }
+  }
+  if file_type.is_symlink() {
+    "symlink"
+  } else if file_type.is_dir() {
+    "directory"
+  } else if file_type.is_file() {
+    "file"
+  } else {
+    "unknown"
+  }
+}
+
+fn send_walk_error(context: &ScanContext, start: &Path, err: &walkdir::Error) -> Result<(), ScanError> {
+  let path = err.path().unwrap_or(start);
+  let relative_path = path.strip_prefix(start).unwrap_or(path);
+  let path = ScanPath::default().join(SegmentKind::FileSystem, context.current_path().join(relative_path));
+  tracing::warn!("Unable to read `{path}`: {err}");
+  context.send(Err(ScanError::Unreadable { path, message: err.to_string() }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn walk(dir: &Path, options: PluginOptions) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+    let (context, receiver) = ScanContext::new_test_context_with_options(options)?;
+    let mut files = vec![];
+    for_each_file(&context, dir, |relative_path, _| {
+      files.push(relative_path.display().to_string());
+      Ok(())
+    })?;
+    drop(context);
+
+    let errors = receiver
+      .iter()
+      .filter_map(|result| match result {
+        Err(ScanError::Unreadable { path, .. }) => Some(path.to_string()),
+        _ => None,
+      })
+      .collect();
+    Ok((files, errors))
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn walk_options() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir_all(dir.path().join("d/.git"))?;
+    std::fs::write(dir.path().join("d/.git/config"), "")?;
+    std::fs::write(dir.path().join("d/a.txt"), "")?;
+    std::fs::write(dir.path().join(".hidden"), "")?;
+    std::os::unix::fs::symlink(dir.path().join("d/a.txt"), dir.path().join("link.txt"))?;
+    std::os::unix::fs::symlink(dir.path().join("nowhere"), dir.path().join("dangling.txt"))?;
+    std::os::unix::fs::symlink(dir.path(), dir.path().join("d/loop"))?;
+
+    let (files, errors) = walk(dir.path(), PluginOptions::default())?;
+    assert_eq!(files, [".hidden", "d/.git/config", "d/a.txt"]);
+    assert_eq!(errors, Vec::<String>::new());
+
+    let (files, _) = walk(dir.path(), PluginOptions::default().with(SKIP_HIDDEN_OPTION, "true"))?;
+    assert_eq!(files, ["d/a.txt"]);
+
+    let (files, errors) = walk(dir.path(), PluginOptions::default().with(FOLLOW_SYMLINKS_OPTION, "true"))?;
+    assert_eq!(files, [".hidden", "d/.git/config", "d/a.txt", "link.txt"]);
+    assert_eq!(errors, ["d/loop", "dangling.txt"]);
+
+    for start in ["nowhere", "dangling.txt"] {
+      let (files, errors) = walk(&dir.path().join(start), PluginOptions::default())?;
+      assert_eq!((files.len(), errors.len()), (0, 1));
+      assert!(errors[0].ends_with(start));
+    }
+
+    Ok(())
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn metadata_nodes() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("a.txt"), "hello")?;
+    std::os::unix::fs::symlink("a.txt", dir.path().join("link.txt"))?;
+    let _socket = std::os::unix::net::UnixListener::bind(dir.path().join("s.sock"))?;
+
+    let (context, receiver) = ScanContext::new_test_context_with_options(PluginOptions::default().with(METADATA_OPTION, "true"))?;
+    let mut files = vec![];
+    for_each_file(&context, dir.path(), |relative_path, _| {
+      files.push(relative_path.display().to_string());
+      Ok(())
+    })?;
+    drop(context);
+    assert_eq!(files, ["a.txt"], "the link and the socket are not read");
+
+    let nodes = receiver
+      .iter()
+      .map(|result| {
+        let scanned = result?;
+        let json = scanned.content.json().ok_or_else(|| anyhow::anyhow!("not a json"))?;
+        Ok((scanned.path.to_string(), json["type"].clone(), json["size"].clone(), json["symlink_target"].clone()))
+      })
+      .collect::<anyhow::Result<Vec<_>>>()?;
+    let types = nodes
+      .iter()
+      .map(|(path, kind, ..)| (path.as_str(), kind.as_str()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      types,
+      [("a.txt#__metadata", Some("file")), ("link.txt#__metadata", Some("symlink")), ("s.sock#__metadata", Some("socket"))]
+    );
+    assert_eq!(nodes[0].2, serde_json::json!(5));
+    assert_eq!(nodes[1].3, serde_json::json!("a.txt"));
+
+    let json = file_metadata(&dir.path().join("a.txt"), false, &DateRepr::UnixSeconds)?;
+    assert_eq!(json["nlink"], serde_json::json!(1));
+    assert!(json["mtime"].is_string() && json["ctime"].is_string() && json["inode"].is_u64());
 
     Ok(())
   }
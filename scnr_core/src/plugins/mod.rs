@@ -6,6 +6,7 @@ pub mod file_system;
 pub mod ips;
 pub mod json;
 pub mod last_resort;
+pub mod stdin;
 pub mod targz;
 pub mod tarxz;
 pub mod text;
@@ -15,10 +16,18 @@ pub mod yaml;
 pub mod zip;
 
 pub type PluginsList = Vec<Box<dyn ScanPlugin>>;
-pub type PluginsGlobsList = Vec<(Option<Pattern>, Box<dyn ScanPlugin>)>;
+pub type PluginRulesList = Vec<PluginRule>;
 
 pub type ScanPluginResult = Result<(), anyhow::Error>;
 
+/// Plugin option: the archive plugins (zip, tar) send the metadata of each member (size, mode, modification date...)
+/// as a json [`file_system::METADATA_NODE_NAME`] child node
+pub const MEMBER_METADATA_OPTION: &str = "archive.metadata";
+
+/// Options shared by several plugins, they are accepted whatever the plugins of the scan
+pub const SHARED_OPTIONS: [OptionSpec; 1] =
+  [OptionSpec::new(MEMBER_METADATA_OPTION, OptionKind::Bool, "the archive plugins send the metadata of each member")];
+
 pub trait ScanPlugin: Sync + Send + std::fmt::Debug {
   // Returns the plugin name using Any::type_name
   fn name(&self) -> &'static str {
@@ -41,6 +50,11 @@ pub trait ScanPlugin: Sync + Send + std::fmt::Debug {
     false
   }
 
+  /// The plugin options (`<plugin>.<option>`) read by this plugin, the options no plugin of a scan declares are rejected
+  fn options(&self) -> &'static [OptionSpec] {
+    &[]
+  }
+
   /// scan the current context and returns a stream of nodes
   fn scan(&self, _context: &ScanContext, _reader: ScanReader<'_>) -> ScanPluginResult {
     Err(anyhow::anyhow!("This plugin cannot scan other plugin nodes"))
@@ -50,10 +64,124 @@ pub trait ScanPlugin: Sync + Send + std::fmt::Debug {
 pub trait PluginPicker: Send + Sync {
   fn pick_start(&self, start_param: &str) -> Option<&dyn ScanPlugin>;
   fn pick_scan(&self, context: &ScanContext) -> Option<&dyn ScanPlugin>;
+
+  /// The rules of the picker in evaluation order, and whether they match this path (used by [`crate::explain`]).
+  /// `pick_scan` picks the plugin of the first matching rule.
+  fn explain_scan(&self, _path: &Path) -> Vec<RuleMatch<'_>> {
+    vec![]
+  }
+
+  /// Plugins tried in turn on the node when the picked one fails to scan it
+  fn pick_fallbacks(&self, _context: &ScanContext) -> Vec<&dyn ScanPlugin> {
+    vec![]
+  }
+
+  /// All the plugins the picker can pick (starters and fallbacks included). The plugin options of a scan are checked
+  /// against the options they declare, unless the picker does not list its plugins (empty).
+  fn plugins(&self) -> Vec<&dyn ScanPlugin> {
+    vec![]
+  }
+}
+
+/// The options declared by the plugins of the picker and the [`SHARED_OPTIONS`], `None` if the picker does not list its
+/// plugins
+#[must_use]
+pub fn declared_options(picker: &dyn PluginPicker) -> Option<Vec<OptionSpec>> {
+  let plugins = picker.plugins();
+  if plugins.is_empty() {
+    return None;
+  }
+  let declared = plugins.iter().flat_map(|plugin| plugin.options().iter().copied());
+  Some(SHARED_OPTIONS.into_iter().chain(declared).collect())
+}
+
+/// A rule of a [`PluginPicker`], matched against a path
+#[derive(Debug, Clone)]
+pub struct RuleMatch<'p> {
+  /// `None` for the starter plugins, which are never picked to scan a node
+  pub glob: Option<&'p str>,
+  pub plugin: &'p dyn ScanPlugin,
+  pub priority: i32,
+  pub fallbacks: Vec<&'p dyn ScanPlugin>,
+  pub matches: bool,
+}
+
+/// A rule of the [`DefaultPluginPicker`]: the plugin scanning the nodes matching a glob (starters have no glob),
+/// and the fallback plugins tried in turn when it fails
+#[derive(Debug)]
+pub struct PluginRule {
+  pattern: Option<Pattern>,
+  plugin: Box<dyn ScanPlugin>,
+  priority: i32,
+  fallbacks: Vec<Arc<dyn ScanPlugin>>,
+}
+
+impl PluginRule {
+  pub fn new(glob: &str, plugin: impl ScanPlugin + 'static) -> Result<Self, ScanError> {
+    Self::new_boxed(glob, Box::new(plugin))
+  }
+
+  pub fn new_boxed(glob: &str, plugin: Box<dyn ScanPlugin>) -> Result<Self, ScanError> {
+    Ok(Self { pattern: Some(Pattern::new(glob)?), plugin, priority: 0, fallbacks: vec![] })
+  }
+
+  #[must_use]
+  pub fn starter(plugin: Box<dyn ScanPlugin>) -> Self {
+    Self { pattern: None, plugin, priority: 0, fallbacks: vec![] }
+  }
+
+  /// Rules of higher priority are evaluated first (the default priority is 0)
+  #[must_use]
+  pub fn with_priority(mut self, priority: i32) -> Self {
+    self.priority = priority;
+    self
+  }
+
+  /// Adds a plugin to try when the previous ones failed to scan a node. Containers neither have nor are fallbacks: the
+  /// members sent before a failure would be sent again by the next plugin.
+  pub fn with_fallback(mut self, plugin: Arc<dyn ScanPlugin>) -> Result<Self, ScanError> {
+    if self.plugin.can_recurse() || plugin.can_recurse() {
+      return Err(ScanError::ContainerFallback { plugin: self.plugin.name(), fallback: plugin.name() });
+    }
+    self.fallbacks.push(plugin);
+    Ok(self)
+  }
+
+  #[must_use]
+  pub fn plugin(&self) -> &dyn ScanPlugin {
+    self.plugin.as_ref()
+  }
+
+  /// Number of literal characters of the glob: `**/Preferences/*.plist` is more specific than `*.plist`
+  #[must_use]
+  pub fn specificity(&self) -> usize {
+    let Some(pattern) = &self.pattern else { return 0 };
+    let mut in_class = false;
+    let mut count = 0;
+    for c in pattern.as_str().chars() {
+      match c {
+        '[' => in_class = true,
+        ']' => in_class = false,
+        '*' | '?' => {}
+        _ if !in_class => count += 1,
+        _ => {}
+      }
+    }
+    count
+  }
+
+  fn matches(&self, path: &Path) -> bool {
+    self
+      .pattern
+      .as_ref()
+      .is_some_and(|pattern| pattern.matches_path_with(path, filter::case_insensitive()))
+  }
 }
 
+/// Picks the plugin of the first matching rule: rules are sorted by priority, then by specificity (the most specific
+/// glob wins), then by insertion order.
 pub struct DefaultPluginPicker {
-  plugins: Arc<PluginsGlobsList>,
+  rules: Arc<PluginRulesList>,
 }
 
 impl DefaultPluginPicker {
@@ -65,46 +193,127 @@ impl DefaultPluginPicker {
 
 impl PluginPicker for DefaultPluginPicker {
   fn pick_start(&self, start_param: &str) -> Option<&dyn ScanPlugin> {
+    self.rules.iter().map(PluginRule::plugin).find(|p| p.can_start(start_param))
+  }
+
+  fn pick_scan(&self, context: &ScanContext) -> Option<&dyn ScanPlugin> {
     self
-      .plugins
+      .rules
       .iter()
-      .map(|(_, p)| p)
-      .find(|p| p.can_start(start_param))
-      .map(AsRef::as_ref)
+      .find(|rule| rule.matches(&context.rel_path))
+      .map(PluginRule::plugin)
+  }
+
+  fn explain_scan(&self, path: &Path) -> Vec<RuleMatch<'_>> {
+    self
+      .rules
+      .iter()
+      .map(|rule| RuleMatch {
+        glob: rule.pattern.as_ref().map(Pattern::as_str),
+        plugin: rule.plugin(),
+        priority: rule.priority,
+        fallbacks: rule.fallbacks.iter().map(AsRef::as_ref).collect(),
+        matches: rule.matches(path),
+      })
+      .collect()
+  }
+
+  fn pick_fallbacks(&self, context: &ScanContext) -> Vec<&dyn ScanPlugin> {
+    self
+      .rules
+      .iter()
+      .find(|rule| rule.matches(&context.rel_path))
+      .map(|rule| rule.fallbacks.iter().map(AsRef::as_ref).collect())
+      .unwrap_or_default()
+  }
+
+  fn plugins(&self) -> Vec<&dyn ScanPlugin> {
+    self
+      .rules
+      .iter()
+      .flat_map(|rule| std::iter::once(rule.plugin()).chain(rule.fallbacks.iter().map(AsRef::as_ref)))
+      .collect()
+  }
+}
+
+/// Picks the [`bin::BinPlugin`] for one path (to get its raw bytes), and delegates everything else
+pub struct RawTargetPicker {
+  inner: Arc<Box<dyn PluginPicker>>,
+  target: ScanPath,
+  target_path: PathBuf,
+}
+
+impl RawTargetPicker {
+  /// The target is a plain or rendered path (see [`ScanPath::designates`])
+  #[must_use]
+  pub fn new(inner: Arc<Box<dyn PluginPicker>>, target: ScanPath) -> Self {
+    Self { inner, target_path: target.to_path_buf(), target }
+  }
+
+  fn is_target(&self, context: &ScanContext) -> bool {
+    self.target.designates(&context.rel_path, &context.scan_path)
+  }
+}
+
+impl PluginPicker for RawTargetPicker {
+  fn pick_start(&self, start_param: &str) -> Option<&dyn ScanPlugin> {
+    self.inner.pick_start(start_param)
   }
 
   fn pick_scan(&self, context: &ScanContext) -> Option<&dyn ScanPlugin> {
-    for (pattern, plugin) in self.plugins.iter() {
-      if let Some(pattern) = pattern {
-        if pattern.matches_path_with(&context.rel_path, filter::case_insensitive()) {
-          return Some(plugin.as_ref());
-        }
-      }
+    if self.is_target(context) {
+      return Some(&bin::BinPlugin);
+    }
+    self.inner.pick_scan(context)
+  }
+
+  fn explain_scan(&self, path: &Path) -> Vec<RuleMatch<'_>> {
+    let target = RuleMatch {
+      glob: self.target_path.to_str(),
+      plugin: &bin::BinPlugin,
+      priority: i32::MAX,
+      fallbacks: vec![],
+      matches: path == self.target_path,
+    };
+    std::iter::once(target).chain(self.inner.explain_scan(path)).collect()
+  }
+
+  fn pick_fallbacks(&self, context: &ScanContext) -> Vec<&dyn ScanPlugin> {
+    if self.is_target(context) {
+      return vec![];
     }
-    None
+    self.inner.pick_fallbacks(context)
+  }
+
+  fn plugins(&self) -> Vec<&dyn ScanPlugin> {
+    self.inner.plugins()
   }
 }
 
 #[derive(Default)]
 pub struct DefaultPluginPickerBuilder {
-  plugins: PluginsGlobsList,
+  rules: PluginRulesList,
 }
 
 impl DefaultPluginPickerBuilder {
   #[must_use]
   pub fn builder() -> Self {
-    Self { plugins: vec![] }
+    Self { rules: vec![] }
   }
 
+  /// Sorts the rules by priority and specificity, rules of the same priority and specificity keep their order
   #[must_use]
-  pub fn build_as_this(self) -> DefaultPluginPicker {
-    DefaultPluginPicker { plugins: Arc::new(self.plugins) }
+  pub fn build_as_this(mut self) -> DefaultPluginPicker {
+    self
+      .rules
+      .sort_by_key(|rule| (std::cmp::Reverse(rule.priority), std::cmp::Reverse(rule.specificity())));
+    DefaultPluginPicker { rules: Arc::new(self.rules) }
   }
 
   pub fn build_with_defaults(mut self) -> Result<DefaultPluginPicker, ScanError> {
-    self.plugins.push((None, Box::new(file_system::FileSystemPlugin)));
-    let all = Pattern::new("*")?;
-    self.plugins.push((Some(all), Box::new(last_resort::LastResortPlugin)));
+    self.rules.push(PluginRule::starter(Box::new(stdin::StdinPlugin)));
+    self.rules.push(PluginRule::starter(Box::new(file_system::FileSystemPlugin)));
+    self.rules.push(PluginRule::new("*", last_resort::LastResortPlugin)?);
     Ok(self.build_as_this())
   }
 
@@ -112,24 +321,304 @@ impl DefaultPluginPickerBuilder {
     self.insert_boxed_plugin(glob, Box::new(plugin))
   }
 
-  pub fn insert_boxed_plugin(mut self, glob: &str, plugin: Box<dyn ScanPlugin>) -> Result<Self, ScanError> {
-    let pattern = Pattern::new(glob)?;
-    self.plugins.insert(0, (Some(pattern), plugin));
-    Ok(self)
+  pub fn insert_boxed_plugin(self, glob: &str, plugin: Box<dyn ScanPlugin>) -> Result<Self, ScanError> {
+    Ok(self.insert_rule(PluginRule::new_boxed(glob, plugin)?))
+  }
+
+  /// Adds a rule before the others (it wins over the rules of the same priority and specificity)
+  #[must_use]
+  pub fn insert_rule(mut self, rule: PluginRule) -> Self {
+    self.rules.insert(0, rule);
+    self
   }
 
   pub fn push_plugin(self, glob: &str, plugin: impl ScanPlugin + 'static) -> Result<Self, ScanError> {
     self.push_boxed_plugin(glob, Box::new(plugin))
   }
 
-  pub fn push_boxed_plugin(mut self, glob: &str, plugin: Box<dyn ScanPlugin>) -> Result<Self, ScanError> {
-    let pattern = Pattern::new(glob)?;
-    self.plugins.push((Some(pattern), plugin));
-    Ok(self)
+  pub fn push_boxed_plugin(self, glob: &str, plugin: Box<dyn ScanPlugin>) -> Result<Self, ScanError> {
+    Ok(self.push_rule(PluginRule::new_boxed(glob, plugin)?))
   }
 
-  pub fn push_starter_plugin(mut self, plugin: Box<dyn ScanPlugin>) -> Result<Self, ScanError> {
-    self.plugins.push((None, plugin));
+  /// Adds a rule after the others (the rules of the same priority and specificity win over it)
+  #[must_use]
+  pub fn push_rule(mut self, rule: PluginRule) -> Self {
+    self.rules.push(rule);
+    self
+  }
+
+  pub fn push_starter_plugin(self, plugin: Box<dyn ScanPlugin>) -> Result<Self, ScanError> {
+    Ok(self.push_rule(PluginRule::starter(plugin)))
+  }
+
+  /// Adds fallback plugins to the rules of a plugin (by [`ScanPlugin::name`]), tried in turn when it fails to scan a node
+  /// (see [`PluginRule::with_fallback`])
+  pub fn with_fallbacks_for(mut self, plugin_name: &str, fallbacks: &[Arc<dyn ScanPlugin>]) -> Result<Self, ScanError> {
+    let rules = std::mem::take(&mut self.rules);
+    for rule in rules {
+      let rule =
+        if rule.plugin.name() == plugin_name { fallbacks.iter().cloned().try_fold(rule, PluginRule::with_fallback)? } else { rule };
+      self.rules.push(rule);
+    }
     Ok(self)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn picked(picker: &DefaultPluginPicker, path: &str) -> Option<&'static str> {
+    picker
+      .explain_scan(Path::new(path))
+      .into_iter()
+      .find(|rule| rule.matches)
+      .map(|rule| crate::listing::short_plugin_name(rule.plugin.name()))
+  }
+
+  #[test]
+  fn most_specific_rule_wins() -> anyhow::Result<()> {
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.json", json::JsonPlugin)?
+      .push_plugin("**/raw/*.json", bin::BinPlugin)?
+      .build_with_defaults()?;
+    assert_eq!(picked(&picker, "a/b.json"), Some("JsonPlugin"));
+    assert_eq!(picked(&picker, "a/raw/b.json"), Some("BinPlugin"));
+    assert_eq!(picked(&picker, "a/b.txt"), Some("LastResortPlugin"));
+
+    // same specificity: insertion order
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.json", json::JsonPlugin)?
+      .insert_plugin("*.json", text::TextPlugin)?
+      .build_as_this();
+    assert_eq!(picked(&picker, "b.json"), Some("TextPlugin"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn priority_comes_first() -> anyhow::Result<()> {
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("**/raw/*.json", json::JsonPlugin)?
+      .push_rule(PluginRule::new("*", bin::BinPlugin)?.with_priority(10))
+      .build_with_defaults()?;
+    assert_eq!(picked(&picker, "a/raw/b.json"), Some("BinPlugin"));
+    assert_eq!(PluginRule::new("**/raw/*.json", bin::BinPlugin)?.specificity(), 10);
+    assert_eq!(PluginRule::new("*.[jt]son", bin::BinPlugin)?.specificity(), 4);
+
+    Ok(())
+  }
+
+  #[derive(Debug)]
+  struct PanicPlugin;
+
+  impl ScanPlugin for PanicPlugin {
+    #[allow(clippy::panic)]
+    fn scan(&self, _context: &ScanContext, _reader: ScanReader<'_>) -> ScanPluginResult {
+      panic!("malformed node")
+    }
+  }
+
+  #[derive(Debug)]
+  struct SlowPlugin;
+
+  impl ScanPlugin for SlowPlugin {
+    fn scan(&self, context: &ScanContext, _reader: ScanReader<'_>) -> ScanPluginResult {
+      std::thread::sleep(std::time::Duration::from_millis(500));
+      context.send_content(Content::Text("too late".into()))?;
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn failing_nodes_do_not_stop_the_scan() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    for name in ["a.boom", "b.slow", "c.txt"] {
+      std::fs::write(dir.path().join(name), name)?;
+    }
+
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.boom", PanicPlugin)?
+      .push_plugin("*.slow", SlowPlugin)?
+      .push_plugin("*.txt", text::TextPlugin)?
+      .build_with_defaults()?;
+    let results = Scanner::new(&dir.path().display(), picker)
+      .with_node_timeout(std::time::Duration::from_millis(50))
+      .get_all()?;
+
+    assert_eq!(results.len(), 3);
+    assert!(matches!(
+      &results[0],
+      Err(ScanError::PluginPanicked { path, message, .. }) if path.to_string() == "a.boom" && message == "malformed node"
+    ));
+    assert!(matches!(&results[1], Err(ScanError::Timeout { path, .. }) if path.to_string() == "b.slow"));
+    assert!(matches!(&results[2], Ok(content) if content.content == Content::Text("c.txt".into())));
+
+    Ok(())
+  }
+
+  #[test]
+  fn archive_members_are_given_a_time_limit() -> anyhow::Result<()> {
+    use ::zip::{write::SimpleFileOptions, ZipWriter};
+    use std::io::Write;
+    let dir = tempfile::tempdir()?;
+    let mut zip = ZipWriter::new(std::fs::File::create(dir.path().join("a.zip"))?);
+    for name in ["b.slow", "c.txt"] {
+      zip.start_file(name, SimpleFileOptions::default())?;
+      zip.write_all(name.as_bytes())?;
+    }
+    zip.finish()?;
+
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.zip", zip::ZipPlugin)?
+      .push_plugin("*.slow", SlowPlugin)?
+      .push_plugin("*.txt", text::TextPlugin)?
+      .build_with_defaults()?;
+    let results = Scanner::new(&dir.path().display(), picker)
+      .with_node_timeout(std::time::Duration::from_millis(50))
+      .get_all()?;
+
+    assert_eq!(results.len(), 2);
+    assert!(matches!(&results[0], Err(ScanError::Timeout { path, .. }) if path.to_string() == "a.zip!/b.slow"));
+    assert!(matches!(&results[1], Ok(content) if content.content == Content::Text("c.txt".into())));
+
+    Ok(())
+  }
+
+  #[test]
+  fn fallbacks_are_tried_in_turn() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("a.json"), r#"{"a": 1}"#)?;
+    std::fs::write(dir.path().join("b.json"), "b = 1")?;
+    std::fs::write(dir.path().join("c.json"), "{not json")?;
+
+    let picker = DefaultPluginPicker::builder()
+      .push_rule(PluginRule::new("*.json", json::JsonPlugin)?.with_fallback(Arc::new(toml::TomlPlugin))?)
+      .with_fallbacks_for(json::JsonPlugin.name(), &[Arc::new(bin::BinPlugin)])?
+      .build_with_defaults()?;
+    let contents = Scanner::new(&dir.path().display(), picker)
+      .get_all()?
+      .into_iter()
+      .map(|content| content.map(|content| (content.path.to_string(), content.content, content.plugin)))
+      .collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(
+      contents,
+      [
+        ("a.json".to_string(), Content::Json(serde_json::json!({"a": 1})), Some(json::JsonPlugin.name())),
+        ("b.json".to_string(), Content::Json(serde_json::json!({"b": 1})), Some(toml::TomlPlugin.name())),
+        ("c.json".to_string(), Content::Bytes(b"{not json".to_vec()), Some(bin::BinPlugin.name())),
+      ]
+    );
+
+    Ok(())
+  }
+
+  #[derive(Debug)]
+  struct HalfwayPlugin;
+
+  impl ScanPlugin for HalfwayPlugin {
+    fn scan(&self, context: &ScanContext, _reader: ScanReader<'_>) -> ScanPluginResult {
+      context.send_content(Content::Text("first part".into()))?;
+      Err(anyhow::anyhow!("malformed second part"))
+    }
+  }
+
+  #[test]
+  fn contents_of_failed_plugins_are_dropped() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("a.half"), "a")?;
+
+    let scan = |fallback: Option<Arc<dyn ScanPlugin>>| -> anyhow::Result<Vec<Result<Content, String>>> {
+      let rule = PluginRule::new("*.half", HalfwayPlugin)?;
+      let rule = match fallback {
+        Some(fallback) => rule.with_fallback(fallback)?,
+        None => rule,
+      };
+      let picker = DefaultPluginPicker::builder().push_rule(rule).build_with_defaults()?;
+      let results = Scanner::new(&dir.path().display(), picker).get_all()?;
+      Ok(
+        results
+          .into_iter()
+          .map(|result| result.map(|content| content.content).map_err(|err| err.to_string()))
+          .collect(),
+      )
+    };
+
+    // the fallback sends the node once
+    assert_eq!(scan(Some(Arc::new(bin::BinPlugin)))?, [Ok(Content::Bytes(b"a".to_vec()))]);
+    // all the plugins failed: the contents of the picked one are kept, as without fallbacks
+    let failed = scan(Some(Arc::new(HalfwayPlugin)))?;
+    assert_eq!(failed, scan(None)?);
+    assert_eq!(failed[0], Ok(Content::Text("first part".into())));
+    assert!(failed[1].as_ref().is_err_and(|err| err.contains("malformed second part")));
+
+    // containers neither have nor are fallbacks
+    assert!(matches!(
+      PluginRule::new("*.zip", zip::ZipPlugin)?.with_fallback(Arc::new(bin::BinPlugin)),
+      Err(ScanError::ContainerFallback { .. })
+    ));
+    assert!(matches!(
+      PluginRule::new("*.json", json::JsonPlugin)?.with_fallback(Arc::new(zip::ZipPlugin)),
+      Err(ScanError::ContainerFallback { .. })
+    ));
+
+    Ok(())
+  }
+
+  #[test]
+  fn raw_nodes_are_sent_before_their_conversion() -> anyhow::Result<()> {
+    let samples = crate::tests_helpers::get_samples_path()?;
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("a.json"), r#"{"a": 1}"#)?;
+    std::fs::write(dir.path().join("b.bin"), "b")?;
+    std::fs::copy(format!("{samples}/z.zip"), dir.path().join("c.zip"))?;
+
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.json", json::JsonPlugin)?
+      .push_plugin("*.zip", zip::ZipPlugin)?
+      .build_with_defaults()?;
+    let contents = Scanner::new(&dir.path().display(), picker)
+      .with_filter(crate::filter::Glob::new("[ab].*")?)
+      .with_raw_nodes()
+      .get_all_oks()?
+      .into_iter()
+      .map(|content| (content.path.to_string(), content.content, content.raw))
+      .collect::<Vec<_>>();
+
+    // the bin plugin output would be the same bytes, the container itself is not sent
+    assert_eq!(
+      contents,
+      [
+        ("a.json".to_string(), Content::Bytes(br#"{"a": 1}"#.to_vec()), true),
+        ("a.json".to_string(), Content::Json(serde_json::json!({"a": 1})), false),
+        ("b.bin".to_string(), Content::Bytes(b"b".to_vec()), true),
+      ]
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn options_are_checked_against_the_plugins() -> anyhow::Result<()> {
+    let scanner = |key: &str, value: &str| -> anyhow::Result<Scanner> {
+      let picker = DefaultPluginPicker::builder()
+        .push_plugin("*.zip", zip::ZipPlugin)?
+        .build_with_defaults()?;
+      let options = ScannerOptions { plugin_options: PluginOptions::new().with(key, value), ..Default::default() };
+      Ok(Scanner::new(&"missing", picker).with_options(options))
+    };
+
+    assert!(scanner(zip::PASSWORDS_OPTION, "a,b")?.scan().is_ok());
+    assert!(scanner(MEMBER_METADATA_OPTION, "true")?.scan().is_ok());
+    let unknown = scanner("zip.pasword", "a")?.scan().err().map(|err| err.to_string());
+    assert!(unknown.is_some_and(|err| err.starts_with("Unknown plugin option `zip.pasword`")));
+    let no_plugin = scanner(xml::ATTRIBUTE_PREFIX_OPTION, "_")?.scan();
+    assert!(matches!(no_plugin, Err(ScanError::PluginOptionsError(_))));
+    let wrong_type = scanner(file_system::METADATA_OPTION, "yes")?.scan();
+    assert!(matches!(wrong_type, Err(ScanError::PluginOptionsError(_))));
+
+    Ok(())
+  }
+}
@@ -0,0 +1,95 @@
+use super::*;
+use crate::magic::{sniff_extension, SNIFF_LEN};
+use std::io::Cursor;
+
+/// Start parameter selecting this plugin
+pub const STDIN_START_PARAM: &str = "-";
+
+/// Plugin option: name of the content read from stdin (e.g. `x.tar.gz`), used by the plugin picker globs.
+/// Without it, the extension is guessed from the magic bytes of the content.
+pub const NAME_OPTION: &str = "stdin.name";
+
+/// Name of the content read from stdin when no name is given, an extension is added if the content is recognized
+pub const DEFAULT_NAME: &str = "stdin";
+
+/// Starts the scan from the standard input, which is read only once (not seekable)
+#[derive(Debug)]
+pub struct StdinPlugin;
+
+impl ScanPlugin for StdinPlugin {
+  fn can_start(&self, start_param: &str) -> bool {
+    start_param == STDIN_START_PARAM
+  }
+
+  fn options(&self) -> &'static [OptionSpec] {
+    const OPTIONS: &[OptionSpec] = &[OptionSpec::new(NAME_OPTION, OptionKind::Text, "name of the content read from stdin")];
+    OPTIONS
+  }
+
+  #[tracing::instrument(level = "debug", skip_all, err)]
+  fn start(&self, context: &ScanContext, _start_param: &str) -> ScanPluginResult {
+    scan_stream(context, &mut std::io::stdin().lock())
+  }
+}
+
+/// Names the stream (from the options or its magic bytes) and recurses on it
+fn scan_stream(context: &ScanContext, stream: &mut dyn Read) -> ScanPluginResult {
+  let mut header = Vec::with_capacity(SNIFF_LEN);
+  stream.take(SNIFF_LEN as u64).read_to_end(&mut header)?;
+
+  let name = match context.options().get_str(NAME_OPTION) {
+    Some(name) => name.to_string(),
+    None => sniff_extension(&header).map_or_else(|| DEFAULT_NAME.to_string(), |extension| format!("{DEFAULT_NAME}.{extension}")),
+  };
+
+  let mut reader = Cursor::new(header).chain(stream);
+  context.recurse(name, ScanReader::read_only(&mut reader))?;
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::PluginOptions;
+  use pretty_assertions::assert_eq;
+  use test_case::test_case;
+
+  #[test_case(b"{\"a\": 1}", None, "stdin.json" ; "sniffed")]
+  #[test_case(b"hello", None, "stdin" ; "unknown")]
+  #[test_case(b"hello", Some("x.tar.gz"), "x.tar.gz" ; "named")]
+  fn names(data: &[u8], name: Option<&str>, expected: &str) -> anyhow::Result<()> {
+    let options = name.map(|name| PluginOptions::new().with(NAME_OPTION, name)).unwrap_or_default();
+    let (context, receiver) = ScanContext::new_test_context_with_options(options)?;
+
+    scan_stream(&context, &mut &data[..])?;
+    drop(context);
+
+    let results = receiver.into_iter().collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].rel_path.as_os_str(), expected);
+    assert_eq!(results[0].content, Content::Bytes(data.to_vec()));
+
+    Ok(())
+  }
+
+  #[test]
+  fn long_streams_are_entirely_read() -> anyhow::Result<()> {
+    let data = vec![b'x'; SNIFF_LEN * 3 + 1];
+    let (context, receiver) = ScanContext::new_test_context()?;
+
+    scan_stream(&context, &mut data.as_slice())?;
+    drop(context);
+
+    let result = receiver.recv()??;
+    assert_eq!(result.content, Content::Bytes(data));
+
+    Ok(())
+  }
+
+  #[test]
+  fn only_dash_starts() {
+    assert!(StdinPlugin.can_start("-"));
+    assert!(!StdinPlugin.can_start("./-"));
+  }
+}
@@ -13,12 +13,16 @@ impl ScanPlugin for TarGzPlugin {
     let tar = flate2::read::GzDecoder::new(reader);
     let mut archive = tar::Archive::new(tar);
 
+    let member_metadata = context.options().get_or(MEMBER_METADATA_OPTION, false)?;
     for entry in archive.entries()? {
       let mut entry = entry?;
       if entry.header().entry_type() != tar::EntryType::file() {
         continue;
       }
       let path = entry.path()?.to_path_buf();
+      if member_metadata {
+        context.send_member_metadata(&path, member_metadata_json(entry.header(), &context.date_repr))?;
+      }
       context.recurse(path, ScanReader::read_only(&mut entry))?;
     }
 
@@ -26,11 +30,28 @@ impl ScanPlugin for TarGzPlugin {
   }
 }
 
+/// Metadata of a tar member, shared by the tar plugins
+pub(crate) fn member_metadata_json(header: &tar::Header, date_repr: &DateRepr) -> serde_json::Value {
+  let mtime = header.mtime().ok().and_then(|secs| {
+    date_repr
+      .to_string(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+      .ok()
+  });
+  serde_json::json!({
+    "type": "file",
+    "size": header.size().ok(),
+    "mode": header.mode().ok().map(|mode| format!("{mode:o}")),
+    "uid": header.uid().ok(),
+    "gid": header.gid().ok(),
+    "mtime": mtime,
+  })
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use crate::{
-    tests_helpers::{exec_plugin_scan, get_samples_path},
+    tests_helpers::{exec_plugin_scan, exec_plugin_scan_with_options, get_samples_path},
     ScanReader,
   };
 
@@ -56,6 +77,25 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn member_metadata() -> anyhow::Result<()> {
+    let samples_dir = get_samples_path()?;
+    let mut file = std::fs::File::open(format!("{samples_dir}/w.tar.gz"))?;
+
+    let options = crate::PluginOptions::new().with(MEMBER_METADATA_OPTION, "true");
+    let results = exec_plugin_scan_with_options(ScanReader::read_seek(&mut file), &TarGzPlugin, options)?;
+    let metadata = results
+      .into_iter()
+      .filter_map(Result::ok)
+      .filter(|content| content.path.to_string().ends_with("#__metadata"))
+      .map(|content| (content.path.to_string(), content.content.json().map(|json| json["size"].clone())))
+      .collect::<Vec<_>>();
+    assert_eq!(metadata[0], ("w/e.json#__metadata".to_string(), Some(serde_json::json!(21))));
+    assert_eq!(metadata.len(), 3);
+
+    Ok(())
+  }
+
   #[test]
   fn failing_test() -> anyhow::Result<()> {
     let samples_dir = get_samples_path()?;
@@ -19,12 +19,16 @@ impl ScanPlugin for TarXzPlugin {
 
     let mut archive = tar::Archive::new(&decomp[..]);
 
+    let member_metadata = context.options().get_or(MEMBER_METADATA_OPTION, false)?;
     for entry in archive.entries()? {
       let mut entry = entry?;
       if entry.header().entry_type() != tar::EntryType::file() {
         continue;
       }
       let path = entry.path()?.to_path_buf();
+      if member_metadata {
+        context.send_member_metadata(&path, targz::member_metadata_json(entry.header(), &context.date_repr))?;
+      }
       context.recurse(path, ScanReader::read_only(&mut entry))?;
     }
 
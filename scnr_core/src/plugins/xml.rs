@@ -1,16 +1,29 @@
-use quickxml_to_serde::xml_str_to_json;
+use quickxml_to_serde::{xml_str_to_json, Config};
 
 use super::*;
 
+/// Plugin option: prefix of the json keys holding the xml attributes (`@` by default)
+pub const ATTRIBUTE_PREFIX_OPTION: &str = "xml.attribute_prefix";
+
 #[derive(Debug)]
 pub struct XmlPlugin;
 
 impl ScanPlugin for XmlPlugin {
+  fn options(&self) -> &'static [OptionSpec] {
+    const OPTIONS: &[OptionSpec] =
+      &[OptionSpec::new(ATTRIBUTE_PREFIX_OPTION, OptionKind::Text, "prefix of the keys holding the xml attributes")];
+    OPTIONS
+  }
+
   #[tracing::instrument(level = "debug", err)]
   fn scan(&self, context: &ScanContext, mut reader: ScanReader<'_>) -> ScanPluginResult {
     let mut xml = String::new();
     reader.read_to_string(&mut xml)?;
-    let json = xml_str_to_json(&xml, &Default::default())?;
+    let mut config = Config::new_with_defaults();
+    if let Some(prefix) = context.options().get_str(ATTRIBUTE_PREFIX_OPTION) {
+      config.xml_attr_prefix = prefix.to_string();
+    }
+    let json = xml_str_to_json(&xml, &config)?;
     let content = Content::Json(json);
     context.send_content(content)?;
     Ok(())
@@ -20,9 +33,13 @@ impl ScanPlugin for XmlPlugin {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::tests_helpers::exec_plugin_scan;
+  use crate::{
+    tests_helpers::{exec_plugin_scan, exec_plugin_scan_with_options},
+    PluginOptions,
+  };
   use pretty_assertions::assert_eq;
   use serde_json::json;
+  use test_case::test_case;
 
   #[test]
   fn test() -> anyhow::Result<()> {
@@ -37,6 +54,21 @@ mod tests {
     Ok(())
   }
 
+  #[test_case(None, json!({ "prop": { "@name": "n", "#text": "value" } }) ; "default prefix")]
+  #[test_case(Some("_"), json!({ "prop": { "_name": "n", "#text": "value" } }) ; "custom prefix")]
+  fn attributes(prefix: Option<&str>, expected: serde_json::Value) -> anyhow::Result<()> {
+    let content = r#"<prop name="n">value</prop>"#.bytes().collect::<Vec<_>>();
+    let options = prefix
+      .map(|prefix| PluginOptions::new().with(ATTRIBUTE_PREFIX_OPTION, prefix))
+      .unwrap_or_default();
+
+    let results = exec_plugin_scan_with_options(ScanReader::read_only(&mut content.as_slice()), &XmlPlugin, options)?;
+    let result = results.into_iter().next().expect("?")?;
+    assert_eq!(result.content, Content::Json(expected));
+
+    Ok(())
+  }
+
   #[test]
   fn failing_test() {
     let content = r"not_xml".bytes().collect::<Vec<_>>();
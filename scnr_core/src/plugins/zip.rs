@@ -1,9 +1,17 @@
 use super::*;
 
+/// Plugin option: comma separated passwords tried on encrypted entries
+pub const PASSWORDS_OPTION: &str = "zip.passwords";
+
 #[derive(Debug)]
 pub struct ZipPlugin;
 
 impl ScanPlugin for ZipPlugin {
+  fn options(&self) -> &'static [OptionSpec] {
+    const OPTIONS: &[OptionSpec] = &[OptionSpec::new(PASSWORDS_OPTION, OptionKind::List, "passwords tried on encrypted entries")];
+    OPTIONS
+  }
+
   fn can_recurse(&self) -> bool {
     true
   }
@@ -15,27 +23,80 @@ impl ScanPlugin for ZipPlugin {
 
     let mut zip = ::zip::ZipArchive::new(&mut reader)?;
 
-    for i in 0..zip.len() {
-      let mut entry = zip.by_index(i)?;
-      if entry.is_dir() {
+    let passwords = context.options().get_list(PASSWORDS_OPTION);
+    let member_metadata = context.options().get_or(MEMBER_METADATA_OPTION, false)?;
+
+    // an entry that cannot be opened is reported, the scan goes on with the next ones
+    'entries: for i in 0..zip.len() {
+      let (name, encrypted) = {
+        let raw = zip.by_index_raw(i)?;
+        (raw.name().to_string(), raw.encrypted())
+      };
+      if !encrypted {
+        match zip.by_index(i) {
+          Ok(entry) => scan_entry(context, entry, member_metadata)?,
+          Err(err) => context.send_member_error(&name, err)?,
+        }
         continue;
       }
-      let file_name = entry.name().to_string();
-      let readonly_scan_reader = ScanReader::read_only(&mut entry);
-      context.recurse(file_name, readonly_scan_reader)?;
+      for password in &passwords {
+        match zip.by_index_decrypt(i, password.as_bytes()) {
+          Ok(entry) => {
+            scan_entry(context, entry, member_metadata)?;
+            continue 'entries;
+          }
+          Err(::zip::result::ZipError::InvalidPassword) => {}
+          Err(err) => {
+            context.send_member_error(&name, err)?;
+            continue 'entries;
+          }
+        }
+      }
+      context.send_member_error(&name, format!("No password of `{PASSWORDS_OPTION}` can decrypt this entry"))?;
     }
 
     Ok(())
   }
 }
 
+fn scan_entry(context: &ScanContext, mut entry: ::zip::read::ZipFile<'_>, member_metadata: bool) -> ScanPluginResult {
+  if entry.is_dir() {
+    return Ok(());
+  }
+  let file_name = entry.name().to_string();
+  if member_metadata {
+    context.send_member_metadata(Path::new(&file_name), member_metadata_json(&entry, &context.date_repr))?;
+  }
+  context.recurse(file_name, ScanReader::read_only(&mut entry))?;
+  Ok(())
+}
+
+/// Zip dates have no timezone, they are read as UTC
+fn member_metadata_json(entry: &::zip::read::ZipFile<'_>, date_repr: &DateRepr) -> serde_json::Value {
+  let mtime = entry
+    .last_modified()
+    .and_then(|datetime| time::OffsetDateTime::try_from(datetime).ok())
+    .and_then(|datetime| date_repr.to_string(datetime.into()).ok());
+  serde_json::json!({
+    "type": "file",
+    "size": entry.size(),
+    "compressed_size": entry.compressed_size(),
+    "crc32": entry.crc32(),
+    "mode": entry.unix_mode().map(|mode| format!("{mode:o}")),
+    "mtime": mtime,
+  })
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use crate::{
-    tests_helpers::{exec_plugin_scan, get_samples_path},
-    ScanReader,
+    tests_helpers::{exec_plugin_scan, exec_plugin_scan_with_options, get_samples_path},
+    PluginOptions, ScanReader,
   };
+  use ::zip::{write::SimpleFileOptions, ZipWriter};
+  use std::io::{Cursor, Write};
+  use test_case::test_case;
 
   #[test]
   fn test() -> anyhow::Result<()> {
@@ -51,6 +112,29 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn member_metadata() -> anyhow::Result<()> {
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    let modified = ::zip::DateTime::from_date_and_time(2021, 1, 2, 3, 4, 6)?;
+    writer.start_file("a.txt", SimpleFileOptions::default().last_modified_time(modified).unix_permissions(0o640))?;
+    writer.write_all(b"hello")?;
+    let zip = writer.finish()?.into_inner();
+
+    let options = PluginOptions::new().with(MEMBER_METADATA_OPTION, "true");
+    let results = exec_plugin_scan_with_options(ScanReader::read_only(&mut zip.as_slice()), &ZipPlugin, options)?;
+    assert_eq!(results.len(), 2);
+
+    let metadata = results.into_iter().next().expect("?")?;
+    assert_eq!(metadata.path.to_string(), "a.txt#__metadata");
+    let json = metadata.content.json().expect("?");
+    assert_eq!(
+      (&json["mtime"], &json["size"], &json["mode"]),
+      (&serde_json::json!("2021-01-02T03:04:06Z"), &serde_json::json!(5), &serde_json::json!("100640"))
+    );
+
+    Ok(())
+  }
+
   #[test]
   fn failing_test() -> anyhow::Result<()> {
     let samples_dir = get_samples_path()?;
@@ -61,4 +145,45 @@ mod tests {
 
     Ok(())
   }
+
+  fn encrypted_zip() -> anyhow::Result<Vec<u8>> {
+    let mut writer = ZipWriter::new(Cursor::new(vec![]));
+    writer.start_file("secret.txt", SimpleFileOptions::default().with_aes_encryption(::zip::AesMode::Aes256, "s3cr3t"))?;
+    writer.write_all(b"hello")?;
+    Ok(writer.finish()?.into_inner())
+  }
+
+  #[test_case("wrong,s3cr3t" ; "second password")]
+  #[test_case("s3cr3t" ; "single password")]
+  fn encrypted(passwords: &str) -> anyhow::Result<()> {
+    let zip = encrypted_zip()?;
+    let options = PluginOptions::new().with(PASSWORDS_OPTION, passwords);
+
+    let results = exec_plugin_scan_with_options(ScanReader::read_only(&mut zip.as_slice()), &ZipPlugin, options)?;
+    assert_eq!(results.len(), 1);
+
+    let result = results.into_iter().next().expect("?")?;
+    assert_eq!(result.rel_path.as_os_str(), "secret.txt");
+    assert_eq!(result.content, Content::Bytes(b"hello".to_vec()));
+
+    Ok(())
+  }
+
+  #[test_case("" ; "no password")]
+  #[test_case("wrong" ; "wrong password")]
+  fn encrypted_failing(passwords: &str) -> anyhow::Result<()> {
+    let mut writer = ZipWriter::new_append(Cursor::new(encrypted_zip()?))?;
+    writer.start_file("plain.txt", SimpleFileOptions::default())?;
+    writer.write_all(b"hi")?;
+    let zip = writer.finish()?.into_inner();
+    let options = PluginOptions::new().with(PASSWORDS_OPTION, passwords);
+
+    // the entry that cannot be decrypted does not prevent reading the next ones
+    let results = exec_plugin_scan_with_options(ScanReader::read_only(&mut zip.as_slice()), &ZipPlugin, options)?;
+    assert_eq!(results.len(), 2);
+    assert!(matches!(&results[0], Err(ScanError::PluginFailed { path, .. }) if path.to_string() == "secret.txt"));
+    assert!(matches!(&results[1], Ok(content) if content.rel_path.as_os_str() == "plain.txt"));
+
+    Ok(())
+  }
 }
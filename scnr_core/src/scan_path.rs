@@ -0,0 +1,263 @@
+//! Structured path of the scanned contents, keeping track of the container boundaries.
+//!
+//! The rendering is unambiguous: `dir/a.zip!/b/c.db#table` is the `table` child node of the `b/c.db` archive member
+//! of the `dir/a.zip` file, while `dir/a.zip/b` is a real directory named `a.zip`.
+//! `\`, `!` and `#` are escaped with a `\` in the segment names.
+
+use std::{
+  fmt::Write,
+  path::{Component, Path, PathBuf},
+};
+
+/// Separator rendered before an archive member
+pub const ARCHIVE_MEMBER_SEPARATOR: &str = "!/";
+/// Separator rendered before a plugin child node
+pub const PLUGIN_CHILD_SEPARATOR: char = '#';
+
+const ESCAPE: char = '\\';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SegmentKind {
+  /// Path relative to the input (prefixed by the input label, if any)
+  FileSystem,
+  /// Path of a file inside a container (archive...)
+  ArchiveMember,
+  /// Node created by a plugin (database table...)
+  PluginChild,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PathSegment {
+  pub kind: SegmentKind,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScanPath {
+  segments: Vec<PathSegment>,
+}
+
+impl ScanPath {
+  #[must_use]
+  pub fn new(segments: Vec<PathSegment>) -> Self {
+    Self { segments }
+  }
+
+  #[must_use]
+  pub fn segments(&self) -> &[PathSegment] {
+    &self.segments
+  }
+
+  #[must_use]
+  pub fn is_empty(&self) -> bool {
+    self.segments.is_empty()
+  }
+
+  /// Returns a new path with one more segment
+  #[must_use]
+  pub fn join(&self, kind: SegmentKind, name: impl AsRef<Path>) -> Self {
+    let mut segments = self.segments.clone();
+    segments.push(PathSegment { kind, name: name.as_ref().to_string_lossy().to_string() });
+    Self { segments }
+  }
+
+  /// The path with all the segments joined as directories (the `rel_path` of the contents)
+  #[must_use]
+  pub fn to_path_buf(&self) -> PathBuf {
+    self.segments.iter().map(|segment| segment.name.as_str()).collect()
+  }
+
+  /// Returns the path prefixed by a file system path (an input label...)
+  #[must_use]
+  pub fn with_prefix(&self, prefix: &Path) -> Self {
+    let mut segments = self.segments.clone();
+    match segments.first_mut() {
+      _ if prefix.as_os_str().is_empty() => {}
+      Some(first) if first.kind == SegmentKind::FileSystem => first.name = prefix.join(&first.name).to_string_lossy().to_string(),
+      _ => segments.insert(0, PathSegment { kind: SegmentKind::FileSystem, name: prefix.to_string_lossy().to_string() }),
+    }
+    Self { segments }
+  }
+
+  /// The path of the file (or archive member) a plugin child node comes from
+  #[must_use]
+  pub fn without_plugin_children(&self) -> Self {
+    let len = self
+      .segments
+      .iter()
+      .rposition(|segment| segment.kind != SegmentKind::PluginChild)
+      .map_or(0, |i| i + 1);
+    Self { segments: self.segments[..len].to_vec() }
+  }
+
+  /// Whether this path, given by the user (see [`Self::parse`]), designates a node: a path without container separators is
+  /// compared to the plain path of the node (`a.zip/b.txt`), a rendered one segment by segment (`a.zip!/b.txt`)
+  #[must_use]
+  pub fn designates(&self, rel_path: &Path, path: &ScanPath) -> bool {
+    if self.is_plain() {
+      self.to_path_buf() == rel_path
+    } else {
+      self == path
+    }
+  }
+
+  /// Same as [`Self::designates`], for the node or one of its ancestors (the file or container it comes from)
+  #[must_use]
+  pub fn designates_descendant_of(&self, rel_path: &Path, path: &ScanPath) -> bool {
+    if self.is_plain() {
+      self.to_path_buf().starts_with(rel_path)
+    } else {
+      self.segments.starts_with(&path.segments)
+    }
+  }
+
+  /// A file system path, without container separators
+  fn is_plain(&self) -> bool {
+    self.segments.iter().all(|segment| segment.kind == SegmentKind::FileSystem)
+  }
+
+  /// Parses a rendered path, a path without separators is a file system path.
+  /// A trailing lone `\` is kept as is.
+  #[must_use]
+  pub fn parse(rendered: &str) -> Self {
+    let mut segments = vec![];
+    let mut kind = SegmentKind::FileSystem;
+    let mut name = String::new();
+    let mut chars = rendered.chars().peekable();
+
+    while let Some(c) = chars.next() {
+      let next_kind = match c {
+        ESCAPE => {
+          name.push(chars.next().unwrap_or(ESCAPE));
+          continue;
+        }
+        '!' if chars.peek() == Some(&'/') => {
+          chars.next();
+          SegmentKind::ArchiveMember
+        }
+        PLUGIN_CHILD_SEPARATOR => SegmentKind::PluginChild,
+        c => {
+          name.push(c);
+          continue;
+        }
+      };
+      segments.push(PathSegment { kind, name: std::mem::take(&mut name) });
+      kind = next_kind;
+    }
+
+    if !name.is_empty() || !segments.is_empty() {
+      segments.push(PathSegment { kind, name });
+    }
+
+    Self { segments }
+  }
+}
+
+impl std::fmt::Display for ScanPath {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    for (i, segment) in self.segments.iter().enumerate() {
+      match segment.kind {
+        SegmentKind::ArchiveMember => f.write_str(ARCHIVE_MEMBER_SEPARATOR)?,
+        SegmentKind::PluginChild => f.write_char(PLUGIN_CHILD_SEPARATOR)?,
+        SegmentKind::FileSystem if i > 0 => f.write_char('/')?,
+        SegmentKind::FileSystem => {}
+      }
+      for c in segment.name.chars() {
+        if matches!(c, ESCAPE | '!' | PLUGIN_CHILD_SEPARATOR) {
+          f.write_char(ESCAPE)?;
+        }
+        f.write_char(c)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl std::str::FromStr for ScanPath {
+  type Err = std::convert::Infallible;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(Self::parse(s))
+  }
+}
+
+/// Only keeps the normal components of the path, so that nothing is written outside of an output directory (`..`,
+/// absolute paths of archive members...)
+#[must_use]
+pub fn sanitize_path(path: &Path) -> PathBuf {
+  let sanitized = path
+    .components()
+    .filter_map(|component| match component {
+      Component::Normal(name) => Some(name),
+      Component::Prefix(_) | Component::RootDir | Component::CurDir | Component::ParentDir => None,
+    })
+    .collect::<PathBuf>();
+  if sanitized.as_os_str().is_empty() {
+    PathBuf::from("_")
+  } else {
+    sanitized
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+  use test_case::test_case;
+  use SegmentKind::*;
+
+  fn path(segments: &[(SegmentKind, &str)]) -> ScanPath {
+    ScanPath::new(
+      segments
+        .iter()
+        .map(|(kind, name)| PathSegment { kind: *kind, name: (*name).to_string() })
+        .collect(),
+    )
+  }
+
+  #[test_case(&[], "" ; "empty")]
+  #[test_case(&[(FileSystem, "dir/a.txt")], "dir/a.txt" ; "file")]
+  #[test_case(&[(FileSystem, "a.zip"), (ArchiveMember, "b/c.txt")], "a.zip!/b/c.txt" ; "archive member")]
+  #[test_case(&[(FileSystem, "a.zip"), (ArchiveMember, "x.db"), (PluginChild, "table")], "a.zip!/x.db#table" ; "plugin child")]
+  #[test_case(&[(FileSystem, "a.tar.gz"), (ArchiveMember, "b.zip"), (ArchiveMember, "c.txt")], "a.tar.gz!/b.zip!/c.txt" ; "nested archives")]
+  #[test_case(&[(FileSystem, "wow!/#1\\.txt"), (PluginChild, "")], "wow\\!/\\#1\\\\.txt#" ; "escaped")]
+  fn round_trip(segments: &[(SegmentKind, &str)], rendered: &str) {
+    let scan_path = path(segments);
+    assert_eq!(scan_path.to_string(), rendered);
+    assert_eq!(ScanPath::parse(rendered), scan_path);
+  }
+
+  #[test]
+  fn rel_path() {
+    let scan_path = path(&[(FileSystem, "dir/a.zip"), (ArchiveMember, "b/x.db"), (PluginChild, "table")]);
+    assert_eq!(scan_path.to_path_buf(), PathBuf::from("dir/a.zip/b/x.db/table"));
+  }
+
+  #[test]
+  fn prefix_and_children() {
+    let scan_path = path(&[(FileSystem, "a.db"), (PluginChild, "table"), (PluginChild, "row")]);
+    assert_eq!(scan_path.with_prefix(Path::new("host")).to_string(), "host/a.db#table#row");
+    assert_eq!(scan_path.with_prefix(Path::new("")), scan_path);
+    assert_eq!(scan_path.without_plugin_children(), path(&[(FileSystem, "a.db")]));
+  }
+
+  #[test]
+  fn designated_nodes() {
+    let node = path(&[(FileSystem, "w.tar.gz"), (ArchiveMember, "w/e.json")]);
+    let rel_path = node.to_path_buf();
+    for target in ["w.tar.gz/w/e.json", "w.tar.gz!/w/e.json"] {
+      assert!(ScanPath::parse(target).designates(&rel_path, &node), "{target}");
+    }
+    assert!(!ScanPath::parse("w.tar.gz!/w!/e.json").designates(&rel_path, &node));
+
+    let archive = path(&[(FileSystem, "w.tar.gz")]);
+    assert!(ScanPath::parse("w.tar.gz!/w/e.json").designates_descendant_of(&archive.to_path_buf(), &archive));
+    assert!(ScanPath::parse("w.tar.gz/w/e.json").designates_descendant_of(&archive.to_path_buf(), &archive));
+    assert!(!ScanPath::parse("w.tar.gz!/w/e.json").designates_descendant_of(&rel_path, &path(&[(FileSystem, "w.tar.gz/w")])));
+  }
+
+  #[test]
+  fn lenient_parsing() {
+    assert_eq!(ScanPath::parse("a!b\\"), path(&[(FileSystem, "a!b\\")]));
+  }
+}
@@ -1,7 +1,15 @@
-use crate::{result::ScanResult, ScanContent, ScanContext, ScanError, ScanPlugin, ScanReader};
+use crate::{result::ScanResult, Content, PluginOptions, ScanContent, ScanContext, ScanError, ScanPath, ScanPlugin, ScanReader};
 
 pub fn exec_plugin_scan(reader: ScanReader<'_>, plugin: &impl ScanPlugin) -> anyhow::Result<Vec<Result<ScanContent, ScanError>>> {
-  let (context, receiver) = ScanContext::new_test_context()?;
+  exec_plugin_scan_with_options(reader, plugin, PluginOptions::default())
+}
+
+pub fn exec_plugin_scan_with_options(
+  reader: ScanReader<'_>,
+  plugin: &impl ScanPlugin,
+  options: PluginOptions,
+) -> anyhow::Result<Vec<Result<ScanContent, ScanError>>> {
+  let (context, receiver) = ScanContext::new_test_context_with_options(options)?;
 
   plugin.scan(&context, reader)?;
 
@@ -17,6 +25,13 @@ pub fn exec_plugin_scan(reader: ScanReader<'_>, plugin: &impl ScanPlugin) -> any
   Ok(res)
 }
 
+/// A content at a rendered path (`a.zip!/b.db#table`), as sent by a plugin with no name and no scan option
+#[must_use]
+pub fn scan_content(path: &str, content: Content) -> ScanContent {
+  let path = ScanPath::parse(path);
+  ScanContent { rel_path: path.to_path_buf(), path, content, change: None, duplicate_of: None, plugin: None, raw: false, origin: None }
+}
+
 /// Return the samples patch of the main repo
 /// DO NOT USE ON OTHER REPOSITORIES
 pub fn get_samples_path() -> Result<String, std::env::VarError> {
@@ -0,0 +1,356 @@
+//! Unified timeline of a scan (`scnr timeline`): the file system and archive member dates (`#__metadata` nodes, see the
+//! `fs.metadata` and `archive.metadata` plugin options) and the timestamp-like values found in the converted contents
+//! (epoch columns and keys of the [`TimestampNormalizer`] rules, plist and ISO dates, dated log lines), sorted by date.
+
+use crate::{
+  plugins::file_system::METADATA_NODE_NAME, scan_path::SegmentKind, timestamps::ADDED_KEY_SUFFIX, Content, DateRepr, ScanContent,
+  TimestampNormalizer,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+  collections::HashMap,
+  io::Write,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use time::{format_description::OwnedFormatItem, PrimitiveDateTime};
+
+/// Dates of the metadata nodes, in bodyfile column order
+const METADATA_DATES: [&str; 4] = ["atime", "mtime", "ctime", "btime"];
+
+/// Dates of log lines without timezone, read as UTC
+const LOG_DATE_FORMAT: &str = "[year]-[month]-[day] [hour]:[minute]:[second][optional [.[subsecond]]]";
+
+#[derive(thiserror::Error, Debug)]
+pub enum TimelineError {
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Json error: {0}")]
+  Json(#[from] serde_json::Error),
+  #[error("Csv error: {0}")]
+  Csv(#[from] csv::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSource {
+  /// Metadata of a file of the file system
+  FileSystem,
+  /// Metadata of a member of an archive
+  ArchiveMember,
+  /// Value found in the converted content of a node
+  Content,
+}
+
+/// Attributes of a file or member, written in the bodyfile line of its dates
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileAttributes {
+  pub inode: u64,
+  pub mode: Option<u32>,
+  pub uid: u64,
+  pub gid: u64,
+  pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimelineEvent {
+  #[serde(skip)]
+  pub time: SystemTime,
+  /// The time, in the date representation of the timeline
+  pub date: String,
+  /// Node holding the timestamp (the file or member itself for its metadata dates)
+  pub path: String,
+  pub source: EventSource,
+  /// Where the timestamp was found: metadata date (`mtime`...), json pointer of the value (`/rows/0/ZDATE`) or log line (`line 12`)
+  pub field: String,
+  /// The original value
+  pub value: String,
+  #[serde(skip)]
+  pub attributes: Option<FileAttributes>,
+}
+
+/// Collects the events of the scanned contents
+pub struct Timeline {
+  normalizer: TimestampNormalizer,
+  date_repr: DateRepr,
+  log_date_format: Option<OwnedFormatItem>,
+  events: Vec<TimelineEvent>,
+}
+
+impl Timeline {
+  /// The `normalizer` rules find the epoch timestamps, the `date_repr` is the one of the scan (dates in the contents) and of the events
+  #[must_use]
+  pub fn new(normalizer: TimestampNormalizer, date_repr: DateRepr) -> Self {
+    let log_date_format = time::format_description::parse_owned::<2>(LOG_DATE_FORMAT).ok();
+    Self { normalizer, date_repr, log_date_format, events: vec![] }
+  }
+
+  /// Adds the events of a content
+  pub fn add(&mut self, content: &ScanContent) {
+    let segments = content.path.segments();
+    match (&content.content, segments.last()) {
+      (Content::Json(json), Some(last)) if last.kind == SegmentKind::PluginChild && last.name == METADATA_NODE_NAME => {
+        let node = content.path.without_plugin_children();
+        let source = match node.segments().last() {
+          Some(segment) if segment.kind == SegmentKind::ArchiveMember => EventSource::ArchiveMember,
+          _ => EventSource::FileSystem,
+        };
+        self.add_metadata(&node.to_string(), source, json);
+      }
+      (Content::Json(json), _) => self.add_json(&content.path.to_string(), json, "", None),
+      (Content::Text(text), _) => self.add_log_lines(&content.path.to_string(), text),
+      (Content::Bytes(_), _) => {}
+    }
+  }
+
+  /// Events sorted by time, then path and field
+  #[must_use]
+  pub fn into_events(mut self) -> Vec<TimelineEvent> {
+    self
+      .events
+      .sort_by(|a, b| (a.time, &a.path, &a.field).cmp(&(b.time, &b.path, &b.field)));
+    self.events
+  }
+
+  fn push(&mut self, time: SystemTime, path: &str, source: EventSource, field: String, value: String, attributes: Option<FileAttributes>) {
+    let Ok(date) = self.date_repr.to_string(time) else { return };
+    self
+      .events
+      .push(TimelineEvent { time, date, path: path.to_string(), source, field, value, attributes });
+  }
+
+  fn add_metadata(&mut self, path: &str, source: EventSource, json: &Value) {
+    let number = |key: &str| json[key].as_u64().unwrap_or(0);
+    let attributes = FileAttributes {
+      inode: number("inode"),
+      mode: json["mode"].as_str().and_then(|mode| u32::from_str_radix(mode, 8).ok()),
+      uid: number("uid"),
+      gid: number("gid"),
+      size: number("size"),
+    };
+
+    for key in METADATA_DATES {
+      let Some(value) = json[key].as_str() else { continue };
+      if let Ok(time) = self.date_repr.parse(value) {
+        self.push(time, path, source, key.to_string(), value.to_string(), Some(attributes.clone()));
+      }
+    }
+  }
+
+  fn add_json(&mut self, path: &str, json: &Value, pointer: &str, key: Option<&str>) {
+    match json {
+      Value::Object(map) => {
+        for (key, value) in map {
+          // dates added by the timestamps normalization are already found from their original values
+          if !key.ends_with(ADDED_KEY_SUFFIX) {
+            let pointer = format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+            self.add_json(path, value, &pointer, Some(key));
+          }
+        }
+      }
+      Value::Array(array) => {
+        for (i, value) in array.iter().enumerate() {
+          self.add_json(path, value, &format!("{pointer}/{i}"), key);
+        }
+      }
+      Value::Number(number) => {
+        let time = key
+          .zip(number.as_f64())
+          .and_then(|(key, timestamp)| self.normalizer.convert(key, timestamp));
+        if let Some(time) = time {
+          self.push(time, path, EventSource::Content, pointer.to_string(), number.to_string(), None);
+        }
+      }
+      Value::String(value) => {
+        if let Some(time) = self.parse_date(value) {
+          self.push(time, path, EventSource::Content, pointer.to_string(), value.clone(), None);
+        }
+      }
+      Value::Bool(_) | Value::Null => {}
+    }
+  }
+
+  /// Lines starting with a date (`2021-01-02T03:04:05Z ...`, `[2021-01-02 03:04:05.123] ...`)
+  fn add_log_lines(&mut self, path: &str, text: &str) {
+    for (i, line) in text.lines().enumerate() {
+      let mut tokens = line.trim_start_matches('[').split_whitespace();
+      let Some(first) = tokens.next() else { continue };
+      let candidate = match tokens.next() {
+        Some(second) if first.len() == "yyyy-mm-dd".len() => format!("{first} {second}"),
+        _ => first.to_string(),
+      };
+      let candidate = candidate.trim_end_matches([']', ',']);
+      if let Some(time) = self.parse_date(candidate) {
+        self.push(time, path, EventSource::Content, format!("line {}", i + 1), candidate.to_string(), None);
+      }
+    }
+  }
+
+  /// Dates in the representation of the scan, rfc3339 or `yyyy-mm-dd hh:mm:ss` (UTC) strings
+  fn parse_date(&self, value: &str) -> Option<SystemTime> {
+    let bytes = value.as_bytes();
+    let looks_like_a_date = (19..=40).contains(&bytes.len()) && bytes[..4].iter().all(u8::is_ascii_digit) && bytes[4] == b'-';
+    if !looks_like_a_date {
+      return None;
+    }
+
+    [&self.date_repr, &DateRepr::Rfc3339]
+      .into_iter()
+      .filter(|repr| !repr.is_epoch())
+      .find_map(|repr| repr.parse(value).ok())
+      .or_else(|| {
+        let format = self.log_date_format.as_ref()?;
+        PrimitiveDateTime::parse(value, format)
+          .ok()
+          .map(|datetime| datetime.assume_utc().into())
+      })
+  }
+}
+
+/// One json event per line
+pub fn write_jsonl(events: &[TimelineEvent], mut out: impl Write) -> Result<(), TimelineError> {
+  for event in events {
+    serde_json::to_writer(&mut out, event)?;
+    writeln!(out)?;
+  }
+  out.flush()?;
+  Ok(())
+}
+
+/// Csv with a header line: date, path, source, field, value
+pub fn write_csv(events: &[TimelineEvent], out: impl Write) -> Result<(), TimelineError> {
+  let mut writer = csv::Writer::from_writer(out);
+  for event in events {
+    writer.serialize(event)?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+/// Mactime bodyfile (`MD5|name|inode|mode_as_string|UID|GID|size|atime|mtime|ctime|crtime`, dates in unix seconds, 0 when unknown).
+/// Files and members have one line holding their metadata dates, the content timestamps have one line each, with the date in
+/// all the columns and the field in the name (`path (field)`). `|` characters of the names are written `%7C`.
+pub fn write_bodyfile(events: &[TimelineEvent], mut out: impl Write) -> Result<(), TimelineError> {
+  let mut lines: Vec<(String, FileAttributes, [i64; 4])> = vec![];
+  let mut files = HashMap::new();
+
+  for event in events {
+    let seconds = unix_seconds(event.time);
+    match (&event.attributes, METADATA_DATES.iter().position(|date| *date == event.field)) {
+      (Some(attributes), Some(column)) => {
+        let line = *files.entry(event.path.clone()).or_insert_with(|| {
+          lines.push((event.path.clone(), attributes.clone(), [0; 4]));
+          lines.len() - 1
+        });
+        lines[line].2[column] = seconds;
+      }
+      _ => lines.push((format!("{} ({})", event.path, event.field), FileAttributes::default(), [seconds; 4])),
+    }
+  }
+
+  for (name, attributes, [atime, mtime, ctime, btime]) in lines {
+    let FileAttributes { inode, mode, uid, gid, size } = attributes;
+    let mode = mode.map_or_else(|| "0".to_string(), mode_string);
+    let name = name.replace('|', "%7C");
+    writeln!(out, "0|{name}|{inode}|{mode}|{uid}|{gid}|{size}|{atime}|{mtime}|{ctime}|{btime}")?;
+  }
+  out.flush()?;
+  Ok(())
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+  match time.duration_since(UNIX_EPOCH) {
+    Ok(duration) => i64::try_from(duration.as_secs()).unwrap_or(i64::MAX),
+    Err(err) => -i64::try_from(err.duration().as_secs()).unwrap_or(i64::MAX),
+  }
+}
+
+/// `ls -l` like mode (`-rw-r--r--`)
+fn mode_string(mode: u32) -> String {
+  let kind = match mode & 0o170_000 {
+    0o040_000 => 'd',
+    0o120_000 => 'l',
+    0o010_000 => 'p',
+    0o140_000 => 's',
+    0o020_000 => 'c',
+    0o060_000 => 'b',
+    _ => '-',
+  };
+  let permissions = (0..9)
+    .rev()
+    .map(|bit| if mode & (1 << bit) == 0 { '-' } else { ['x', 'w', 'r'][bit % 3] });
+  std::iter::once(kind).chain(permissions).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{tests_helpers::scan_content, timestamps::TimestampMode};
+  use pretty_assertions::assert_eq;
+  use serde_json::json;
+
+  fn timeline() -> anyhow::Result<Vec<TimelineEvent>> {
+    let mut timeline = Timeline::new(TimestampNormalizer::new(TimestampMode::Add).with_heuristics()?, DateRepr::Rfc3339);
+    timeline.add(&scan_content(
+      "a.zip!/b.txt#__metadata",
+      Content::Json(json!({"type": "file", "size": 5, "mode": "100644", "mtime": "2021-01-03T00:00:00Z"})),
+    ));
+    timeline.add(&scan_content(
+      "c.db#visits",
+      Content::Json(json!([{"id": 1_609_459_200, "visit_time": 1_609_459_200, "visit_time__date": "2021-01-01T00:00:00Z"}])),
+    ));
+    timeline.add(&scan_content("d.plist", Content::Json(json!({"a/b": {"Expires": "2021-01-04T00:00:00Z", "Name": "2021"}}))));
+    timeline.add(&scan_content("e.log", Content::Text("[2021-01-02 00:00:00.5] start\nno date\n2021-01-05T00:00:00Z stop".into())));
+    timeline.add(&scan_content("f.bin", Content::Bytes(vec![0])));
+    Ok(timeline.into_events())
+  }
+
+  #[test]
+  fn events() -> anyhow::Result<()> {
+    let events = timeline()?
+      .into_iter()
+      .map(|event| (event.date, event.path, event.source, event.field))
+      .collect::<Vec<_>>();
+    let event = |date: &str, path: &str, source, field: &str| (date.to_string(), path.to_string(), source, field.to_string());
+    assert_eq!(
+      events,
+      [
+        event("2021-01-01T00:00:00Z", "c.db#visits", EventSource::Content, "/0/visit_time"),
+        event("2021-01-02T00:00:00.5Z", "e.log", EventSource::Content, "line 1"),
+        event("2021-01-03T00:00:00Z", "a.zip!/b.txt", EventSource::ArchiveMember, "mtime"),
+        event("2021-01-04T00:00:00Z", "d.plist", EventSource::Content, "/a~1b/Expires"),
+        event("2021-01-05T00:00:00Z", "e.log", EventSource::Content, "line 3"),
+      ]
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn formats() -> anyhow::Result<()> {
+    let events = timeline()?;
+
+    let mut bodyfile = vec![];
+    write_bodyfile(&events[..3], &mut bodyfile)?;
+    assert_eq!(
+      String::from_utf8(bodyfile)?,
+      "0|c.db#visits (/0/visit_time)|0|0|0|0|0|1609459200|1609459200|1609459200|1609459200\n\
+       0|e.log (line 1)|0|0|0|0|0|1609545600|1609545600|1609545600|1609545600\n\
+       0|a.zip!/b.txt|0|-rw-r--r--|0|0|5|0|1609632000|0|0\n"
+    );
+
+    let mut csv = vec![];
+    write_csv(&events[..1], &mut csv)?;
+    assert_eq!(
+      String::from_utf8(csv)?,
+      "date,path,source,field,value\n2021-01-01T00:00:00Z,c.db#visits,content,/0/visit_time,1609459200\n"
+    );
+
+    let mut jsonl = vec![];
+    write_jsonl(&events[2..3], &mut jsonl)?;
+    assert_eq!(
+      serde_json::from_slice::<Value>(&jsonl)?,
+      json!({"date": "2021-01-03T00:00:00Z", "path": "a.zip!/b.txt", "source": "archive_member", "field": "mtime", "value": "2021-01-03T00:00:00Z"})
+    );
+    Ok(())
+  }
+}
@@ -0,0 +1,235 @@
+//! Converts well-known epoch timestamps found in json contents (sqlite columns, plist keys...) to [`DateRepr`] strings.
+
+use crate::{filter::case_insensitive, DateRepr, ScanError};
+use glob::Pattern;
+use serde_json::{Map, Value};
+use std::{
+  ops::RangeInclusive,
+  time::{Duration, SystemTime},
+};
+
+/// Suffix of the key added next to the original value in [`TimestampMode::Add`] mode
+pub const ADDED_KEY_SUFFIX: &str = "__date";
+
+/// Key name patterns that are likely to hold a timestamp, their epoch is guessed from the value
+pub const HEURISTIC_PATTERNS: [&str; 5] = ["*date*", "*time*", "*_at", "*created*", "*modified*"];
+
+/// Smaller values are more likely durations or counters than dates, their epoch is not guessed
+const MIN_GUESSED_TIMESTAMP: f64 = 100_000_000.0;
+/// Guessed dates older than this unix timestamp (1990-01-01) are considered implausible
+const PLAUSIBLE_SINCE_SECS: u64 = 631_152_000;
+/// Guessed dates newer than this unix timestamp (2040-01-01) are considered implausible. The bound is fixed so that the
+/// same evidence always gives the same dates.
+const PLAUSIBLE_UNTIL_SECS: u64 = 2_208_988_800;
+
+/// Parts of key names (lowercase) hinting at the epoch of their values, tried before the other epochs
+const EPOCH_HINTS: [(&str, DateRepr); 11] = [
+  ("cocoa", DateRepr::CocoaSeconds),
+  ("absolute", DateRepr::CocoaSeconds),
+  ("webkit", DateRepr::WebKitMicros),
+  ("chrome", DateRepr::WebKitMicros),
+  ("usec", DateRepr::WebKitMicros),
+  ("micros", DateRepr::WebKitMicros),
+  ("millis", DateRepr::UnixMillis),
+  ("_ms", DateRepr::UnixMillis),
+  ("unix", DateRepr::UnixSeconds),
+  ("epoch", DateRepr::UnixSeconds),
+  ("_at", DateRepr::UnixSeconds),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TimestampMode {
+  /// Adds a `<key>__date` entry next to the original value
+  #[default]
+  Add,
+  /// Replaces the original value
+  Replace,
+}
+
+#[derive(Debug)]
+pub struct TimestampRule {
+  pattern: Pattern,
+  /// The epoch of the matching values, `None` means the epoch is guessed from the value
+  epoch: Option<DateRepr>,
+}
+
+#[derive(Debug)]
+pub struct TimestampNormalizer {
+  rules: Vec<TimestampRule>,
+  mode: TimestampMode,
+  plausible_range: RangeInclusive<SystemTime>,
+}
+
+impl TimestampNormalizer {
+  #[must_use]
+  pub fn new(mode: TimestampMode) -> Self {
+    let since = SystemTime::UNIX_EPOCH + Duration::from_secs(PLAUSIBLE_SINCE_SECS);
+    let until = SystemTime::UNIX_EPOCH + Duration::from_secs(PLAUSIBLE_UNTIL_SECS);
+    Self { rules: vec![], mode, plausible_range: since..=until }
+  }
+
+  /// Replaces the range of the dates considered plausible when guessing epochs (1990-01-01 to 2040-01-01 by default)
+  #[must_use]
+  pub fn with_plausible_range(mut self, plausible_range: RangeInclusive<SystemTime>) -> Self {
+    self.plausible_range = plausible_range;
+    self
+  }
+
+  /// Adds a rule for keys matching the glob, rules are evaluated in insertion order
+  pub fn with_rule(mut self, glob: &str, epoch: Option<DateRepr>) -> Result<Self, ScanError> {
+    if let Some(epoch) = epoch.as_ref().filter(|epoch| !epoch.is_epoch()) {
+      return Err(ScanError::DateReprError(crate::date_repr::DateReprError::NotAnEpoch(epoch.clone())));
+    }
+    self.rules.push(TimestampRule { pattern: Pattern::new(glob)?, epoch });
+    Ok(self)
+  }
+
+  /// Adds the [`HEURISTIC_PATTERNS`] rules, with guessed epochs
+  pub fn with_heuristics(self) -> Result<Self, ScanError> {
+    HEURISTIC_PATTERNS
+      .iter()
+      .try_fold(self, |normalizer, glob| normalizer.with_rule(glob, None))
+  }
+
+  /// Converts all the matching values of the json (recursively), formatting them with the `date_repr`
+  pub fn normalize(&self, json: &mut Value, date_repr: &DateRepr) {
+    match json {
+      Value::Array(array) => array.iter_mut().for_each(|value| self.normalize(value, date_repr)),
+      Value::Object(map) => self.normalize_map(map, date_repr),
+      _ => {}
+    }
+  }
+
+  fn normalize_map(&self, map: &mut Map<String, Value>, date_repr: &DateRepr) {
+    let mut added = vec![];
+
+    for (key, value) in map.iter_mut() {
+      if let Some(date) = value.as_f64().and_then(|timestamp| self.convert(key, timestamp)) {
+        let Ok(date) = date_repr.to_string(date) else { continue };
+        match self.mode {
+          TimestampMode::Replace => *value = Value::String(date),
+          TimestampMode::Add => added.push((format!("{key}{ADDED_KEY_SUFFIX}"), Value::String(date))),
+        }
+      } else {
+        self.normalize(value, date_repr);
+      }
+    }
+
+    for (key, date) in added {
+      map.entry(key).or_insert(date);
+    }
+  }
+
+  pub(crate) fn convert(&self, key: &str, timestamp: f64) -> Option<SystemTime> {
+    let rule = self.rules.iter().find(|rule| rule.pattern.matches_with(key, case_insensitive()))?;
+    match &rule.epoch {
+      Some(epoch) => epoch.from_timestamp(timestamp).ok(),
+      None => self.guess(key, timestamp),
+    }
+  }
+
+  /// The epoch hinted by the key name is kept if it gives a plausible date, otherwise the most recent plausible date of
+  /// all the epochs is kept
+  fn guess(&self, key: &str, timestamp: f64) -> Option<SystemTime> {
+    if timestamp.abs() < MIN_GUESSED_TIMESTAMP {
+      return None;
+    }
+    let plausible = |epoch: &DateRepr| {
+      epoch
+        .from_timestamp(timestamp)
+        .ok()
+        .filter(|date| self.plausible_range.contains(date))
+    };
+    hinted_epoch(key)
+      .and_then(plausible)
+      .or_else(|| DateRepr::EPOCHS.iter().filter_map(plausible).max())
+  }
+}
+
+/// Core Data columns (`ZCREATIONDATE`) hold Cocoa dates, other keys are matched against [`EPOCH_HINTS`]
+fn hinted_epoch(key: &str) -> Option<&'static DateRepr> {
+  let core_data = key.starts_with('Z') && key.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+  if core_data {
+    return Some(&DateRepr::CocoaSeconds);
+  }
+  let key = key.to_lowercase();
+  EPOCH_HINTS.iter().find(|(hint, _)| key.contains(hint)).map(|(_, epoch)| epoch)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+  use serde_json::json;
+  use test_case::test_case;
+
+  #[test_case(1_609_459_200, "2021-01-01T00:00:00Z" ; "unix seconds")]
+  #[test_case(1_609_459_200_000_i64, "2021-01-01T00:00:00Z" ; "unix millis")]
+  #[test_case(631_152_000, "2021-01-01T00:00:00Z" ; "cocoa")]
+  #[test_case(13_253_932_800_000_000_i64, "2021-01-01T00:00:00Z" ; "webkit")]
+  fn guess_epochs(timestamp: i64, expected: &str) -> anyhow::Result<()> {
+    let normalizer = TimestampNormalizer::new(TimestampMode::Replace).with_heuristics()?;
+    let mut json = json!({ "ZCREATIONDATE": timestamp });
+    normalizer.normalize(&mut json, &DateRepr::Rfc3339);
+    assert_eq!(json, json!({ "ZCREATIONDATE": expected }));
+    Ok(())
+  }
+
+  #[test]
+  fn key_names_hint_epochs() -> anyhow::Result<()> {
+    let normalizer = TimestampNormalizer::new(TimestampMode::Replace).with_heuristics()?;
+    // plausible as unix seconds (2001) and as cocoa seconds (2032)
+    let mut json = json!({ "updated_at": 1_000_000_000, "ZDATE": 1_000_000_000, "date": 1_000_000_000 });
+    normalizer.normalize(&mut json, &DateRepr::Rfc3339);
+    assert_eq!(json, json!({ "updated_at": "2001-09-09T01:46:40Z", "ZDATE": "2032-09-09T01:46:40Z", "date": "2032-09-09T01:46:40Z" }));
+
+    // the range is fixed unless configured
+    let until = DateRepr::UnixSeconds.from_timestamp(1_500_000_000.0)?;
+    let normalizer = normalizer.with_plausible_range(SystemTime::UNIX_EPOCH..=until);
+    let mut json = json!({ "date": 1_000_000_000 });
+    normalizer.normalize(&mut json, &DateRepr::Rfc3339);
+    assert_eq!(json, json!({ "date": "2001-09-09T01:46:40Z" }));
+    Ok(())
+  }
+
+  #[test]
+  fn implausible_values_are_kept() -> anyhow::Result<()> {
+    let normalizer = TimestampNormalizer::new(TimestampMode::Replace).with_heuristics()?;
+    let mut json = json!([{ "timeInterval": 12.5, "name": 1_609_459_200, "date": "yesterday" }]);
+    let expected = json.clone();
+    normalizer.normalize(&mut json, &DateRepr::Rfc3339);
+    assert_eq!(json, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn explicit_rules_come_first() -> anyhow::Result<()> {
+    let normalizer = TimestampNormalizer::new(TimestampMode::Add)
+      .with_rule("last_visit_time", Some(DateRepr::WebKitMicros))?
+      .with_rule("z*", Some(DateRepr::CocoaSeconds))?
+      .with_heuristics()?;
+
+    let mut json = json!({ "rows": [{ "last_visit_time": 0, "ZDATE": 0, "updated_at": 1_609_459_200 }] });
+    normalizer.normalize(&mut json, &DateRepr::Rfc3339);
+
+    assert_eq!(
+      json,
+      json!({ "rows": [{
+        "last_visit_time": 0,
+        "ZDATE": 0,
+        "updated_at": 1_609_459_200,
+        "last_visit_time__date": "1601-01-01T00:00:00Z",
+        "ZDATE__date": "2001-01-01T00:00:00Z",
+        "updated_at__date": "2021-01-01T00:00:00Z"
+      }]})
+    );
+    Ok(())
+  }
+
+  #[test]
+  fn rules_need_epochs() {
+    assert!(TimestampNormalizer::new(TimestampMode::Add)
+      .with_rule("*", Some(DateRepr::Rfc3339))
+      .is_err());
+  }
+}
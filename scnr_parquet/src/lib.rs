@@ -0,0 +1,319 @@
+//! Export of the tabular contents of a scan in parquet files (`scnr export --to parquet out_dir`): json arrays of objects
+//! (sqlite tables, json files) and csv files. The arrow schema of a file is inferred from the first node written in it,
+//! every node gets its own file laid out by `rel_path` (`a.zip/b.db/users.parquet`, without its `..` and root components),
+//! unless it belongs to a named group of nodes matching a glob (`out_dir/<group>.parquet`).
+//! The file of a node is closed as soon as the next node is written (the parts of a split table arrive one after the
+//! other), the files of the groups stay open until the end of the export.
+
+#![allow(clippy::module_name_repetitions, clippy::wildcard_imports)]
+#![deny(clippy::expect_used, clippy::unwrap_used, clippy::panic)]
+
+use arrow_array::RecordBatch;
+use arrow_schema::{ArrowError, Schema, SchemaRef};
+use parquet::{arrow::ArrowWriter, basic::Compression, errors::ParquetError, file::properties::WriterProperties};
+use scnr_core::{
+  filter::{Glob, ScanFilter},
+  scan_path::sanitize_path,
+  *,
+};
+use serde_json::Value;
+use std::{
+  collections::{HashMap, HashSet},
+  fs::File,
+  io::Cursor,
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+
+/// Rows decoded per record batch
+const BATCH_SIZE: usize = 8192;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Parquet error: {0}")]
+  Parquet(#[from] ParquetError),
+  #[error("Invalid group glob: {0}")]
+  Glob(#[from] ScanError),
+  /// The rows of a node do not fit in the schema of its file, the node is not written
+  #[error("`{path}` does not match the schema of {}: {source}", file.display())]
+  Mismatch { path: String, file: PathBuf, source: ArrowError },
+}
+
+struct ParquetFile {
+  writer: ArrowWriter<File>,
+  schema: SchemaRef,
+  rows: usize,
+}
+
+/// Writes the tabular contents of a scan in parquet files, under a directory
+pub struct ParquetExport {
+  root: PathBuf,
+  groups: Vec<(String, Glob)>,
+  /// Open files: the ones of the groups, and the one of the last node out of the groups
+  files: HashMap<PathBuf, ParquetFile>,
+  node_file: Option<PathBuf>,
+  closed: Vec<(PathBuf, usize)>,
+}
+
+impl ParquetExport {
+  /// The directory is created if needed, existing parquet files are overwritten
+  pub fn create(root: &Path) -> Result<Self, ExportError> {
+    std::fs::create_dir_all(root)?;
+    Ok(Self { root: root.to_path_buf(), groups: vec![], files: HashMap::new(), node_file: None, closed: vec![] })
+  }
+
+  /// The nodes matching the glob (`**/*.db#logs`, `logs/*.csv`) are all written in `<name>.parquet`, with one schema
+  pub fn with_group(mut self, name: &str, glob: &str) -> Result<Self, ExportError> {
+    self.groups.push((name.to_string(), Glob::new(glob)?));
+    Ok(self)
+  }
+
+  /// Writes the rows of a tabular content and returns their number, other contents are ignored (0 rows)
+  pub fn add(&mut self, content: &ScanContent) -> Result<usize, ExportError> {
+    let file = self.file_path(content)?;
+    let path = content.path.to_string();
+    let mismatch = |source| ExportError::Mismatch { path: path.clone(), file: file.clone(), source };
+
+    let batches = match &content.content {
+      Content::Json(Value::Array(rows)) if !rows.is_empty() && rows.iter().all(Value::is_object) => {
+        let schema = match self.files.get(&file) {
+          Some(parquet) => parquet.schema.clone(),
+          None => Arc::new(json_schema(rows).map_err(mismatch)?),
+        };
+        json_batches(&schema, rows).map_err(mismatch)?
+      }
+      Content::Text(text) if is_csv(&content.rel_path) => {
+        let schema = match self.files.get(&file) {
+          Some(parquet) => parquet.schema.clone(),
+          None => Arc::new(csv_schema(text).map_err(mismatch)?),
+        };
+        csv_batches(&schema, text).map_err(mismatch)?
+      }
+      _ => return Ok(0),
+    };
+    let Some(first) = batches.first() else { return Ok(0) };
+
+    if !self.files.contains_key(&file) {
+      if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+      }
+      let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+      let writer = ArrowWriter::try_new(File::create(&file)?, first.schema(), Some(properties))?;
+      self
+        .files
+        .insert(file.clone(), ParquetFile { writer, schema: first.schema(), rows: 0 });
+    }
+    let Some(parquet) = self.files.get_mut(&file) else { return Ok(0) };
+
+    let mut rows = 0;
+    for batch in &batches {
+      parquet.writer.write(batch)?;
+      rows += batch.num_rows();
+    }
+    parquet.rows += rows;
+    Ok(rows)
+  }
+
+  /// Closes the parquet files and returns them with their number of rows
+  pub fn finish(self) -> Result<Vec<(PathBuf, usize)>, ExportError> {
+    let mut files = self.closed;
+    for (path, parquet) in self.files {
+      parquet.writer.close()?;
+      files.push((path, parquet.rows));
+    }
+    files.sort();
+    Ok(files)
+  }
+
+  /// The file of the group of the content, or of the content itself: the file of the previous node is closed when it is
+  /// another one, and a node whose file was already closed (a path seen again later) gets a `~2`, `~3`... suffix
+  fn file_path(&mut self, content: &ScanContent) -> Result<PathBuf, ExportError> {
+    let group = self
+      .groups
+      .iter()
+      .find(|(_, glob)| glob.should_scan_path(&content.rel_path, &content.path));
+    if let Some((name, _)) = group {
+      return Ok(self.root.join(sanitize_path(Path::new(&format!("{name}.parquet")))));
+    }
+
+    let node = self.root.join(sanitize_path(&content.rel_path));
+    let with_suffix = |suffix: &str| {
+      let mut file = node.clone().into_os_string();
+      file.push(suffix);
+      PathBuf::from(file)
+    };
+    let file = with_suffix(".parquet");
+    if self.node_file.as_ref() == Some(&file) {
+      return Ok(file);
+    }
+
+    if let Some((path, parquet)) = self.node_file.take().and_then(|node_file| self.files.remove_entry(&node_file)) {
+      parquet.writer.close()?;
+      self.closed.push((path, parquet.rows));
+    }
+    let closed = self.closed.iter().map(|(path, _)| path).collect::<HashSet<_>>();
+    let mut free = file;
+    let mut n = 2;
+    while closed.contains(&free) || self.files.contains_key(&free) {
+      free = with_suffix(&format!("~{n}.parquet"));
+      n += 1;
+    }
+    self.node_file = Some(free.clone());
+    Ok(free)
+  }
+}
+
+fn is_csv(rel_path: &Path) -> bool {
+  rel_path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv"))
+}
+
+/// Inferred from all the rows, the columns are kept in their order of appearance
+fn json_schema(rows: &[Value]) -> Result<Schema, ArrowError> {
+  let inferred = arrow_json::reader::infer_json_schema_from_iterator(rows.iter().map(Ok))?;
+
+  let mut columns = vec![];
+  for row in rows.iter().filter_map(Value::as_object) {
+    for column in row.keys() {
+      if !columns.contains(&column) {
+        columns.push(column);
+      }
+    }
+  }
+  let mut fields = inferred.fields().iter().cloned().collect::<Vec<_>>();
+  fields.sort_by_key(|field| columns.iter().position(|column| *column == field.name()));
+  Ok(Schema::new(fields))
+}
+
+/// The columns missing from the schema are dropped
+fn json_batches(schema: &SchemaRef, rows: &[Value]) -> Result<Vec<RecordBatch>, ArrowError> {
+  let mut decoder = arrow_json::ReaderBuilder::new(schema.clone()).build_decoder()?;
+  let mut batches = vec![];
+  for chunk in rows.chunks(BATCH_SIZE) {
+    decoder.serialize(chunk)?;
+    batches.extend(decoder.flush()?);
+  }
+  Ok(batches)
+}
+
+fn csv_schema(text: &str) -> Result<Schema, ArrowError> {
+  let (schema, _) = arrow_csv::reader::Format::default()
+    .with_header(true)
+    .infer_schema(Cursor::new(text), None)?;
+  Ok(schema)
+}
+
+fn csv_batches(schema: &SchemaRef, text: &str) -> Result<Vec<RecordBatch>, ArrowError> {
+  arrow_csv::ReaderBuilder::new(schema.clone())
+    .with_header(true)
+    .with_batch_size(BATCH_SIZE)
+    .build(Cursor::new(text))?
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use parquet::file::reader::{FileReader, SerializedFileReader};
+  use scnr_core::{
+    plugins::DefaultPluginPicker,
+    tests_helpers::{get_samples_path, scan_content},
+  };
+  use serde_json::json;
+
+  fn parquet_columns(path: &Path) -> anyhow::Result<(Vec<String>, i64)> {
+    let reader = SerializedFileReader::new(File::open(path)?)?;
+    let metadata = reader.metadata().file_metadata();
+    let columns = metadata
+      .schema_descr()
+      .columns()
+      .iter()
+      .map(|column| column.name().to_string())
+      .collect();
+    Ok((columns, metadata.num_rows()))
+  }
+
+  #[test]
+  fn export_database_tables() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.db", scnr_sqlite::SqlitePlugin)?
+      .build_with_defaults()?;
+    let scanner = Scanner::new(&format!("{samples}/sakila_country_only.db"), picker).with_options(ScannerOptions {
+      plugin_options: PluginOptions::new().with(scnr_sqlite::JSON_ARRAY_LIMIT_OPTION, "50"),
+      ..Default::default()
+    });
+
+    let dir = tempfile::tempdir()?;
+    let mut export = ParquetExport::create(dir.path())?;
+    let mut rows = 0;
+    for content in scanner.get_all()? {
+      rows += export.add(&content?)?;
+    }
+    assert_eq!(rows, 109);
+
+    // the split table is written again in one file, the schema node is not tabular
+    let file = dir.path().join("sakila_country_only.db/country.parquet");
+    assert_eq!(export.finish()?, [(file.clone(), 109)]);
+    assert_eq!(parquet_columns(&file)?, (vec!["country_id".into(), "country".into(), "last_update".into()], 109));
+
+    Ok(())
+  }
+
+  #[test]
+  fn groups_and_csv() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut export = ParquetExport::create(dir.path())?.with_group("users", "**/users*.json")?;
+
+    let users = json!([{ "name": "a", "age": 1 }, { "name": "b" }]);
+    assert_eq!(export.add(&scan_content("a/users1.json", Content::Json(users)))?, 2);
+    // extra columns are dropped, the types must match the schema of the group
+    let users = json!([{ "age": 3, "name": "c", "extra": true }]);
+    assert_eq!(export.add(&scan_content("b.zip!/users2.json", Content::Json(users)))?, 1);
+    let users = json!([{ "age": "old" }]);
+    assert!(matches!(export.add(&scan_content("users3.json", Content::Json(users))), Err(ExportError::Mismatch { .. })));
+
+    assert_eq!(export.add(&scan_content("a/data.csv", Content::Text("id,label\n1,one\n2,two\n".into())))?, 2);
+    assert_eq!(export.add(&scan_content("a/notes.txt", Content::Text("id,label\n".into())))?, 0);
+    assert_eq!(export.add(&scan_content("a/object.json", Content::Json(json!({ "name": "a" }))))?, 0);
+
+    let users = dir.path().join("users.parquet");
+    let data = dir.path().join("a/data.csv.parquet");
+    assert_eq!(export.finish()?, [(data.clone(), 2), (users.clone(), 3)]);
+    assert_eq!(parquet_columns(&users)?, (vec!["name".into(), "age".into()], 3));
+    assert_eq!(parquet_columns(&data)?, (vec!["id".into(), "label".into()], 2));
+
+    Ok(())
+  }
+
+  #[test]
+  fn node_files() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("out");
+    let mut export = ParquetExport::create(&out)?;
+    let rows = |n: i64| Content::Json(json!([{ "n": n }]));
+
+    // nothing is written outside of the export directory
+    assert_eq!(export.add(&scan_content("a.zip!/../../escaped.json", rows(1)))?, 1);
+    assert_eq!(export.add(&scan_content("/abs.json", rows(2)))?, 1);
+    // the file of a node is closed when the next node comes, a node seen again gets another file
+    assert_eq!(export.add(&scan_content("x.db#t", rows(3)))?, 1);
+    assert_eq!(parquet_columns(&out.join("abs.json.parquet"))?, (vec!["n".into()], 1));
+    assert_eq!(export.add(&scan_content("x.db#t", rows(4)))?, 1);
+    assert_eq!(export.add(&scan_content("abs.json", rows(5)))?, 1);
+
+    assert_eq!(
+      export.finish()?,
+      [
+        (out.join("a.zip/escaped.json.parquet"), 1),
+        (out.join("abs.json.parquet"), 1),
+        (out.join("abs.json~2.parquet"), 1),
+        (out.join("x.db/t.parquet"), 2)
+      ]
+    );
+    assert!(!dir.path().join("escaped.json.parquet").exists());
+
+    Ok(())
+  }
+}
@@ -13,13 +13,13 @@ impl ScanPlugin for PlistPlugin {
   fn scan(&self, context: &ScanContext, reader: ScanReader<'_>) -> ScanPluginResult {
     let seekable = reader.into_seekable()?;
     let plist_value = from_reader::<_, Value>(seekable)?;
-    let content = Content::Json(plist_to_json(plist_value, context.bin_repr, context.date_repr)?);
+    let content = Content::Json(plist_to_json(plist_value, context.bin_repr, &context.date_repr)?);
     context.send_content(content)?;
     Ok(())
   }
 }
 
-fn plist_to_json(plist: Value, bin_repr: BinRepr, date_repr: DateRepr) -> Result<serde_json::Value, ScanError> {
+fn plist_to_json(plist: Value, bin_repr: BinRepr, date_repr: &DateRepr) -> Result<serde_json::Value, ScanError> {
   use serde_json::Value as J;
   Ok(match plist {
     Value::Array(a) => J::Array(
@@ -72,7 +72,7 @@ mod tests {
   fn test_xml() -> anyhow::Result<()> {
     let result = get_plist_content("sampled.xml.plist");
 
-    let Ok(ScanContent { rel_path, content: Content::Json(_json) }) = result else {
+    let Ok(ScanContent { rel_path, content: Content::Json(_json), .. }) = result else {
       anyhow::bail!("Expected a json content, got {:?}", result)
     };
     assert_eq!(rel_path.as_os_str(), "");
@@ -84,7 +84,7 @@ mod tests {
   fn test_bin() -> anyhow::Result<()> {
     let result = get_plist_content("sampled.plist");
 
-    let Ok(ScanContent { rel_path, content: Content::Json(_json) }) = result else {
+    let Ok(ScanContent { rel_path, content: Content::Json(_json), .. }) = result else {
       anyhow::bail!("Expected a json content, got {:?}", result)
     };
     assert_eq!(rel_path.as_os_str(), "");
@@ -0,0 +1,229 @@
+//! Export of a scan in a sqlite database (`scnr export --to sqlite`): one row per content in the `nodes` table (json
+//! contents are queryable with the sqlite json functions, e.g. `json_extract(json, '$.name')`), one row per error in the
+//! `errors` table. The tables of the scanned databases can be materialised again as real tables, named after their node.
+
+use crate::{sqlite_ext::quote_identifier, SqlitePlugin, SCHEMA_NODE_NAME};
+use rusqlite::{params, params_from_iter, types, Connection, OpenFlags};
+use scnr_core::{listing::short_plugin_name, scan_path::SegmentKind, *};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, path::Path};
+
+const SCHEMA: &str = "
+CREATE TABLE nodes (
+  id INTEGER PRIMARY KEY,
+  path TEXT NOT NULL,
+  type TEXT NOT NULL,
+  plugin TEXT,
+  size INTEGER NOT NULL,
+  sha256 TEXT NOT NULL,
+  text TEXT,
+  json TEXT CHECK (json IS NULL OR json_valid(json)),
+  bin BLOB,
+  duplicate_of TEXT
+);
+CREATE INDEX nodes_path ON nodes (path);
+CREATE TABLE errors (id INTEGER PRIMARY KEY, path TEXT, plugin TEXT, message TEXT NOT NULL);
+";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ExportError {
+  #[error("Sqlite error: {0}")]
+  Sqlite(#[from] rusqlite::Error),
+  #[error("Json error: {0}")]
+  Json(#[from] serde_json::Error),
+}
+
+/// Writes the contents of a scan in a new sqlite file
+pub struct SqliteExport {
+  conn: Connection,
+  materialize: bool,
+  /// Columns of the materialised tables, by table name
+  tables: HashMap<String, Vec<String>>,
+  nodes: usize,
+}
+
+impl SqliteExport {
+  /// Creates the database file, which must not exist
+  pub fn create(path: &Path) -> Result<Self, ExportError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+    conn.execute_batch(SCHEMA)?;
+    conn.execute_batch("BEGIN")?;
+    Ok(Self { conn, materialize: false, tables: HashMap::new(), nodes: 0 })
+  }
+
+  /// The tables of the scanned databases (`a.db#users`) are also written as tables, named after their node path
+  #[must_use]
+  pub fn with_materialized_tables(mut self) -> Self {
+    self.materialize = true;
+    self
+  }
+
+  /// Stores a content, the size and sha256 are the ones of the stored data (json text, text or bytes)
+  pub fn add(&mut self, content: &ScanContent) -> Result<(), ExportError> {
+    let (text, json, bin) = match &content.content {
+      Content::Json(json) => (None, Some(serde_json::to_string(json)?), None),
+      Content::Text(text) => (Some(text.as_str()), None, None),
+      Content::Bytes(bytes) => (None, None, Some(bytes.as_slice())),
+    };
+    let data = text
+      .map(str::as_bytes)
+      .or(json.as_deref().map(str::as_bytes))
+      .or(bin)
+      .unwrap_or_default();
+    let sha256 = format!("{:x}", Sha256::digest(data));
+
+    self.conn.execute(
+      "INSERT INTO nodes (path, type, plugin, size, sha256, text, json, bin, duplicate_of) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+      params![
+        content.path.to_string(),
+        content.content.to_string(),
+        content.plugin.map(short_plugin_name),
+        data.len(),
+        sha256,
+        text,
+        json,
+        bin,
+        content.duplicate_of.as_ref().map(ToString::to_string),
+      ],
+    )?;
+    self.nodes += 1;
+
+    if let (true, Content::Json(Value::Array(rows))) = (self.materialize && is_database_table(content), &content.content) {
+      self.materialize_rows(&content.path.to_string(), rows)?;
+    }
+
+    Ok(())
+  }
+
+  pub fn add_error(&mut self, error: &ScanError) -> Result<(), ExportError> {
+    self.conn.execute(
+      "INSERT INTO errors (path, plugin, message) VALUES (?1, ?2, ?3)",
+      params![error.path().map(ToString::to_string), error.plugin().map(short_plugin_name), error.to_string()],
+    )?;
+    Ok(())
+  }
+
+  /// Commits the database and returns the number of nodes
+  pub fn finish(self) -> Result<usize, ExportError> {
+    self.conn.execute_batch("COMMIT")?;
+    Ok(self.nodes)
+  }
+
+  /// Appends the rows to the table (created or extended with the columns of the rows), the columns are not typed
+  fn materialize_rows(&mut self, table: &str, rows: &[Value]) -> Result<(), ExportError> {
+    let rows = rows.iter().filter_map(Value::as_object).collect::<Vec<_>>();
+    let Some(first) = rows.first() else { return Ok(()) };
+
+    if !self.tables.contains_key(table) {
+      let definitions = first.keys().map(|column| quote_identifier(column)).collect::<Vec<_>>();
+      self
+        .conn
+        .execute_batch(&format!("CREATE TABLE {} ({})", quote_identifier(table), definitions.join(", ")))?;
+      self.tables.insert(table.to_string(), first.keys().cloned().collect());
+    }
+    let Some(columns) = self.tables.get_mut(table) else { return Ok(()) };
+
+    for row in &rows {
+      for column in row.keys() {
+        if !columns.contains(column) {
+          self
+            .conn
+            .execute_batch(&format!("ALTER TABLE {} ADD COLUMN {}", quote_identifier(table), quote_identifier(column)))?;
+          columns.push(column.clone());
+        }
+      }
+    }
+
+    let names = columns.iter().map(|column| quote_identifier(column)).collect::<Vec<_>>();
+    let placeholders = (1..=columns.len()).map(|i| format!("?{i}")).collect::<Vec<_>>();
+    let mut insert =
+      self
+        .conn
+        .prepare(&format!("INSERT INTO {} ({}) VALUES ({})", quote_identifier(table), names.join(", "), placeholders.join(", ")))?;
+    for row in rows {
+      insert.execute(params_from_iter(columns.iter().map(|column| json_to_sqlite(row.get(column)))))?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Tables and views sent by the sqlite plugin (`a.db#users`), the schema excepted
+fn is_database_table(content: &ScanContent) -> bool {
+  content.plugin == Some(SqlitePlugin.name())
+    && content
+      .path
+      .segments()
+      .last()
+      .is_some_and(|segment| segment.kind == SegmentKind::PluginChild && segment.name != SCHEMA_NODE_NAME)
+}
+
+fn json_to_sqlite(value: Option<&Value>) -> types::Value {
+  match value {
+    None | Some(Value::Null) => types::Value::Null,
+    Some(Value::Bool(b)) => types::Value::Integer(i64::from(*b)),
+    Some(Value::Number(number)) => match number.as_i64() {
+      Some(i) => types::Value::Integer(i),
+      None => number.as_f64().map_or(types::Value::Null, types::Value::Real),
+    },
+    Some(Value::String(s)) => types::Value::Text(s.clone()),
+    Some(value) => types::Value::Text(value.to_string()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use scnr_core::{plugins::DefaultPluginPicker, tests_helpers::get_samples_path};
+
+  #[test]
+  fn export_with_tables() -> anyhow::Result<()> {
+    let samples = get_samples_path()?;
+    let picker = DefaultPluginPicker::builder()
+      .push_plugin("*.db", SqlitePlugin)?
+      .build_with_defaults()?;
+    let scanner = Scanner::new(&format!("{samples}/sakila_country_only.db"), picker).with_options(ScannerOptions {
+      plugin_options: PluginOptions::new().with(crate::JSON_ARRAY_LIMIT_OPTION, "50"),
+      ..Default::default()
+    });
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("export.db");
+    let mut export = SqliteExport::create(&path)?.with_materialized_tables();
+    for content in scanner.get_all()? {
+      match content {
+        Ok(content) => export.add(&content)?,
+        Err(err) => export.add_error(&err)?,
+      }
+    }
+    export.add_error(&ScanError::Unreadable { path: ScanPath::parse("x.db"), message: "denied".into() })?;
+    assert_eq!(export.finish()?, 4);
+
+    let conn = Connection::open(&path)?;
+    let nodes = conn
+      .prepare("SELECT path, type, plugin FROM nodes ORDER BY id")?
+      .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+      .collect::<Result<Vec<_>, _>>()?;
+    let node = |path: &str| (path.to_string(), "json".to_string(), "SqlitePlugin".to_string());
+    assert_eq!(
+      nodes,
+      [
+        node("sakila_country_only.db#country"),
+        node("sakila_country_only.db#country"),
+        node("sakila_country_only.db#country"),
+        node("sakila_country_only.db#__schema")
+      ]
+    );
+
+    // json contents are queryable, the split table is materialised again
+    let country: String = conn.query_row("SELECT json_extract(json, '$[0].country') FROM nodes WHERE id = 1", [], |row| row.get(0))?;
+    assert_eq!(country, "Afghanistan");
+    let count: i64 = conn.query_row(r#"SELECT count(*) FROM "sakila_country_only.db#country""#, [], |row| row.get(0))?;
+    assert_eq!(count, 109);
+    let error: String = conn.query_row("SELECT path FROM errors", [], |row| row.get(0))?;
+    assert_eq!(error, "x.db");
+
+    Ok(())
+  }
+}
@@ -0,0 +1,437 @@
+//! Persistent index of a scan: the converted contents of all nodes are stored in a sqlite file, so that later scans
+//! (jq queries...) read them back instead of decompressing and parsing the input again.
+//!
+//! The index remembers the size and modification time of every scanned file of the input, files that changed since
+//! the indexing (or new ones) are scanned again, the others are replayed from the index. It also remembers the parameters
+//! of the scan (see [`ScannerOptions::run_params`]), all the files are scanned again with other parameters.
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use scnr_core::{
+  plugins::file_system::{for_each_file, METADATA_NODE_NAME},
+  scan_path::SegmentKind,
+  *,
+};
+use std::{
+  collections::{HashMap, HashSet},
+  path::{Path, PathBuf},
+  time::UNIX_EPOCH,
+};
+
+/// Version of the index tables, indexes of other versions are not opened
+pub const INDEX_VERSION: &str = "1";
+
+/// Plugin option: root of the indexed input, when it has moved since the indexing
+pub const ROOT_OPTION: &str = "index.root";
+
+/// Key of the scan parameters in the `scnr_index` table
+const RUN_PARAMS_KEY: &str = "run_params";
+
+/// Key of the filters in the scan parameters (a json object), the filters of a scan are applied to the replayed
+/// contents: an index built without filters can be replayed whatever the filters of the scan
+pub const RUN_PARAMS_FILTERS: &str = "filters";
+
+const SCHEMA: &str = "
+CREATE TABLE scnr_index (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE sources (path TEXT PRIMARY KEY, size INTEGER NOT NULL, mtime_ns INTEGER NOT NULL);
+CREATE TABLE nodes (id INTEGER PRIMARY KEY, source TEXT NOT NULL, path TEXT NOT NULL, kind TEXT NOT NULL, content BLOB NOT NULL);
+CREATE INDEX nodes_source ON nodes (source);
+";
+
+#[derive(thiserror::Error, Debug)]
+pub enum IndexError {
+  #[error("Sqlite error: {0}")]
+  Sqlite(#[from] rusqlite::Error),
+  #[error("IO error: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("Json error: {0}")]
+  Json(#[from] serde_json::Error),
+  #[error("`{0}` is not a scnr index (version {INDEX_VERSION})")]
+  NotAnIndex(PathBuf),
+  #[error("Content `{0}` does not come from a file of the input")]
+  NoSource(ScanPath),
+  #[error("Unknown content kind `{0}`")]
+  UnknownKind(String),
+}
+
+/// Size and modification time (in nanoseconds since the unix epoch) of a file
+type FileStamp = (i64, i64);
+
+fn file_stamp(path: &Path) -> Result<FileStamp, IndexError> {
+  let metadata = std::fs::metadata(path)?;
+  let mtime = metadata
+    .modified()?
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |duration| duration.as_nanos());
+  Ok((i64::try_from(metadata.len()).unwrap_or(i64::MAX), i64::try_from(mtime).unwrap_or(i64::MAX)))
+}
+
+/// Full path of an indexed file, the root is the file itself when a single file was indexed
+fn source_file(root: &Path, source: &str) -> PathBuf {
+  if root.is_file() {
+    root.to_path_buf()
+  } else {
+    root.join(source)
+  }
+}
+
+/// Writes the contents of a scan in a new index file
+pub struct IndexWriter {
+  conn: Connection,
+  root: PathBuf,
+  sources: HashSet<String>,
+  nodes: usize,
+  run_params: Option<String>,
+}
+
+impl IndexWriter {
+  /// Creates the index file (which must not exist) for a scan started on `root`
+  pub fn create(path: &Path, root: &Path) -> Result<Self, IndexError> {
+    let root = std::fs::canonicalize(root)?;
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)?;
+    conn.execute_batch(SCHEMA)?;
+    conn.execute(
+      "INSERT INTO scnr_index (key, value) VALUES ('version', ?1), ('root', ?2)",
+      params![INDEX_VERSION, root.to_string_lossy()],
+    )?;
+    conn.execute_batch("BEGIN")?;
+    Ok(Self { conn, root, sources: HashSet::new(), nodes: 0, run_params: None })
+  }
+
+  /// Parameters of the scan (see [`ScannerOptions::run_params`]), the index is only replayed by scans with the same ones
+  #[must_use]
+  pub fn with_run_params(mut self, run_params: Option<&str>) -> Self {
+    self.run_params = run_params.map(ToString::to_string);
+    self
+  }
+
+  /// Stores a content, the first content of a file also stores the file size and modification time
+  pub fn add(&mut self, content: &ScanContent) -> Result<(), IndexError> {
+    let Some(source) = source_of(&content.path) else {
+      return Err(IndexError::NoSource(content.path.clone()));
+    };
+
+    self.add_source(&source)?;
+
+    let data = match &content.content {
+      Content::Json(json) => serde_json::to_vec(json)?,
+      Content::Text(text) => text.as_bytes().to_vec(),
+      Content::Bytes(bytes) => bytes.clone(),
+    };
+    self.conn.execute(
+      "INSERT INTO nodes (source, path, kind, content) VALUES (?1, ?2, ?3, ?4)",
+      params![source, content.path.to_string(), content.content.to_string(), data],
+    )?;
+    self.nodes += 1;
+
+    Ok(())
+  }
+
+  fn add_source(&mut self, source: &str) -> Result<(), IndexError> {
+    if !self.sources.contains(source) {
+      let (size, mtime) = file_stamp(&source_file(&self.root, source))?;
+      self
+        .conn
+        .execute("INSERT INTO sources (path, size, mtime_ns) VALUES (?1, ?2, ?3)", params![source, size, mtime])?;
+      self.sources.insert(source.to_string());
+    }
+    Ok(())
+  }
+
+  /// Records the file a scan error comes from, so that a file that failed is not scanned again.
+  /// The files that were not scanned (filtered out...) are not recorded, nor the ones whose size cannot be read.
+  pub fn add_error(&mut self, error: &ScanError) -> Result<(), IndexError> {
+    match error.path().and_then(source_of) {
+      Some(source) => match self.add_source(&source) {
+        Err(IndexError::Io(err)) => {
+          tracing::debug!("`{source}` is not recorded in the index: {err}");
+          Ok(())
+        }
+        result => result,
+      },
+      None => Ok(()),
+    }
+  }
+
+  /// Commits the index and returns the number of files and contents
+  pub fn finish(self) -> Result<(usize, usize), IndexError> {
+    if let Some(run_params) = &self.run_params {
+      self
+        .conn
+        .execute("INSERT INTO scnr_index (key, value) VALUES (?1, ?2)", params![RUN_PARAMS_KEY, run_params])?;
+    }
+    self.conn.execute_batch("COMMIT")?;
+    Ok((self.sources.len(), self.nodes))
+  }
+}
+
+/// The file of the input a node comes from
+fn source_of(path: &ScanPath) -> Option<String> {
+  match path.segments().first() {
+    Some(segment) if segment.kind == SegmentKind::FileSystem => Some(segment.name.clone()),
+    _ => None,
+  }
+}
+
+fn meta(conn: &Connection, key: &str) -> Option<String> {
+  conn
+    .query_row("SELECT value FROM scnr_index WHERE key = ?1", [key], |row| row.get(0))
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// Opens an index file, fails if it is not a sqlite file holding an index of the current version
+fn open_index(path: &Path) -> Result<(Connection, PathBuf), IndexError> {
+  let not_an_index = || IndexError::NotAnIndex(path.to_path_buf());
+  let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(|_| not_an_index())?;
+
+  if meta(&conn, "version").as_deref() != Some(INDEX_VERSION) {
+    return Err(not_an_index());
+  }
+  let root = meta(&conn, "root").ok_or_else(not_an_index)?;
+
+  Ok((conn, PathBuf::from(root)))
+}
+
+/// Starts a scan from an index file built by [`IndexWriter`] (e.g. `scnr index`).
+/// Unchanged files are replayed from the index, new and modified ones are scanned again, all of them when the scan
+/// parameters are not the ones of the indexing.
+/// When the indexed input is not available anymore, all the contents are replayed.
+#[derive(Debug)]
+pub struct IndexPlugin;
+
+impl ScanPlugin for IndexPlugin {
+  fn options(&self) -> &'static [OptionSpec] {
+    const OPTIONS: &[OptionSpec] = &[OptionSpec::new(ROOT_OPTION, OptionKind::Text, "root of the indexed input, when it has moved")];
+    OPTIONS
+  }
+
+  fn can_start(&self, start_param: &str) -> bool {
+    let path = Path::new(start_param);
+    path.is_file() && open_index(path).is_ok()
+  }
+
+  #[tracing::instrument(level = "debug", err)]
+  fn start(&self, context: &ScanContext, start_param: &str) -> ScanPluginResult {
+    let (conn, indexed_root) = open_index(Path::new(start_param))?;
+    let root = context.options().get_str(ROOT_OPTION).map_or(indexed_root, PathBuf::from);
+
+    let mut sources = HashMap::new();
+    let mut sttmt = conn.prepare("SELECT path, size, mtime_ns FROM sources")?;
+    let mut rows = sttmt.query([])?;
+    while let Some(row) = rows.next()? {
+      sources.insert(row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?));
+    }
+
+    let mut nodes = conn.prepare("SELECT path, kind, content FROM nodes WHERE source = ?1 ORDER BY id")?;
+    // the metadata nodes are sent again by the walk of the input (with the `fs.metadata` option), not replayed
+    let mut replay = |source: &str, with_metadata: bool| -> ScanPluginResult {
+      let mut rows = nodes.query([source])?;
+      while let Some(row) = rows.next()? {
+        let path = ScanPath::parse(&row.get::<_, String>(0)?);
+        if !with_metadata
+          && path
+            .segments()
+            .last()
+            .is_some_and(|segment| segment.kind == SegmentKind::PluginChild && segment.name == METADATA_NODE_NAME)
+        {
+          continue;
+        }
+        let content = to_content(&row.get::<_, String>(1)?, row.get(2)?)?;
+        context.send_scanned_content(&path, content)?;
+      }
+      Ok(())
+    };
+
+    let same_params = is_replayable(meta(&conn, RUN_PARAMS_KEY).as_deref(), context.run_params());
+    if !root.exists() {
+      tracing::warn!("Indexed input `{}` is not available, contents are not checked for changes", root.display());
+      if !same_params {
+        tracing::warn!("The index was built with other scan parameters, its contents are replayed as they are");
+      }
+      let mut all_sources = sources.into_keys().collect::<Vec<_>>();
+      all_sources.sort();
+      return all_sources.iter().try_for_each(|source| replay(source, true));
+    }
+    if !same_params {
+      tracing::info!("The index was built with other scan parameters, all the files are scanned again");
+      sources.clear();
+    }
+
+    for_each_file(context, &root, |relative_path, path| {
+      let source = relative_path.to_string_lossy();
+      if sources
+        .get(source.as_ref())
+        .is_some_and(|stamp| file_stamp(path).is_ok_and(|current| &current == stamp))
+      {
+        replay(&source, false)
+      } else {
+        tracing::info!("`{source}` is new or changed since the indexing, scanning it");
+        Ok(context.recurse_file(&relative_path, path)?)
+      }
+    })
+  }
+}
+
+/// Whether an index built with the `indexed` scan parameters can be replayed by a scan with the `current` ones
+fn is_replayable(indexed: Option<&str>, current: Option<&str>) -> bool {
+  use serde_json::Value;
+  let parse = |params: Option<&str>| params.map(|params| serde_json::from_str(params).unwrap_or_else(|_| Value::from(params)));
+  match (parse(indexed), parse(current)) {
+    (Some(Value::Object(mut indexed)), Some(Value::Object(mut current)))
+      if indexed
+        .get(RUN_PARAMS_FILTERS)
+        .is_none_or(|filters| filters.as_array().is_some_and(Vec::is_empty)) =>
+    {
+      indexed.remove(RUN_PARAMS_FILTERS);
+      current.remove(RUN_PARAMS_FILTERS);
+      indexed == current
+    }
+    (indexed, current) => indexed == current,
+  }
+}
+
+fn to_content(kind: &str, data: Vec<u8>) -> Result<Content, IndexError> {
+  Ok(match kind {
+    "json" => Content::Json(serde_json::from_slice(&data)?),
+    "text" => Content::Text(String::from_utf8_lossy(&data).into_owned()),
+    "bin" => Content::Bytes(data),
+    kind => return Err(IndexError::UnknownKind(kind.to_string())),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use scnr_core::{
+    plugins::{json::JsonPlugin, text::TextPlugin, DefaultPluginPicker},
+    timestamps::{TimestampMode, TimestampNormalizer},
+    DateRepr,
+  };
+  use std::collections::BTreeMap;
+
+  fn picker() -> anyhow::Result<DefaultPluginPicker> {
+    Ok(
+      DefaultPluginPicker::builder()
+        .push_plugin("*.json", JsonPlugin)?
+        .push_plugin("*.txt", TextPlugin)?
+        .push_starter_plugin(Box::new(IndexPlugin))?
+        .build_with_defaults()?,
+    )
+  }
+
+  fn scan(start: &Path) -> anyhow::Result<BTreeMap<String, Content>> {
+    scan_with(Scanner::new(&start.display(), picker()?))
+  }
+
+  fn scan_with(scanner: Scanner) -> anyhow::Result<BTreeMap<String, Content>> {
+    let contents = scanner.get_all_oks()?;
+    Ok(
+      contents
+        .into_iter()
+        .map(|content| (content.path.to_string(), content.content))
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn replay_and_invalidation() -> anyhow::Result<()> {
+    let input = tempfile::tempdir()?;
+    std::fs::write(input.path().join("a.json"), r#"{"a": 1}"#)?;
+    std::fs::create_dir(input.path().join("logs"))?;
+    std::fs::write(input.path().join("logs/b.txt"), "hello")?;
+    std::fs::write(input.path().join("broken.json"), "{")?;
+
+    let index_dir = tempfile::tempdir()?;
+    let index_path = index_dir.path().join("case.scnr");
+    let mut writer = IndexWriter::create(&index_path, input.path())?;
+    for content in Scanner::new(&input.path().display(), picker()?).get_all()? {
+      match content {
+        Ok(content) => writer.add(&content)?,
+        Err(err) => writer.add_error(&err)?,
+      }
+    }
+    assert_eq!(writer.finish()?, (3, 2));
+    assert!(IndexPlugin.can_start(&index_path.display().to_string()));
+    assert!(!IndexPlugin.can_start(&input.path().join("a.json").display().to_string()));
+
+    let direct = scan(input.path())?;
+    assert_eq!(scan(&index_path)?, direct);
+
+    // the index is not modified, a changed file is scanned again each time
+    std::fs::write(input.path().join("logs/b.txt"), "hello world")?;
+    std::fs::write(input.path().join("c.txt"), "new")?;
+    let rescanned = scan(&index_path)?;
+    assert_eq!(rescanned.get("logs/b.txt"), Some(&Content::Text("hello world".into())));
+    assert_eq!(rescanned.get("c.txt"), Some(&Content::Text("new".into())));
+
+    // without the input, the index is replayed as is
+    drop(input);
+    assert_eq!(scan(&index_path)?, direct);
+
+    Ok(())
+  }
+
+  #[test]
+  fn filtered_files_and_run_params() -> anyhow::Result<()> {
+    let input = tempfile::tempdir()?;
+    let a_json = input.path().join("a.json");
+    std::fs::write(&a_json, r#"{"a": 1}"#)?;
+    std::fs::write(input.path().join("b.txt"), "hello")?;
+
+    let index_dir = tempfile::tempdir()?;
+    let index_path = index_dir.path().join("case.scnr");
+    let options = |run_params: &str| ScannerOptions { run_params: Some(run_params.into()), ..Default::default() };
+    let mut writer = IndexWriter::create(&index_path, input.path())?.with_run_params(Some("json only"));
+    let scanner = Scanner::new(&input.path().display(), picker()?)
+      .with_filter(filter::Glob::new("*.json")?)
+      .with_options(options("json only"));
+    for content in scanner.get_all_oks()? {
+      writer.add(&content)?;
+    }
+    // the filtered out file is not recorded, it is scanned by the next scans
+    assert_eq!(writer.finish()?, (1, 1));
+    let replayed = scan_with(Scanner::new(&index_path.display(), picker()?).with_options(options("json only")))?;
+    assert_eq!(replayed.keys().collect::<Vec<_>>(), ["a.json", "b.txt"]);
+
+    // same size and modification time: only other run params tell that the indexed content is stale
+    let mtime = std::fs::metadata(&a_json)?.modified()?;
+    std::fs::write(&a_json, r#"{"a": 2}"#)?;
+    std::fs::File::options().write(true).open(&a_json)?.set_modified(mtime)?;
+    let stale = scan_with(Scanner::new(&index_path.display(), picker()?).with_options(options("json only")))?;
+    assert_eq!(stale.get("a.json"), Some(&Content::Json(serde_json::json!({"a": 1}))));
+    let rescanned = scan_with(Scanner::new(&index_path.display(), picker()?).with_options(options("all")))?;
+    assert_eq!(rescanned.get("a.json"), Some(&Content::Json(serde_json::json!({"a": 2}))));
+
+    // the replaced timestamps are not replayed to a scan that keeps them
+    let replaced_path = index_dir.path().join("replaced.scnr");
+    let mut writer = IndexWriter::create(&replaced_path, input.path())?.with_run_params(Some(r#"{"timestamps":"replace"}"#));
+    let normalizer = TimestampNormalizer::new(TimestampMode::Replace).with_rule("a", Some(DateRepr::UnixSeconds))?;
+    let scanner = Scanner::new(&input.path().display(), picker()?)
+      .with_timestamps(normalizer)
+      .with_options(options(r#"{"timestamps":"replace"}"#));
+    for content in scanner.get_all_oks()? {
+      writer.add(&content)?;
+    }
+    writer.finish()?;
+    let replayed = scan_with(Scanner::new(&replaced_path.display(), picker()?).with_options(options(r#"{"timestamps":"replace"}"#)))?;
+    assert!(matches!(replayed.get("a.json"), Some(Content::Json(json)) if json["a"].is_string()));
+    let rescanned = scan_with(Scanner::new(&replaced_path.display(), picker()?).with_options(options(r#"{"timestamps":"off"}"#)))?;
+    assert_eq!(rescanned.get("a.json"), Some(&Content::Json(serde_json::json!({"a": 2}))));
+
+    Ok(())
+  }
+
+  #[test]
+  fn replayable_run_params() {
+    let params = |filters: &[&str], profile: &str| serde_json::json!({ RUN_PARAMS_FILTERS: filters, "profile": profile }).to_string();
+    assert!(is_replayable(None, None));
+    assert!(!is_replayable(None, Some(&params(&[], "standard"))));
+    assert!(is_replayable(Some(&params(&[], "standard")), Some(&params(&["*.plist"], "standard"))));
+    assert!(!is_replayable(Some(&params(&["*.json"], "standard")), Some(&params(&["*.plist"], "standard"))));
+    assert!(!is_replayable(Some(&params(&[], "standard")), Some(&params(&[], "sysdiagnose"))));
+    let timestamps = |mode: &str| serde_json::json!({ RUN_PARAMS_FILTERS: [], "timestamps": mode }).to_string();
+    assert!(is_replayable(Some(&timestamps("off")), Some(&timestamps("off"))));
+    assert!(!is_replayable(Some(&timestamps("replace")), Some(&timestamps("off"))));
+  }
+}
@@ -7,23 +7,26 @@ use serde_json::{Map, Number, Value};
 use std::io::Write;
 use tempfile::NamedTempFile;
 
+pub mod export;
+pub mod index;
 mod sqlite_ext;
-use sqlite_ext::SqliteExt;
+use sqlite_ext::{quote_identifier, SqliteExt};
+
+/// Name of the child node holding the database header, the DDL and the columns of every table and view
+pub const SCHEMA_NODE_NAME: &str = "__schema";
+
+/// Plugin option: maximum number of rows per json array, tables are not split when it is unset or 0
+pub const JSON_ARRAY_LIMIT_OPTION: &str = "sqlite.json_array_limit";
 
 #[derive(Debug)]
-pub struct SqlitePlugin {
-  json_limit: usize,
-}
+pub struct SqlitePlugin;
 
-impl SqlitePlugin {
-  #[must_use]
-  pub fn new(options: &ScannerOptions) -> Self {
-    let json_limit = if options.split_tables_output { options.json_array_limit } else { usize::MAX };
-    Self { json_limit }
+impl ScanPlugin for SqlitePlugin {
+  fn options(&self) -> &'static [OptionSpec] {
+    const OPTIONS: &[OptionSpec] = &[OptionSpec::new(JSON_ARRAY_LIMIT_OPTION, OptionKind::Count, "maximum number of rows per json array")];
+    OPTIONS
   }
-}
 
-impl ScanPlugin for SqlitePlugin {
   #[tracing::instrument(level = "debug", err)]
   fn scan(&self, context: &ScanContext, mut reader: ScanReader<'_>) -> ScanPluginResult {
     // todo: could be better with https://crates.io/crates/memfd ?
@@ -32,53 +35,92 @@ impl ScanPlugin for SqlitePlugin {
     std::io::copy(&mut reader, &mut tmp_file)?;
     tmp_file.flush()?;
 
+    let json_limit = match context.options().get::<usize>(JSON_ARRAY_LIMIT_OPTION)? {
+      None | Some(0) => usize::MAX,
+      Some(limit) => limit,
+    };
+
     let conn = Connection::open_with_flags(&tmp_file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
 
     let table_names = conn.get_table_names()?;
+    let view_names = conn.get_view_names()?;
 
-    for table_name in table_names {
-      let mut sttmt = conn.prepare(&format!("SELECT * FROM '{table_name}'"))?;
-      let mut rows = sttmt.query(params![])?;
+    // a broken view (missing table or function...) does not prevent reading the other tables
+    for table_name in table_names.iter().chain(&view_names) {
+      if let Err(err) = send_table(context, &conn, table_name, json_limit) {
+        context.send_child_error(table_name, format!("{err:#}"))?;
+      }
+    }
 
-      let mut big_json: Vec<Value> = vec![];
+    send_schema(context, &conn)?;
 
-      let send_big_json = |json: Vec<Value>, already_sent_this_table: bool| {
-        if json.is_empty() && already_sent_this_table {
-          return Ok(());
-        }
-        tracing::debug!("Sending json array of {} elements for table {}", json.len(), &table_name);
-        let json_array = Value::Array(json);
-        context.send_child_content(Content::Json(json_array), &table_name)?;
-        ScanPluginResult::Ok(())
-      };
+    drop(tmp_file);
 
-      let columns = conn.get_columns_infos(&table_name)?;
+    Ok(())
+  }
+}
 
-      let mut already_sent_this_table = false;
+/// Sends the content of a table (or a view) as json arrays, split in chunks of `json_limit` rows
+fn send_table(context: &ScanContext, conn: &Connection, table_name: &str, json_limit: usize) -> ScanPluginResult {
+  let mut sttmt = conn.prepare(&format!("SELECT * FROM {}", quote_identifier(table_name)))?;
+  let mut rows = sttmt.query(params![])?;
 
-      while let Some(row) = rows.next()? {
-        let mut json = Map::new();
-        for (i, column) in columns.iter().map(|c| &c.name).enumerate() {
-          let value = row.get::<_, types::Value>(i)?;
-          json.insert(column.clone(), sqlite_to_json(value, context.bin_repr));
-        }
+  let mut big_json: Vec<Value> = vec![];
 
-        big_json.push(Value::Object(json));
+  let send_big_json = |json: Vec<Value>, already_sent_this_table: bool| {
+    if json.is_empty() && already_sent_this_table {
+      return Ok(());
+    }
+    tracing::debug!("Sending json array of {} elements for table {}", json.len(), table_name);
+    let json_array = Value::Array(json);
+    context.send_child_content(Content::Json(json_array), table_name)?;
+    ScanPluginResult::Ok(())
+  };
 
-        if big_json.len() >= self.json_limit {
-          send_big_json(big_json, already_sent_this_table)?;
-          big_json = vec![];
-          already_sent_this_table = true;
-        }
-      }
+  let columns = conn.get_columns_infos(table_name)?;
 
+  let mut already_sent_this_table = false;
+
+  while let Some(row) = rows.next()? {
+    let mut json = Map::new();
+    for (i, column) in columns.iter().map(|c| &c.name).enumerate() {
+      let value = row.get::<_, types::Value>(i)?;
+      json.insert(column.clone(), sqlite_to_json(value, context.bin_repr));
+    }
+
+    big_json.push(Value::Object(json));
+
+    if big_json.len() >= json_limit {
       send_big_json(big_json, already_sent_this_table)?;
+      big_json = vec![];
+      already_sent_this_table = true;
     }
+  }
 
-    drop(tmp_file);
+  send_big_json(big_json, already_sent_this_table)
+}
 
-    Ok(())
+/// Sends the `__schema` child node: database header, DDL of all entries and columns infos of tables and views
+fn send_schema(context: &ScanContext, conn: &Connection) -> ScanPluginResult {
+  let header = serde_json::to_value(conn.get_header()?)?;
+
+  let mut entries = vec![];
+  for entry in conn.get_schema_entries()? {
+    let columns = match entry.entry_type.as_str() {
+      // the columns of a broken view cannot be read, its DDL is still sent
+      "table" | "view" => conn.get_columns_infos(&entry.name).ok(),
+      _ => None,
+    };
+    let mut entry_json = serde_json::to_value(&entry)?;
+    if let (Some(columns), Value::Object(map)) = (columns, &mut entry_json) {
+      map.insert("columns".to_string(), serde_json::to_value(columns)?);
+    }
+    entries.push(entry_json);
   }
+
+  let schema = serde_json::json!({ "header": header, "entries": entries });
+  context.send_child_content(Content::Json(schema), SCHEMA_NODE_NAME)?;
+  Ok(())
 }
 
 fn sqlite_to_json(sql: types::Value, bin_repr: BinRepr) -> serde_json::Value {
@@ -98,21 +140,19 @@ mod tests {
 
   use super::*;
   use crate::{
-    tests_helpers::{exec_plugin_scan, get_samples_path},
+    tests_helpers::{exec_plugin_scan, exec_plugin_scan_with_options, get_samples_path},
     ScanReader,
   };
 
-  fn get_json_contents(
-    sample_path: &str,
-    split_tables_output: bool,
-    json_array_limit: usize,
-  ) -> anyhow::Result<Vec<(PathBuf, serde_json::Value)>> {
+  fn get_json_contents(sample_path: &str, json_array_limit: Option<usize>) -> anyhow::Result<Vec<(PathBuf, serde_json::Value)>> {
     let samples_dir = get_samples_path()?;
     let mut file = std::fs::File::open(format!("{samples_dir}/{sample_path}"))?;
 
-    let plugin = SqlitePlugin::new(&ScannerOptions { split_tables_output, json_array_limit });
+    let options = json_array_limit
+      .map(|limit| PluginOptions::new().with(JSON_ARRAY_LIMIT_OPTION, limit.to_string()))
+      .unwrap_or_default();
 
-    let results = exec_plugin_scan(ScanReader::read_seek(&mut file), &plugin)?;
+    let results = exec_plugin_scan_with_options(ScanReader::read_seek(&mut file), &SqlitePlugin, options)?;
 
     let mut json_contents = vec![];
     for result in results {
@@ -128,20 +168,21 @@ mod tests {
 
   #[test]
   fn test() -> anyhow::Result<()> {
-    let jsons = get_json_contents("sakila_country_only.db", false, 0)?;
+    let jsons = get_json_contents("sakila_country_only.db", None)?;
 
-    assert_eq!(jsons.len(), 1);
+    assert_eq!(jsons.len(), 2);
     assert_eq!(jsons[0].0, PathBuf::from("country"));
     assert_eq!(jsons[0].1.as_array().unwrap().len(), 109);
+    assert_eq!(jsons[1].0, PathBuf::from(SCHEMA_NODE_NAME));
 
     Ok(())
   }
 
   #[test]
   fn test_chunks_of_1() -> anyhow::Result<()> {
-    let jsons = get_json_contents("sakila_country_only.db", true, 1)?;
+    let jsons = get_json_contents("sakila_country_only.db", Some(1))?;
 
-    assert_eq!(jsons.len(), 109);
+    assert_eq!(jsons.len(), 110);
     assert_eq!(jsons[0].0, PathBuf::from("country"));
     assert_eq!(jsons[0].1.as_array().unwrap().len(), 1);
 
@@ -150,12 +191,13 @@ mod tests {
 
   #[test]
   fn test_read_all_tables() -> anyhow::Result<()> {
-    let jsons = get_json_contents("sakila_full.db", true, 5000)?;
+    let jsons = get_json_contents("sakila_full.db", Some(5000))?;
 
-    assert_eq!(jsons.len(), 23);
+    assert_eq!(jsons.len(), 30);
 
     let real_table_and_counts = jsons
       .into_iter()
+      .filter(|(path, _)| path != &PathBuf::from(SCHEMA_NODE_NAME))
       .map(|(path, json)| (path, json.as_array().unwrap().len()))
       .collect::<Vec<_>>();
 
@@ -183,6 +225,12 @@ mod tests {
       ("rental", 1044),
       ("staff", 2),
       ("store", 2),
+      ("customer_list", 599),
+      ("film_list", 5000),
+      ("film_list", 462),
+      ("sales_by_film_category", 16),
+      ("sales_by_store", 2),
+      ("staff_list", 2),
     ]
     .map(|(p, c)| (PathBuf::from(p), c))
     .into_iter()
@@ -193,13 +241,71 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn test_schema() -> anyhow::Result<()> {
+    let jsons = get_json_contents("sakila_country_only.db", None)?;
+
+    let (_, schema) = jsons
+      .iter()
+      .find(|(path, _)| path == &PathBuf::from(SCHEMA_NODE_NAME))
+      .expect("no schema node");
+
+    assert_eq!(schema["header"]["text_encoding"], "UTF-8");
+    assert!(schema["header"]["page_size"].as_i64().unwrap() > 0);
+
+    let entries = schema["entries"].as_array().unwrap();
+    let types = entries
+      .iter()
+      .map(|e| (e["type"].as_str().unwrap(), e["name"].as_str().unwrap()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      types,
+      [("index", "sqlite_autoindex_country_1"), ("table", "country"), ("trigger", "country_trigger_ai"), ("trigger", "country_trigger_au")]
+    );
+
+    let country = &entries[1];
+    assert!(country["sql"].as_str().unwrap().starts_with("CREATE TABLE"));
+    let columns = country["columns"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|c| c["name"].as_str().unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(columns, ["country_id", "country", "last_update"]);
+    assert!(entries[0].get("columns").is_none());
+
+    Ok(())
+  }
+
+  #[test]
+  fn broken_view() -> anyhow::Result<()> {
+    let file = NamedTempFile::new()?;
+    let conn = Connection::open(file.path())?;
+    conn.execute_batch(
+      "CREATE TABLE a (id INTEGER); CREATE TABLE gone (id INTEGER); CREATE VIEW broken AS SELECT * FROM gone;
+       CREATE TABLE b (id INTEGER); INSERT INTO b VALUES (1); DROP TABLE gone;",
+    )?;
+    drop(conn);
+
+    let results = exec_plugin_scan(ScanReader::read_seek(&mut std::fs::File::open(file.path())?), &SqlitePlugin)?;
+    let paths = results
+      .iter()
+      .map(|result| match result {
+        Ok(content) => content.path.to_string(),
+        Err(err) => format!("error {}", err.path().map(ToString::to_string).unwrap_or_default()),
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(paths, ["#a", "#b", "error #broken", "#__schema"]);
+
+    Ok(())
+  }
+
   #[test]
   fn failing_test() -> anyhow::Result<()> {
     let samples_dir = get_samples_path()?;
     let mut file = std::fs::File::open(format!("{samples_dir}/w.tar.gz"))?;
 
-    let plugin = SqlitePlugin::new(&ScannerOptions::default());
-    let result = exec_plugin_scan(ScanReader::read_seek(&mut file), &plugin);
+    let result = exec_plugin_scan(ScanReader::read_seek(&mut file), &SqlitePlugin);
     assert!(result.is_err());
 
     Ok(())
@@ -1,5 +1,6 @@
 // reexport all sqlite so no more need to `use rusqlite` in other crates
 pub use rusqlite::*;
+use serde::Serialize;
 
 /// Table columns informations from the sql command `PRAGMA table_info('<TABLE_NAME>')`
 ///
@@ -14,7 +15,7 @@ pub use rusqlite::*;
 /// │     4 │ `NodeID`       │ BIGINT  │ false   │              │ false   │
 /// │     5 │ `RootNodeID`   │ BIGINT  │ false   │              │ false   │
 ///
-#[allow(dead_code)]
+#[derive(Debug, Serialize)]
 pub struct TableFieldInfos {
   pub column_id: i32,
   pub name: String,
@@ -24,29 +25,57 @@ pub struct TableFieldInfos {
   pub primary_key: bool,
 }
 
+/// One entry of the `sqlite_master` table (table, view, index or trigger)
+#[derive(Debug, Serialize)]
+pub struct SchemaEntry {
+  #[serde(rename = "type")]
+  pub entry_type: String,
+  pub name: String,
+  pub table_name: String,
+  /// The DDL statement, internal objects (like auto indexes) don't have one
+  pub sql: Option<String>,
+}
+
+/// Database header fields, read through pragmas
+#[derive(Debug, Serialize)]
+pub struct DatabaseHeader {
+  pub page_size: i64,
+  pub page_count: i64,
+  pub user_version: i64,
+  pub application_id: i64,
+  pub text_encoding: String,
+}
+
 pub trait SqliteExt {
   /// Returns the list of table names in the database
   fn get_table_names(&self) -> Result<Vec<String>>;
 
-  /// Returns the list of table field names in a table
-  /// /!\ the `table_name` parameter is sensitive to SQL INJECTION ! (won't fix it - be sure what you pass in)
+  /// Returns the list of view names in the database
+  fn get_view_names(&self) -> Result<Vec<String>>;
+
+  /// Returns the list of table (or view) field names
   fn get_columns_infos(&self, table_name: &str) -> Result<Vec<TableFieldInfos>>;
+
+  /// Returns all the entries of the `sqlite_master` table
+  fn get_schema_entries(&self) -> Result<Vec<SchemaEntry>>;
+
+  /// Returns the database header infos
+  fn get_header(&self) -> Result<DatabaseHeader>;
 }
 
 impl SqliteExt for Connection {
   fn get_table_names(&self) -> Result<Vec<String>> {
-    let mut stmt = self.prepare("SELECT name FROM sqlite_master WHERE type='table' ORDER BY name;")?;
-    let mut table_names = vec![];
-    let names_iter = stmt.query_map([], |row| row.get(0))?;
-    for name in names_iter {
-      table_names.push(name?);
-    }
-    Ok(table_names)
+    get_master_names(self, "table")
+  }
+
+  fn get_view_names(&self) -> Result<Vec<String>> {
+    get_master_names(self, "view")
   }
 
   fn get_columns_infos(&self, table_name: &str) -> Result<Vec<TableFieldInfos>> {
-    let mut stmt = self.prepare(&format!("PRAGMA table_info('{table_name}');"))?;
-    let columns_info = stmt.query_map([], |row| {
+    // the table valued pragma function allows to bind the table name instead of formatting it in the query
+    let mut stmt = self.prepare("SELECT cid, name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1);")?;
+    let columns_info = stmt.query_map([table_name], |row| {
       let infos = TableFieldInfos {
         column_id: row.get(0)?,
         name: row.get(1)?,
@@ -60,6 +89,43 @@ impl SqliteExt for Connection {
 
     columns_info.into_iter().collect()
   }
+
+  fn get_schema_entries(&self) -> Result<Vec<SchemaEntry>> {
+    let mut stmt = self.prepare("SELECT type, name, tbl_name, sql FROM sqlite_master ORDER BY type, name;")?;
+    let entries = stmt.query_map([], |row| {
+      let entry = SchemaEntry { entry_type: row.get(0)?, name: row.get(1)?, table_name: row.get(2)?, sql: row.get(3)? };
+      Ok(entry)
+    })?;
+
+    entries.into_iter().collect()
+  }
+
+  fn get_header(&self) -> Result<DatabaseHeader> {
+    let pragma_i64 = |name: &str| self.pragma_query_value(None, name, |row| row.get::<_, i64>(0));
+    Ok(DatabaseHeader {
+      page_size: pragma_i64("page_size")?,
+      page_count: pragma_i64("page_count")?,
+      user_version: pragma_i64("user_version")?,
+      application_id: pragma_i64("application_id")?,
+      text_encoding: self.pragma_query_value(None, "encoding", |row| row.get(0))?,
+    })
+  }
+}
+
+fn get_master_names(conn: &Connection, entry_type: &str) -> Result<Vec<String>> {
+  let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type=?1 ORDER BY name;")?;
+  let mut names = vec![];
+  let names_iter = stmt.query_map([entry_type], |row| row.get(0))?;
+  for name in names_iter {
+    names.push(name?);
+  }
+  Ok(names)
+}
+
+/// Quotes an identifier (table or column name) so it can be safely formatted in a query
+#[must_use]
+pub fn quote_identifier(name: &str) -> String {
+  format!("\"{}\"", name.replace('"', "\"\""))
 }
 
 pub struct DisplayableValue(types::Value);
@@ -110,4 +176,22 @@ mod tests {
     assert_eq!(table_names.len(), 0);
     Ok(())
   }
+
+  #[test]
+  fn quoted_names() -> anyhow::Result<()> {
+    let conn = Connection::open_in_memory().unwrap();
+    let table_name = r#"it's a "table""#;
+    conn.execute(&format!("CREATE TABLE {} (\"col'1\" INTEGER NOT NULL)", quote_identifier(table_name)), [])?;
+    conn.execute(&format!("CREATE VIEW {} AS SELECT 1 AS one", quote_identifier("a view")), [])?;
+
+    assert_eq!(conn.get_table_names()?, vec![table_name.to_string()]);
+    assert_eq!(conn.get_view_names()?, vec!["a view".to_string()]);
+
+    let columns = conn.get_columns_infos(table_name)?;
+    assert_eq!(columns.len(), 1);
+    assert_eq!(columns[0].name, "col'1");
+    assert!(columns[0].not_null);
+
+    Ok(())
+  }
 }